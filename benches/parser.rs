@@ -0,0 +1,30 @@
+//! Parser benchmarks, on the same 1000-step document as the stress tests
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use usr_bin_sysadmin::parser::SysadminParser;
+
+/// A runbook with one section, some text and one step per step number
+fn stress_document(steps: usize) -> String {
+    let mut content = String::from("# Large Document Test\n\n");
+    for i in 1..=steps {
+        content.push_str(&format!(
+            "## Step {}\n\nSome text here.\n\n```bash\ncommand {}\n```\n\n",
+            i, i
+        ));
+    }
+    content
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let content = stress_document(1000);
+
+    c.bench_function("parse 1000 steps", |b| {
+        b.iter(|| SysadminParser::parse(black_box(&content)).unwrap())
+    });
+    c.bench_function("iterate 1000 steps", |b| {
+        b.iter(|| SysadminParser::steps(black_box(&content)).count())
+    });
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
@@ -1,5 +1,8 @@
-use clap::{Parser, Subcommand};
+use clap::builder::PossibleValuesParser;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
+use usr_bin_sysadmin::executor::DEFAULT_SUDO_CMD;
+use usr_bin_sysadmin::ui::theme::THEME_NAMES;
 
 #[derive(Parser, Debug)]
 #[command(name = "sysadmin")]
@@ -11,31 +14,251 @@ pub struct Cli {
 
     /// Path to the .sysadmin file
     pub file: Option<PathBuf>,
+
+    /// Color theme (defaults to the config file, or mono when NO_COLOR is set)
+    #[arg(long, global = true, value_parser = PossibleValuesParser::new(THEME_NAMES))]
+    pub theme: Option<String>,
+
+    /// Run 4-space indented code blocks as steps in this language
+    #[arg(long, global = true, value_name = "LANG")]
+    pub allow_indented: Option<String>,
+
+    /// Don't print warnings about the runbook, e.g. a shell mismatch
+    #[arg(long, global = true)]
+    pub no_warnings: bool,
 }
 
+// Parsed once per process, so the size of `Run` doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Execute a .sysadmin file interactively (default)
     Run {
-        /// Path to the .sysadmin file
+        /// Path to the .sysadmin file or a directory of them, or `-` to read it from stdin
         file: PathBuf,
+
+        /// Set a variable for ${VAR} substitution in code blocks (repeatable)
+        #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_key_val)]
+        vars: Vec<(String, String)>,
+
+        /// Set an environment variable for every step (repeatable)
+        #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_key_val)]
+        env: Vec<(String, String)>,
+
+        /// Error on undefined variables instead of leaving them as-is
+        #[arg(long)]
+        strict_vars: bool,
+
+        /// Append a JSON line per executed step to this audit log
+        #[arg(long, value_name = "PATH")]
+        log: Option<PathBuf>,
+
+        /// Save a plain-text copy of everything shown to this file
+        #[arg(long, value_name = "PATH")]
+        transcript: Option<PathBuf>,
+
+        /// Resume from this step number (1-based, as shown by dry-run)
+        #[arg(long, value_name = "STEP")]
+        from: Option<usize>,
+
+        /// Run only this step number and exit
+        #[arg(long, value_name = "STEP", conflicts_with = "from")]
+        only: Option<usize>,
+
+        /// Start at the first step of the section with this id (its slugified header)
+        #[arg(long, value_name = "ID", conflicts_with_all = ["from", "only"])]
+        from_section: Option<String>,
+
+        /// Extra substring marking a step as dangerous (repeatable)
+        #[arg(long = "danger-pattern", value_name = "PATTERN")]
+        danger_patterns: Vec<String>,
+
+        /// Run each step directly instead of dropping to a shell
+        #[arg(long, overrides_with = "no_auto_run")]
+        auto_run: bool,
+
+        /// Drop to a shell for each step, even if the config file sets `auto_run`
+        #[arg(long, overrides_with = "auto_run")]
+        no_auto_run: bool,
+
+        /// Kill directly-run steps after this many seconds
+        #[arg(long, value_name = "SECS")]
+        timeout: Option<u64>,
+
+        /// Run every step without prompts, stopping at the first failure
+        #[arg(long)]
+        ci: bool,
+
+        /// With --ci, keep running after a step fails and summarize at the end
+        #[arg(long, requires = "ci")]
+        continue_on_error: bool,
+
+        /// With --ci, write a JSON report of every step's outcome to this file
+        #[arg(long, value_name = "PATH", requires = "ci")]
+        report: Option<PathBuf>,
+
+        /// With --ci, show output taller than the terminal through $PAGER (or less -R)
+        #[arg(long, requires = "ci")]
+        pager: bool,
+
+        /// Ask before entering each `#` section, or each section down to header LEVEL
+        #[arg(
+            long,
+            value_name = "LEVEL",
+            num_args = 0..=1,
+            default_missing_value = "1",
+            conflicts_with = "ci"
+        )]
+        section_gate: Option<u32>,
+
+        /// Only run steps tagged with TAG via `tags=` (repeatable)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
+        /// Skip steps tagged with TAG (repeatable)
+        #[arg(long = "skip-tag", value_name = "TAG")]
+        skip_tags: Vec<String>,
+
+        /// Start even if tools listed under `requires` are missing
+        #[arg(long)]
+        skip_checks: bool,
+
+        /// Command to run `{sudo}` steps under
+        #[arg(long, value_name = "CMD", default_value = DEFAULT_SUDO_CMD)]
+        sudo_cmd: String,
+
+        /// Prepend `set -euo pipefail` to directly-run bash/sh steps
+        #[arg(long)]
+        strict_shell: bool,
+
+        /// Shell to drop into and run bash/sh steps with, over the frontmatter `shell`
+        #[arg(long, value_name = "SHELL")]
+        shell: Option<String>,
     },
 
     /// Execute with TUI interface
     Tui {
         /// Path to the .sysadmin file
         file: PathBuf,
+
+        /// Export an environment variable into dropped-to shells (repeatable)
+        #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_key_val)]
+        env: Vec<(String, String)>,
+
+        /// Extra substring marking a step as dangerous (repeatable)
+        #[arg(long = "danger-pattern", value_name = "PATTERN")]
+        danger_patterns: Vec<String>,
+
+        /// Shell to drop into and run bash/sh steps with, over the frontmatter `shell`
+        #[arg(long, value_name = "SHELL")]
+        shell: Option<String>,
+
+        /// Quit when a shell exits after Ctrl-C, instead of returning to the TUI
+        #[arg(long)]
+        exit_on_interrupt: bool,
+
+        /// Kill steps run with `x` after this many seconds
+        #[arg(long, value_name = "SECS")]
+        timeout: Option<u64>,
+
+        /// Set a variable for ${VAR} substitution in steps run with `x` (repeatable)
+        #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_key_val)]
+        vars: Vec<(String, String)>,
+
+        /// Command to run `{sudo}` steps under
+        #[arg(long, value_name = "CMD", default_value = DEFAULT_SUDO_CMD)]
+        sudo_cmd: String,
     },
 
     /// Display all steps without executing (dry-run)
     DryRun {
+        /// Path to the .sysadmin file, or `-` to read it from stdin
+        file: PathBuf,
+
+        /// Print the parsed document as pretty-printed JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Command that `{sudo}` steps would run under
+        #[arg(long, value_name = "CMD", default_value = DEFAULT_SUDO_CMD)]
+        sudo_cmd: String,
+    },
+
+    /// Print a one-line summary of each step
+    List {
         /// Path to the .sysadmin file
         file: PathBuf,
     },
 
+    /// Summarize the runbook's size: sections, steps, code and reading time
+    Stats {
+        /// Path to the .sysadmin file, or `-` to read it from stdin
+        file: PathBuf,
+    },
+
     /// View the file as formatted documentation
     View {
+        /// Path to the .sysadmin file, or `-` to read it from stdin
+        file: PathBuf,
+
+        /// Print the file as-is instead of rendering it
+        #[arg(long)]
+        raw: bool,
+
+        /// Render again whenever the file changes, until interrupted
+        #[arg(long, conflicts_with = "raw")]
+        watch: bool,
+    },
+
+    /// Check a .sysadmin file for structural problems without running it
+    Validate {
         /// Path to the .sysadmin file
         file: PathBuf,
     },
+
+    /// Rewrite the runbook with normalized markdown formatting
+    Fmt {
+        /// Path to the .sysadmin file
+        file: PathBuf,
+
+        /// Don't write anything; exit non-zero if the file isn't formatted
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Export the runbook's steps to another format
+    Export {
+        /// Path to the .sysadmin file
+        file: PathBuf,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Sh)]
+        format: ExportFormat,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Formats supported by the export command
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A flat, executable shell script
+    Sh,
+    /// Normalized markdown with a `step-N` anchor before each step
+    Md,
+    /// A self-contained HTML page, with code ready for client-side highlighting
+    Html,
+}
+
+/// Parse a `KEY=VALUE` argument
+fn parse_key_val(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", s))?;
+    if key.is_empty() {
+        return Err(format!("empty key in '{}'", s));
+    }
+    Ok((key.to_string(), value.to_string()))
 }
@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use directories::BaseDirs;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Persistent user defaults from `~/.config/sysadmin/config.toml`
+///
+/// Every field is optional. Precedence is CLI flags, then this file, then the
+/// built-in defaults.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Shell to drop into for each step, instead of `$SHELL`
+    pub shell: Option<String>,
+    /// Name of the color theme to use
+    pub theme: Option<String>,
+    /// Extra substrings that mark a step as dangerous
+    pub danger_patterns: Vec<String>,
+    /// Kill directly-run steps after this many seconds
+    pub timeout: Option<u64>,
+    /// Run each step directly instead of dropping to a shell
+    pub auto_run: bool,
+    /// Message shown before dropping to a shell, instead of the built-in one
+    pub shell_prompt_message: Option<String>,
+    /// Program and arguments to run each fence language with, e.g.
+    /// `python = ["python3", "-u"]`
+    pub interpreters: HashMap<String, Vec<String>>,
+}
+
+impl Config {
+    /// Where the config file lives, if a home directory can be found
+    pub fn default_path() -> Option<PathBuf> {
+        BaseDirs::new().map(|dirs| dirs.config_dir().join("sysadmin").join("config.toml"))
+    }
+
+    /// Load the config from the default path
+    ///
+    /// A missing file is not an error and yields the built-in defaults.
+    pub fn load() -> Result<Self> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Load the config from a specific file
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config: {}", path.display()))?;
+        Self::from_toml(&content)
+            .with_context(|| format!("Invalid config file: {}", path.display()))
+    }
+
+    /// Parse a config from TOML text
+    pub fn from_toml(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    /// The step timeout as a duration
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_config_is_default() {
+        assert_eq!(Config::from_toml("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_config_fields() {
+        let config = Config::from_toml(
+            r#"
+shell = "/bin/zsh"
+theme = "light"
+danger_patterns = ["mkfs", "dd if="]
+timeout = 30
+auto_run = true
+shell_prompt_message = "Step {step}: run it, then exit"
+
+[interpreters]
+python = ["python3", "-u"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.shell.as_deref(), Some("/bin/zsh"));
+        assert_eq!(config.theme.as_deref(), Some("light"));
+        assert_eq!(config.danger_patterns, vec!["mkfs", "dd if="]);
+        assert_eq!(config.timeout(), Some(Duration::from_secs(30)));
+        assert!(config.auto_run);
+        assert_eq!(
+            config.shell_prompt_message.as_deref(),
+            Some("Step {step}: run it, then exit")
+        );
+        assert_eq!(config.interpreters["python"], vec!["python3", "-u"]);
+    }
+
+    #[test]
+    fn test_config_rejects_wrong_types() {
+        assert!(Config::from_toml("timeout = \"soon\"").is_err());
+    }
+}
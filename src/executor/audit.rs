@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::model::Redactor;
+
+/// One executed step, as recorded in the audit log
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEntry {
+    /// When the step finished, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// 1-based step number
+    pub step: usize,
+    /// Header of the section the step belongs to
+    pub section: Option<String>,
+    /// Language of the code block
+    pub language: String,
+    /// The command content as shown to the operator
+    pub content: String,
+    /// Exit code of the shell or process, if it exited normally
+    pub exit_code: Option<i32>,
+    /// How long the step took
+    pub duration_ms: u128,
+    /// The operator skipped the step without running anything
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+}
+
+/// Append-only JSONL log of executed steps
+pub struct AuditLog {
+    file: File,
+    /// Masks secrets in each entry's content
+    redactor: Redactor,
+}
+
+impl AuditLog {
+    /// Open (or create) the log at `path` in append mode
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open audit log: {}", path.display()))?;
+        Ok(Self {
+            file,
+            redactor: Redactor::default(),
+        })
+    }
+
+    /// Mask secrets in step content before it is written
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// Append one entry as a single JSON line
+    pub fn record(&mut self, mut entry: AuditEntry) -> Result<()> {
+        entry.content = self.redactor.redact(&entry.content).into_owned();
+        let line = serde_json::to_string(&entry).context("Failed to serialize audit entry")?;
+        writeln!(self.file, "{}", line).context("Failed to write audit log")?;
+        self.file.flush().context("Failed to write audit log")?;
+        Ok(())
+    }
+}
+
+/// Current time in seconds since the Unix epoch
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,461 @@
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::audit::{unix_timestamp, AuditEntry, AuditLog};
+use super::pager::{print_errors, print_output, show_output};
+use super::process::{
+    check_undefined_variables, run_code, run_code_capturing, run_code_collecting,
+    run_code_transcribed, strict_script, DEFAULT_SUDO_CMD,
+};
+use super::report::{ExecutionReport, StepResult};
+use super::requirements::check_document_requirements;
+use super::selection::{StepSelection, TagFilter};
+use crate::model::{next_retry_delay, output_matches, CodeBlock, Document, Redactor};
+use crate::ui::{Renderer, Theme};
+
+/// Runs every step directly, with no prompts, stopping at the first failure
+///
+/// This is the non-interactive path used by `run --ci`. With
+/// [`BatchExecutor::with_continue_on_error`] it runs every step regardless.
+pub struct BatchExecutor {
+    renderer: Renderer,
+    /// Maximum time a step may take before it is killed
+    timeout: Option<Duration>,
+    /// Values for `${VAR}` substitution in code blocks
+    vars: HashMap<String, String>,
+    /// Environment variables set for every executed step
+    env: HashMap<String, String>,
+    /// Fail on variables that can't be resolved instead of leaving them intact
+    strict_vars: bool,
+    /// Where to record each executed step, if anywhere
+    audit_log: Option<AuditLog>,
+    /// Which steps to execute
+    selection: StepSelection,
+    /// Which steps to execute, by tag
+    tag_filter: TagFilter,
+    /// Keep going after a step fails instead of stopping
+    continue_on_error: bool,
+    /// Don't verify the document's `requires` tools before starting
+    skip_checks: bool,
+    /// Command that `{sudo}` steps are run under
+    sudo_cmd: String,
+    /// Show output taller than the terminal through a pager
+    pager: bool,
+    /// Masks secrets in step output before it is shown
+    redactor: Redactor,
+    /// Make shell steps abort on the first failed command or unset variable
+    strict_shell: bool,
+    /// Commands that replace the built-in interpreter for a fence language
+    interpreters: HashMap<String, Vec<String>>,
+    /// Steps that succeeded in an earlier, interrupted run
+    succeeded_steps: Vec<usize>,
+}
+
+impl BatchExecutor {
+    pub fn new() -> Self {
+        Self {
+            renderer: Renderer::new(),
+            timeout: None,
+            vars: HashMap::new(),
+            env: HashMap::new(),
+            strict_vars: false,
+            audit_log: None,
+            selection: StepSelection::All,
+            tag_filter: TagFilter::default(),
+            skip_checks: false,
+            continue_on_error: false,
+            sudo_cmd: DEFAULT_SUDO_CMD.to_string(),
+            pager: false,
+            redactor: Redactor::default(),
+            strict_shell: false,
+            interpreters: HashMap::new(),
+            succeeded_steps: Vec::new(),
+        }
+    }
+
+    /// Kill steps that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Substitute these variables into code blocks before running
+    pub fn with_vars(mut self, vars: HashMap<String, String>) -> Self {
+        self.vars = vars;
+        self
+    }
+
+    /// Set these environment variables for every executed step
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Treat references to undefined variables as an error
+    pub fn with_strict_vars(mut self, strict: bool) -> Self {
+        self.strict_vars = strict;
+        self
+    }
+
+    /// Render with these colors instead of the default dark theme
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.renderer = self.renderer.with_theme(theme);
+        self
+    }
+
+    /// Copy everything shown on the terminal, minus colors, to `file`
+    pub fn with_transcript(mut self, file: File) -> Self {
+        self.renderer = self.renderer.with_transcript(file);
+        self
+    }
+
+    /// Record every executed step to an audit log
+    pub fn with_audit_log(mut self, log: AuditLog) -> Self {
+        self.audit_log = Some(log);
+        self
+    }
+
+    /// Only execute steps whose tags pass `filter`
+    pub fn with_tag_filter(mut self, filter: TagFilter) -> Self {
+        self.tag_filter = filter;
+        self
+    }
+
+    /// Start without checking that the document's required tools are installed
+    pub fn with_skip_checks(mut self, skip: bool) -> Self {
+        self.skip_checks = skip;
+        self
+    }
+
+    /// Run `{sudo}` steps under this command (e.g. `doas`) instead of `sudo`
+    pub fn with_sudo_cmd(mut self, sudo_cmd: impl Into<String>) -> Self {
+        self.sudo_cmd = sudo_cmd.into();
+        self
+    }
+
+    /// Run steps in these fence languages with the given program and arguments
+    pub fn with_interpreters(mut self, interpreters: HashMap<String, Vec<String>>) -> Self {
+        self.interpreters = interpreters;
+        self
+    }
+
+    /// Prepend `set -euo pipefail` (`set -eu` for sh) to shell steps
+    pub fn with_strict_shell(mut self, strict: bool) -> Self {
+        self.strict_shell = strict;
+        self
+    }
+
+    /// Only execute the selected steps
+    pub fn with_selection(mut self, selection: StepSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Count these steps as succeeded by an earlier run, for `needs=`
+    ///
+    /// These are a [`Checkpoint`](super::Checkpoint)'s `succeeded_steps`.
+    pub fn with_succeeded_steps(mut self, steps: Vec<usize>) -> Self {
+        self.succeeded_steps = steps;
+        self
+    }
+
+    /// Run every step even if earlier ones fail
+    pub fn with_continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    /// Show each step's output through `$PAGER` (or `less -R`) when it
+    /// doesn't fit on the terminal
+    pub fn with_pager(mut self, pager: bool) -> Self {
+        self.pager = pager;
+        self
+    }
+
+    /// Mask secrets in step content and output before showing them
+    ///
+    /// With any patterns, each step's output is shown once the step finishes.
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.renderer = self.renderer.with_redactor(redactor.clone());
+        self.redactor = redactor;
+        self
+    }
+
+    /// Execute a document without any interaction
+    ///
+    /// Failing steps are reported rather than returned as errors; errors are
+    /// reserved for problems like an unreadable document or a missing interpreter.
+    pub fn execute(&mut self, doc: &Document) -> Result<ExecutionReport> {
+        let steps = doc.steps();
+        self.selection.validate(steps.len())?;
+        if !self.skip_checks {
+            check_document_requirements(doc)?;
+        }
+        self.renderer.set_total_steps(steps.len());
+
+        let run_started = Instant::now();
+        let mut report = ExecutionReport {
+            total_steps: steps
+                .iter()
+                .filter(|s| self.selection.contains(s.index) && self.tag_filter.matches(s.code))
+                .count(),
+            ..ExecutionReport::default()
+        };
+
+        let mut last_header = None;
+        // Ids of the steps that have succeeded so far, for `needs=`
+        let mut succeeded_ids: HashSet<&str> = steps
+            .iter()
+            .filter(|step| self.succeeded_steps.contains(&step.index))
+            .flat_map(|step| step.code.attribute("id"))
+            .collect();
+        for step in steps {
+            if !self.selection.contains(step.index) || !self.tag_filter.matches(step.code) {
+                self.renderer.skip_step();
+                report.steps.push(StepResult {
+                    index: step.index,
+                    language: step.code.language.clone(),
+                    content: self.redactor.redact(&step.code.content).into_owned(),
+                    skipped: true,
+                    ..StepResult::default()
+                });
+                continue;
+            }
+
+            if step.section_header != last_header {
+                if let Some(header) = step.section_header {
+                    let level = step.header_level.unwrap_or(1);
+                    self.renderer
+                        .render_header(header, level, step.section_steps)?;
+                }
+                last_header = step.section_header;
+            }
+
+            if let Some(missing) = step
+                .code
+                .needs()
+                .into_iter()
+                .find(|id| !succeeded_ids.contains(id))
+            {
+                self.renderer.render_error(&format!(
+                    "Step {} needs step '{}', which has not succeeded",
+                    step.index, missing
+                ))?;
+                // Never started, so it has no exit code and counts as failed
+                report.steps.push(StepResult {
+                    index: step.index,
+                    language: step.code.language.clone(),
+                    content: self.redactor.redact(&step.code.content).into_owned(),
+                    ..StepResult::default()
+                });
+                if !self.continue_on_error {
+                    break;
+                }
+                continue;
+            }
+            if self.strict_vars {
+                check_undefined_variables(step.code, &self.vars, &self.env)?;
+            }
+            self.renderer
+                .render_code(step.code, &self.vars, &self.env)?;
+
+            let context = || format!("Step at line {}", step.code.line_number);
+            let retries = step.code.retries().with_context(context)?;
+            let mut delay = step.code.retry_delay().with_context(context)?;
+
+            // Re-run failing steps up to `retries` times, doubling the delay each time up to a cap
+            let started = Instant::now();
+            let started_at = unix_timestamp();
+            let mut script = step.code.render_content(&self.vars, &self.env);
+            if self.strict_shell {
+                script = strict_script(step.code, &script, &self.interpreters);
+            }
+            let expected = step.code.expected_output.as_deref();
+            let mut attempt = 1;
+            let (result, stdout) = loop {
+                let (mut result, stdout) = self.run_attempt(step.code, &script)?;
+                result.index = step.index;
+                result.started_at = Some(started_at);
+                result.duration = started.elapsed();
+                if result.succeeded() || attempt > retries {
+                    break (result, stdout);
+                }
+
+                self.renderer.render_retry(
+                    step.index,
+                    attempt,
+                    retries + 1,
+                    &failure_reason(&result),
+                    delay,
+                )?;
+                thread::sleep(delay);
+                delay = next_retry_delay(delay);
+                attempt += 1;
+            };
+
+            if let Some(log) = &mut self.audit_log {
+                log.record(AuditEntry {
+                    timestamp: unix_timestamp(),
+                    step: step.index,
+                    section: step.section_header.map(str::to_string),
+                    language: step.code.language.clone(),
+                    content: script,
+                    exit_code: result.exit_code,
+                    duration_ms: result.duration.as_millis(),
+                    skipped: false,
+                })?;
+            }
+
+            if result.output_mismatch {
+                self.renderer.render_output_mismatch(
+                    step.index,
+                    expected.unwrap_or_default(),
+                    &stdout,
+                )?;
+            } else if result.timed_out {
+                self.renderer.render_error(&format!(
+                    "Step {} timed out after {:?} and was killed",
+                    step.index,
+                    self.timeout.unwrap_or_default()
+                ))?;
+            } else if !result.succeeded() {
+                self.renderer.render_error(&format!(
+                    "Step {} failed with {}",
+                    step.index,
+                    failure_reason(&result)
+                ))?;
+            }
+
+            if result.succeeded() {
+                succeeded_ids.extend(step.code.attribute("id"));
+            }
+            let failed = result.failed();
+            report.steps.push(result);
+            if failed && !self.continue_on_error {
+                break;
+            }
+        }
+        report.total_duration = run_started.elapsed();
+
+        self.renderer.render_run_table(&report.steps)?;
+        if report.is_success() {
+            self.renderer.render_completion()?;
+        } else {
+            self.renderer.render_summary(
+                report.succeeded(),
+                report.total_steps,
+                &report.failed_steps(),
+            )?;
+            // Stopped at a failure: say how much of the run is left
+            if !self.continue_on_error {
+                self.renderer
+                    .render_progress_summary(report.succeeded(), 0, report.total_steps)?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Run a step once, capturing stdout if it has an expected output, is
+    /// paged, is redacted or goes into a transcript
+    ///
+    /// The returned result's index and timings are left for the caller to fill in.
+    fn run_attempt(&self, code: &CodeBlock, script: &str) -> Result<(StepResult, String)> {
+        let expected = code.expected_output.as_deref();
+        let (status, stdout) = match (expected, self.renderer.transcript()) {
+            _ if self.pager || !self.redactor.is_empty() => {
+                let (status, stdout, stderr) = run_code_collecting(
+                    code,
+                    script,
+                    &self.env,
+                    self.timeout,
+                    &self.sudo_cmd,
+                    &self.interpreters,
+                )?;
+                let shown = self.redactor.redact(&stdout);
+                let shown_errors = self.redactor.redact(&stderr);
+                if self.pager {
+                    show_output(&shown)?;
+                } else {
+                    print_output(&shown)?;
+                }
+                print_errors(&shown_errors)?;
+                self.renderer.record_output(&shown)?;
+                self.renderer.record_output(&shown_errors)?;
+                (status, stdout)
+            }
+            (_, Some(transcript)) => run_code_transcribed(
+                code,
+                script,
+                &self.env,
+                self.timeout,
+                &self.sudo_cmd,
+                &self.interpreters,
+                transcript,
+            )?,
+            (Some(_), None) => run_code_capturing(
+                code,
+                script,
+                &self.env,
+                self.timeout,
+                &self.sudo_cmd,
+                &self.interpreters,
+            )?,
+            (None, None) => (
+                run_code(
+                    code,
+                    script,
+                    &self.env,
+                    self.timeout,
+                    &self.sudo_cmd,
+                    &self.interpreters,
+                )?,
+                String::new(),
+            ),
+        };
+        // Only steps that otherwise succeeded have their output checked
+        let output_mismatch = match expected {
+            Some(expected) if status.is_some_and(|s| s.success()) => {
+                !output_matches(expected, &stdout).with_context(|| {
+                    format!(
+                        "Invalid expected output regex for step at line {}",
+                        code.line_number
+                    )
+                })?
+            }
+            _ => false,
+        };
+
+        let result = StepResult {
+            language: code.language.clone(),
+            content: self.redactor.redact(script).into_owned(),
+            exit_code: status.and_then(|s| s.code()),
+            timed_out: status.is_none(),
+            output_mismatch,
+            ..StepResult::default()
+        };
+        Ok((result, stdout))
+    }
+}
+
+/// Why a step failed, e.g. "exit code 2"
+fn failure_reason(result: &StepResult) -> String {
+    if result.timed_out {
+        "a timeout".to_string()
+    } else if result.output_mismatch {
+        "unexpected output".to_string()
+    } else {
+        match result.exit_code {
+            Some(code) => format!("exit code {}", code),
+            None => "a signal".to_string(),
+        }
+    }
+}
+
+impl Default for BatchExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Progress of an interrupted run, saved next to the runbook
+///
+/// For `deploy.sysadmin` the state lives in `.deploy.sysadmin.state` in the
+/// same directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Where the checkpoint is stored
+    #[serde(skip)]
+    path: PathBuf,
+    /// Hash of the runbook content the checkpoint was taken for
+    pub document_hash: String,
+    /// 1-based number of the last step that completed successfully
+    pub last_completed_step: usize,
+    /// 1-based numbers of every step that completed successfully, in order
+    ///
+    /// Steps before [`Checkpoint::last_completed_step`] that were skipped,
+    /// failed or left out by `--from` are missing here.
+    #[serde(default)]
+    pub succeeded_steps: Vec<usize>,
+}
+
+impl Checkpoint {
+    /// A fresh checkpoint for `runbook` with no steps completed
+    pub fn new(runbook: &Path, content: &str) -> Self {
+        Self {
+            path: Self::path_for(runbook),
+            document_hash: document_hash(content),
+            last_completed_step: 0,
+            succeeded_steps: Vec::new(),
+        }
+    }
+
+    /// Path of the state file for `runbook`
+    pub fn path_for(runbook: &Path) -> PathBuf {
+        let name = runbook
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        runbook.with_file_name(format!(".{}.state", name))
+    }
+
+    /// Read the checkpoint for `runbook`, if a previous run left one
+    pub fn load(runbook: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(runbook);
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read checkpoint: {}", path.display()))
+            }
+        };
+
+        let mut checkpoint: Self = serde_json::from_str(&json).with_context(|| {
+            format!(
+                "Invalid checkpoint file {}; delete it to start fresh",
+                path.display()
+            )
+        })?;
+        checkpoint.path = path;
+        Ok(Some(checkpoint))
+    }
+
+    /// Write the checkpoint to its state file
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string(self).context("Failed to serialize checkpoint")?;
+        fs::write(&self.path, json)
+            .with_context(|| format!("Failed to write checkpoint: {}", self.path.display()))
+    }
+
+    /// Remove the state file, if there is one
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e)
+                .with_context(|| format!("Failed to remove checkpoint: {}", self.path.display())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the checkpoint was taken for exactly this runbook content
+    pub fn matches(&self, content: &str) -> bool {
+        self.document_hash == document_hash(content)
+    }
+
+    /// Record `step` as completed and save
+    pub fn record(&mut self, step: usize) -> Result<()> {
+        self.last_completed_step = step;
+        if !self.succeeded_steps.contains(&step) {
+            self.succeeded_steps.push(step);
+        }
+        self.save()
+    }
+}
+
+/// 64-bit FNV-1a hash of the runbook, stable across builds and platforms
+fn document_hash(content: &str) -> String {
+    let hash = content
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_path_is_hidden_sibling() {
+        assert_eq!(
+            Checkpoint::path_for(Path::new("/srv/runbooks/deploy.sysadmin")),
+            PathBuf::from("/srv/runbooks/.deploy.sysadmin.state")
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let dir = std::env::temp_dir().join(format!("sysadmin-checkpoint-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let runbook = dir.join("deploy.sysadmin");
+
+        assert_eq!(Checkpoint::load(&runbook).unwrap(), None);
+
+        let mut checkpoint = Checkpoint::new(&runbook, "# Deploy\n");
+        checkpoint.record(2).unwrap();
+        let loaded = Checkpoint::load(&runbook).unwrap().unwrap();
+        assert_eq!(loaded, checkpoint);
+        assert!(loaded.matches("# Deploy\n"));
+        assert!(!loaded.matches("# Deploy v2\n"));
+
+        loaded.clear().unwrap();
+        assert_eq!(Checkpoint::load(&runbook).unwrap(), None);
+        loaded.clear().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Editor used when `$EDITOR` isn't set
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Counter keeping the temp files of concurrent edits apart
+static EDIT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Open `content` in `$EDITOR` (or `vi`) and return what was saved
+///
+/// The content is edited in a temp file, which is removed afterwards.
+/// Trailing whitespace is dropped, as the parser does for step content.
+pub fn edit_in_editor(content: &str) -> Result<String> {
+    let editor = env::var("EDITOR")
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string());
+    edit_with(&editor, content)
+}
+
+/// Edit `content` with `editor`, a command that may carry its own arguments
+/// (e.g. `code --wait`)
+fn edit_with(editor: &str, content: &str) -> Result<String> {
+    let path = env::temp_dir().join(format!(
+        "sysadmin-step-{}-{}",
+        std::process::id(),
+        EDIT_COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    create_private(&path)
+        .and_then(|mut file| writeln!(file, "{}", content))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let edited = run_editor(editor, &path).and_then(|_| {
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))
+    });
+    let _ = fs::remove_file(&path);
+    Ok(edited?.trim_end().to_string())
+}
+
+/// Create a new file only the current user can read or write
+///
+/// Fails if `path` already exists, so a file planted at the predictable temp
+/// path by someone else is never opened and run.
+fn create_private(path: &Path) -> std::io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+/// Run `editor` on the file at `path` and wait for it to exit successfully
+fn run_editor(editor: &str, path: &Path) -> Result<()> {
+    let mut words = editor.split_whitespace();
+    let program = words.next().context("Empty editor command")?;
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to start editor: {}", editor))?;
+    if !status.success() {
+        bail!("Editor {} exited with {}", editor, status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_with_returns_saved_content() {
+        let edited = edit_with("sed -i s/web01/web02/", "ssh web01 uptime").unwrap();
+        assert_eq!(edited, "ssh web02 uptime");
+    }
+
+    #[test]
+    fn test_edit_with_failing_editor_is_an_error() {
+        let err = edit_with("false", "ls").unwrap_err();
+        assert!(err.to_string().contains("exited with"));
+    }
+
+    #[test]
+    fn test_create_private_refuses_existing_files() {
+        let path = env::temp_dir().join(format!("sysadmin-private-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let file = create_private(&path).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = file.metadata().unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        drop(file);
+
+        assert!(create_private(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
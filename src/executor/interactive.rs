@@ -1,91 +1,618 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
 use std::env;
-use std::process::Command;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+use std::time::{Duration, Instant};
 
-use crate::model::{Block, Document};
-use crate::ui::Renderer;
+use super::audit::{unix_timestamp, AuditEntry, AuditLog};
+use super::checkpoint::Checkpoint;
+use super::editor::edit_in_editor;
+use super::pager::{print_errors, print_output};
+use super::process::{
+    check_undefined_variables, run_code, run_code_collecting, run_code_transcribed, strict_script,
+    working_dir, DEFAULT_SUDO_CMD,
+};
+use super::prompt::ShellPrompt;
+use super::requirements::check_document_requirements;
+use super::selection::{StepSelection, TagFilter};
+use crate::model::{danger_patterns, Block, CodeBlock, Document, Redactor, Section};
+use crate::ui::{Renderer, Theme};
+
+/// What to do with a step, as answered at the pre-step prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StepAction {
+    /// Run it directly through its interpreter
+    Run,
+    /// Edit it in `$EDITOR`, then run the edited version directly
+    Edit,
+    /// Drop into a sub-shell to run it by hand
+    Shell,
+    /// Move on without running anything
+    Skip,
+    /// Stop the run here
+    Quit,
+}
+
+impl StepAction {
+    /// Parse a key (`r`, `e`, `s`, `k`, `q`) or the whole word; an empty answer means the shell
+    fn from_answer(answer: &str) -> Option<Self> {
+        match answer.trim().to_lowercase().as_str() {
+            "" | "s" | "shell" => Some(Self::Shell),
+            "r" | "run" => Some(Self::Run),
+            "e" | "edit" => Some(Self::Edit),
+            "k" | "skip" => Some(Self::Skip),
+            "q" | "quit" => Some(Self::Quit),
+            _ => None,
+        }
+    }
+}
 
 pub struct InteractiveExecutor {
     renderer: Renderer,
+    /// Maximum time a directly-run step may take before it is killed
+    timeout: Option<Duration>,
+    /// Values for `${VAR}` substitution in code blocks
+    vars: HashMap<String, String>,
+    /// Environment variables set for every executed step
+    env: HashMap<String, String>,
+    /// Fail on variables that can't be resolved instead of leaving them intact
+    strict_vars: bool,
+    /// Where to record each executed step, if anywhere
+    audit_log: Option<AuditLog>,
+    /// Which steps to execute
+    selection: StepSelection,
+    /// Which steps to execute, by tag
+    tag_filter: TagFilter,
+    /// Lowercased patterns that mark a step as dangerous
+    danger_patterns: Vec<String>,
+    /// Shell to drop into, instead of `$SHELL`
+    shell: Option<String>,
+    /// Run each step directly instead of dropping to a shell
+    auto_run: bool,
+    /// Don't verify the document's `requires` tools before starting
+    skip_checks: bool,
+    /// Where to record progress so an interrupted run can be resumed
+    checkpoint: Option<Checkpoint>,
+    /// Command that `{sudo}` steps are run under
+    sudo_cmd: String,
+    /// Ask before entering sections with headers at this level or above (1 = `#`)
+    section_gate: Option<u32>,
+    /// Print warnings about the runbook, such as a shell mismatch
+    warnings: bool,
+    /// Make directly-run shell steps abort on the first failed command or unset variable
+    strict_shell: bool,
+    /// Commands that replace the built-in interpreter for a fence language
+    interpreters: HashMap<String, Vec<String>>,
+    /// Masks secrets in directly-run steps' output
+    redactor: Redactor,
 }
 
 impl InteractiveExecutor {
     pub fn new() -> Self {
         Self {
             renderer: Renderer::new(),
+            timeout: None,
+            vars: HashMap::new(),
+            env: HashMap::new(),
+            strict_vars: false,
+            audit_log: None,
+            selection: StepSelection::All,
+            tag_filter: TagFilter::default(),
+            skip_checks: false,
+            danger_patterns: danger_patterns(&[]),
+            shell: None,
+            auto_run: false,
+            checkpoint: None,
+            sudo_cmd: DEFAULT_SUDO_CMD.to_string(),
+            section_gate: None,
+            warnings: true,
+            strict_shell: false,
+            interpreters: HashMap::new(),
+            redactor: Redactor::default(),
         }
     }
 
+    /// Kill directly-run steps that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Substitute these variables into code blocks before rendering or running
+    pub fn with_vars(mut self, vars: HashMap<String, String>) -> Self {
+        self.vars = vars;
+        self
+    }
+
+    /// Set these environment variables for every executed step
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Treat references to undefined variables as an error
+    pub fn with_strict_vars(mut self, strict: bool) -> Self {
+        self.strict_vars = strict;
+        self
+    }
+
+    /// Render with these colors instead of the default dark theme
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.renderer = self.renderer.with_theme(theme);
+        self
+    }
+
+    /// Copy everything shown on the terminal, minus colors, to `file`
+    pub fn with_transcript(mut self, file: File) -> Self {
+        self.renderer = self.renderer.with_transcript(file);
+        self
+    }
+
+    /// Mask secrets in the step content shown, in the output of directly-run
+    /// steps and in the transcript
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.renderer = self.renderer.with_redactor(redactor.clone());
+        self.redactor = redactor;
+        self
+    }
+
+    /// Show `template` before each sub-shell instead of the built-in message
+    pub fn with_shell_prompt_message(mut self, template: impl Into<String>) -> Self {
+        self.renderer = self.renderer.with_shell_prompt_message(template);
+        self
+    }
+
+    /// Record every executed step to an audit log
+    pub fn with_audit_log(mut self, log: AuditLog) -> Self {
+        self.audit_log = Some(log);
+        self
+    }
+
+    /// Require confirmation for steps matching these patterns, on top of the defaults
+    pub fn with_danger_patterns(mut self, extra: &[String]) -> Self {
+        self.danger_patterns = danger_patterns(extra);
+        self
+    }
+
+    /// Drop into this shell instead of `$SHELL`
+    pub fn with_shell(mut self, shell: impl Into<String>) -> Self {
+        self.shell = Some(shell.into());
+        self
+    }
+
+    /// Run each step through its interpreter instead of dropping to a shell
+    pub fn with_auto_run(mut self, auto_run: bool) -> Self {
+        self.auto_run = auto_run;
+        self
+    }
+
+    /// Only execute steps whose tags pass `filter`
+    pub fn with_tag_filter(mut self, filter: TagFilter) -> Self {
+        self.tag_filter = filter;
+        self
+    }
+
+    /// Start without checking that the document's required tools are installed
+    pub fn with_skip_checks(mut self, skip: bool) -> Self {
+        self.skip_checks = skip;
+        self
+    }
+
+    /// Save progress to `checkpoint` after each successful step, clearing it when done
+    pub fn with_checkpoint(mut self, checkpoint: Checkpoint) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// Run `{sudo}` steps under this command (e.g. `doas`) instead of `sudo`
+    pub fn with_sudo_cmd(mut self, sudo_cmd: impl Into<String>) -> Self {
+        self.sudo_cmd = sudo_cmd.into();
+        self
+    }
+
+    /// Run steps in these fence languages with the given program and arguments
+    pub fn with_interpreters(mut self, interpreters: HashMap<String, Vec<String>>) -> Self {
+        self.interpreters = interpreters;
+        self
+    }
+
+    /// Prepend `set -euo pipefail` (`set -eu` for sh) to directly-run shell steps
+    pub fn with_strict_shell(mut self, strict: bool) -> Self {
+        self.strict_shell = strict;
+        self
+    }
+
+    /// Only execute the selected steps
+    pub fn with_selection(mut self, selection: StepSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Warn (or not) when the runbook's shell differs from the one steps are dropped into
+    pub fn with_warnings(mut self, warnings: bool) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Ask before entering each section headed at `level` or above (1 for `#` headers)
+    ///
+    /// Declining stops the run, keeping the checkpoint. Sections with no
+    /// selected steps are entered without asking.
+    pub fn with_section_gate(mut self, level: u32) -> Self {
+        self.section_gate = Some(level);
+        self
+    }
+
     /// Execute a document interactively
     pub fn execute(&mut self, doc: &Document) -> Result<()> {
         let total_steps = doc.step_count();
+        self.selection.validate(total_steps)?;
+        if !self.skip_checks {
+            check_document_requirements(doc)?;
+        }
+        if self.warnings && !self.auto_run {
+            self.check_shell(doc)?;
+        }
+
         self.renderer.set_total_steps(total_steps);
+        let mut step = 0;
+        // Progress through the selected steps, for the summary at the end
+        let selected = doc
+            .steps()
+            .iter()
+            .filter(|s| self.selection.contains(s.index) && self.tag_filter.matches(s.code))
+            .count();
+        let mut completed = 0;
+        let mut skipped = 0;
 
         for section in &doc.sections {
+            let section_steps = section.step_count();
+            let show_context = !self.selection.is_focused()
+                || (step + 1..=step + section_steps).any(|s| self.selection.contains(s));
+
             // Render section header if present
-            if let Some(header) = &section.header {
+            if let Some(header) = section.header.as_ref().filter(|_| show_context) {
                 let level = section.header_level.unwrap_or(1);
-                self.renderer.render_header(header, level)?;
+                self.renderer.render_header(header, level, section_steps)?;
+
+                let gated = self.section_gate.is_some_and(|gate| level <= gate);
+                if gated
+                    && self.section_has_selected_steps(section, step)
+                    && !self.confirm_section(header)?
+                {
+                    self.renderer.render_stopped(step + 1)?;
+                    self.renderer
+                        .render_progress_summary(completed, skipped, selected)?;
+                    return Ok(());
+                }
             }
 
             // Render each block in the section
             for block in &section.blocks {
                 match block {
-                    Block::Text(text) => {
-                        self.renderer.render_text(text)?;
-                    }
-                    Block::Code(code) => {
-                        self.renderer.render_code(code)?;
+                    Block::Code(code) if code.is_executable() => {
+                        step += 1;
+                        if !self.selection.contains(step) || !self.tag_filter.matches(code) {
+                            // Keep the renderer's numbering in sync with skipped steps
+                            self.renderer.skip_step();
+                            continue;
+                        }
 
-                        // Drop into a sub-shell for the user to run the command
-                        self.drop_to_shell()?;
+                        self.check_variables(code)?;
+                        self.renderer.render_code(code, &self.vars, &self.env)?;
+
+                        // Run the step directly, or let the user decide; `None` means skipped
+                        let started = Instant::now();
+                        // The step as edited at the prompt, if it was
+                        let mut edited = None;
+                        let status = if self.auto_run {
+                            Some(self.run_block(code)?)
+                        } else {
+                            if code.requires_sudo() {
+                                self.renderer.render_needs_root(&self.sudo_cmd)?;
+                            }
+                            if let Some(host) = &code.remote_host {
+                                self.renderer.render_remote_notice(host)?;
+                            }
+                            match self.prompt_step_action()? {
+                                StepAction::Run => Some(self.run_block(code)?),
+                                StepAction::Edit => {
+                                    let content = edit_in_editor(
+                                        &code.render_content(&self.vars, &self.env),
+                                    )?;
+                                    if content.trim().is_empty() {
+                                        self.renderer.render_skipped(step)?;
+                                        None
+                                    } else {
+                                        let code = edited.insert(CodeBlock {
+                                            content,
+                                            ..code.clone()
+                                        });
+                                        self.renderer.render_edited(code)?;
+                                        // Variables were filled in before editing
+                                        Some(self.run_script(code, &code.content)?)
+                                    }
+                                }
+                                StepAction::Shell => {
+                                    // A missing directory is only noted; it can be created there
+                                    let dir = match working_dir(code) {
+                                        Ok(dir) => dir,
+                                        Err(_) => {
+                                            let dir = code.working_dir().unwrap_or_default();
+                                            self.renderer.render_missing_dir(&dir)?;
+                                            None
+                                        }
+                                    };
+                                    Some(self.drop_to_shell(dir.as_deref())?)
+                                }
+                                StepAction::Skip => {
+                                    self.renderer.render_skipped(step)?;
+                                    None
+                                }
+                                StepAction::Quit => {
+                                    // The checkpoint stays, so the next run can resume here
+                                    self.renderer.render_stopped(step)?;
+                                    self.renderer
+                                        .render_progress_summary(completed, skipped, selected)?;
+                                    return Ok(());
+                                }
+                            }
+                        };
+
+                        if let Some(log) = &mut self.audit_log {
+                            log.record(AuditEntry {
+                                timestamp: unix_timestamp(),
+                                step,
+                                section: section.header.clone(),
+                                language: code.language.clone(),
+                                content: match &edited {
+                                    Some(edited) => edited.content.clone(),
+                                    None => code.render_content(&self.vars, &self.env),
+                                },
+                                exit_code: status.and_then(|s| s.code()),
+                                duration_ms: started.elapsed().as_millis(),
+                                skipped: status.is_none(),
+                            })?;
+                        }
+
+                        match status {
+                            Some(status) if status.success() => self.save_checkpoint(step)?,
+                            Some(status) => {
+                                if self.auto_run {
+                                    self.renderer
+                                        .render_progress_summary(completed, skipped, selected)?;
+                                    bail!("Step {} failed: {}", step, status);
+                                }
+                                self.renderer
+                                    .render_error(&format!("Step {} failed: {}", step, status))?;
+                            }
+                            // Never run, so it isn't recorded as done
+                            None => {}
+                        }
+                        // A failed step the user moved on from still counts as done
+                        match status {
+                            Some(_) => completed += 1,
+                            None => skipped += 1,
+                        }
+                    }
+                    // Text, tables, callouts and display-only code
+                    block => {
+                        if !self.selection.is_focused() {
+                            self.renderer.render_block(block)?;
+                        }
                     }
                 }
             }
         }
 
-        self.renderer.render_completion()?;
+        if let Some(checkpoint) = &self.checkpoint {
+            checkpoint.clear()?;
+        }
+        if skipped == 0 {
+            self.renderer.render_completion()?;
+        } else {
+            self.renderer
+                .render_progress_summary(completed, skipped, selected)?;
+        }
         Ok(())
     }
 
-    /// Drop into a sub-shell for the user to execute commands
-    fn drop_to_shell(&self) -> Result<()> {
-        self.renderer.render_shell_prompt()?;
+    /// Warn once if most shell steps are written for a shell other than the one dropped into
+    fn check_shell(&self, doc: &Document) -> Result<()> {
+        let shell = self.shell_path();
+        let shell_name = shell_name(&shell);
+        match primary_shell(doc) {
+            Some(language) if !shell_runs(shell_name, language) => {
+                self.renderer.render_shell_mismatch(language, shell_name)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Record `step` as done; a checkpoint that can't be written is dropped with a warning
+    fn save_checkpoint(&mut self, step: usize) -> Result<()> {
+        if let Some(checkpoint) = &mut self.checkpoint {
+            if let Err(e) = checkpoint.record(step) {
+                self.renderer
+                    .render_error(&format!("{:#}; resuming won't be offered", e))?;
+                self.checkpoint = None;
+            }
+        }
+        Ok(())
+    }
 
-        // Get the user's shell, default to bash
-        let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
+    /// Whether any step of `section`, numbered from `first_step + 1`, is to be executed
+    fn section_has_selected_steps(&self, section: &Section, first_step: usize) -> bool {
+        section
+            .blocks
+            .iter()
+            .filter_map(|b| match b {
+                Block::Code(code) if code.is_executable() => Some(code),
+                _ => None,
+            })
+            .zip(first_step + 1..)
+            .any(|(code, step)| self.selection.contains(step) && self.tag_filter.matches(code))
+    }
 
-        // Determine shell type from path
-        let shell_name = std::path::Path::new(&shell)
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("bash");
+    /// Ask whether to go on into the section headed `header`; only "y" or "yes" does
+    fn confirm_section(&self, header: &str) -> Result<bool> {
+        self.renderer.render_section_gate(header)?;
 
-        // Set a custom prompt to make it obvious we're in a sysadmin sub-shell
-        let custom_prompt = "%F{magenta}[sysadmin]%f $ ";
-        let custom_ps1 = "\x1b[1;35m[sysadmin]\x1b[0m $ ";
-        
-        // Spawn a sub-shell with custom prompt
-        let mut cmd = Command::new(&shell);
-        
-        // Set prompt based on shell type
-        match shell_name {
-            "zsh" => {
-                cmd.env("PROMPT", custom_prompt);
-                // Also set PS1 for compatibility
-                cmd.env("PS1", custom_ps1);
+        let mut answer = String::new();
+        io::stdin()
+            .read_line(&mut answer)
+            .context("Failed to read answer")?;
+        Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+    }
+
+    /// Ask whether to run, shell out for, skip the step, or quit, until the answer makes sense
+    fn prompt_step_action(&self) -> Result<StepAction> {
+        loop {
+            self.renderer.render_step_prompt()?;
+
+            let mut answer = String::new();
+            let read = io::stdin()
+                .read_line(&mut answer)
+                .context("Failed to read answer")?;
+            // End of input: nobody is left to answer
+            if read == 0 {
+                return Ok(StepAction::Quit);
             }
-            "fish" => {
-                // Fish uses a function, but we can try setting a simple prompt
-                cmd.env("fish_greeting", "");
-                // Fish doesn't use PS1, we'd need to write a function
-                // For now, just let fish use its default
+            if let Some(action) = StepAction::from_answer(&answer) {
+                return Ok(action);
             }
-            _ => {
-                // bash, sh, and most others use PS1
-                cmd.env("PS1", custom_ps1);
+        }
+    }
+
+    /// In strict mode, fail if the block references undefined variables
+    fn check_variables(&self, code: &CodeBlock) -> Result<()> {
+        if !self.strict_vars {
+            return Ok(());
+        }
+
+        check_undefined_variables(code, &self.vars, &self.env)
+    }
+
+    /// Run a code block directly through its interpreter
+    ///
+    /// The block content is piped to the interpreter's stdin. If a timeout is
+    /// configured and the process outlives it, the process is killed and an
+    /// error is returned.
+    pub fn run_block(&self, code: &CodeBlock) -> Result<ExitStatus> {
+        self.check_variables(code)?;
+        self.run_script(code, &code.render_content(&self.vars, &self.env))
+    }
+
+    /// Run `script`, the block's content with variables already filled in
+    fn run_script(&self, code: &CodeBlock, script: &str) -> Result<ExitStatus> {
+        if code.is_dangerous_with(&self.danger_patterns) && !self.confirm_dangerous(code)? {
+            bail!(
+                "Dangerous step at line {} was not confirmed",
+                code.line_number
+            );
+        }
+
+        let script = if self.strict_shell {
+            strict_script(code, script, &self.interpreters)
+        } else {
+            script.to_string()
+        };
+        // With secrets to mask, output is collected and shown once the step ends
+        let status = if let Some(transcript) = self
+            .renderer
+            .transcript()
+            .filter(|_| self.redactor.is_empty())
+        {
+            run_code_transcribed(
+                code,
+                &script,
+                &self.env,
+                self.timeout,
+                &self.sudo_cmd,
+                &self.interpreters,
+                transcript,
+            )?
+            .0
+        } else if self.redactor.is_empty() {
+            run_code(
+                code,
+                &script,
+                &self.env,
+                self.timeout,
+                &self.sudo_cmd,
+                &self.interpreters,
+            )?
+        } else {
+            let (status, stdout, stderr) = run_code_collecting(
+                code,
+                &script,
+                &self.env,
+                self.timeout,
+                &self.sudo_cmd,
+                &self.interpreters,
+            )?;
+            let shown = self.redactor.redact(&stdout);
+            let shown_errors = self.redactor.redact(&stderr);
+            print_output(&shown)?;
+            print_errors(&shown_errors)?;
+            self.renderer.record_output(&shown)?;
+            self.renderer.record_output(&shown_errors)?;
+            status
+        };
+        match status {
+            Some(status) => Ok(status),
+            None => {
+                let limit = self.timeout.unwrap_or_default();
+                self.renderer
+                    .render_error(&format!("Step timed out after {:?} and was killed", limit))?;
+                bail!("Step timed out after {:?}", limit);
             }
         }
+    }
+
+    /// Make the user type the command name (or "yes") before a dangerous step
+    fn confirm_dangerous(&self, code: &CodeBlock) -> Result<bool> {
+        let expected = code.command_name().unwrap_or("yes");
+        self.renderer.render_danger_confirmation(expected)?;
+
+        let mut answer = String::new();
+        io::stdin()
+            .read_line(&mut answer)
+            .context("Failed to read confirmation")?;
+
+        let answer = answer.trim();
+        Ok(answer == expected || answer.eq_ignore_ascii_case("yes"))
+    }
+
+    /// The shell to drop into: the configured one, `$SHELL`, or bash
+    fn shell_path(&self) -> String {
+        self.shell
+            .clone()
+            .or_else(|| env::var("SHELL").ok())
+            .unwrap_or_else(|| "/bin/bash".to_string())
+    }
+
+    /// Drop into a sub-shell for the user to execute commands
+    ///
+    /// The shell starts in `dir` if given. Returns the exit status of the shell.
+    fn drop_to_shell(&self, dir: Option<&Path>) -> Result<ExitStatus> {
+        self.renderer.render_shell_prompt()?;
+
+        let shell = self.shell_path();
+        let shell_name = shell_name(&shell);
+
+        // Spawn a sub-shell with custom prompt
+        let mut cmd = Command::new(&shell);
+        cmd.envs(&self.env);
+        if let Some(dir) = dir {
+            cmd.current_dir(dir);
+        }
+
+        // Make it obvious we're in a sysadmin sub-shell; kept until the shell exits
+        let _prompt = ShellPrompt::configure(&mut cmd, shell_name)?;
 
         let status = cmd
             .stdin(std::process::Stdio::inherit())
@@ -105,7 +632,7 @@ impl InteractiveExecutor {
         }
 
         println!(); // Add spacing after shell exits
-        Ok(())
+        Ok(status)
     }
 }
 
@@ -114,3 +641,82 @@ impl Default for InteractiveExecutor {
         Self::new()
     }
 }
+
+/// The file name of a shell binary, e.g. `zsh` for `/usr/bin/zsh`
+fn shell_name(shell: &str) -> &str {
+    Path::new(shell)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("bash")
+}
+
+/// The shell language most of the document's shell steps are written in
+///
+/// Ties go to the language that appears first.
+fn primary_shell(doc: &Document) -> Option<&str> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for code in doc.executable_blocks().into_iter().filter(|c| c.is_shell()) {
+        match counts
+            .iter_mut()
+            .find(|(language, _)| *language == code.language)
+        {
+            Some((_, count)) => *count += 1,
+            None => counts.push((&code.language, 1)),
+        }
+    }
+    counts
+        .iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(language, _)| *language)
+}
+
+/// Whether commands written for `language` can be typed into `shell` as-is
+///
+/// Plain `sh` steps work in any POSIX shell, which fish is not.
+fn shell_runs(shell: &str, language: &str) -> bool {
+    shell == language || (language == "sh" && shell != "fish")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_action_from_answer() {
+        assert_eq!(StepAction::from_answer("\n"), Some(StepAction::Shell));
+        assert_eq!(StepAction::from_answer("s\n"), Some(StepAction::Shell));
+        assert_eq!(StepAction::from_answer("R\n"), Some(StepAction::Run));
+        assert_eq!(StepAction::from_answer("e\n"), Some(StepAction::Edit));
+        assert_eq!(StepAction::from_answer(" skip\n"), Some(StepAction::Skip));
+        assert_eq!(StepAction::from_answer("k\n"), Some(StepAction::Skip));
+        assert_eq!(StepAction::from_answer("quit\n"), Some(StepAction::Quit));
+        assert_eq!(StepAction::from_answer("x\n"), None);
+    }
+
+    #[test]
+    fn test_primary_shell_mismatch() {
+        let content = "```zsh\nsetopt extendedglob\n```\n\n```bash\nls\n```\n\n```zsh\nprint -l **/*.conf\n```\n\n```python\nprint(1)\n```\n";
+        let doc = crate::parser::SysadminParser::parse(content).unwrap();
+        assert_eq!(primary_shell(&doc), Some("zsh"));
+        assert!(!shell_runs(shell_name("/bin/bash"), "zsh"));
+        assert!(shell_runs(shell_name("/usr/bin/zsh"), "zsh"));
+
+        assert!(shell_runs("bash", "sh"));
+        assert!(!shell_runs("fish", "sh"));
+        assert_eq!(primary_shell(&Document::new()), None);
+    }
+
+    #[test]
+    fn test_section_gate_skips_sections_without_selected_steps() {
+        let content = "# Prepare\n\n```bash\necho one\n```\n\n# Notes\n\nNothing to run\n\n# Migrate\n\n```bash\necho two\n```\n";
+        let doc = crate::parser::SysadminParser::parse(content).unwrap();
+        let executor = InteractiveExecutor::new()
+            .with_section_gate(1)
+            .with_selection(StepSelection::Range(2, 2));
+
+        assert!(!executor.section_has_selected_steps(&doc.sections[0], 0));
+        assert!(!executor.section_has_selected_steps(&doc.sections[1], 1));
+        assert!(executor.section_has_selected_steps(&doc.sections[2], 1));
+    }
+}
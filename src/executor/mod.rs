@@ -1,3 +1,22 @@
+pub mod audit;
+pub mod batch;
+pub mod checkpoint;
+mod editor;
 pub mod interactive;
+mod pager;
+mod process;
+mod prompt;
+pub mod report;
+pub mod requirements;
+pub mod selection;
 
+pub use audit::{AuditEntry, AuditLog};
+pub use batch::BatchExecutor;
+pub use checkpoint::Checkpoint;
+pub use editor::edit_in_editor;
 pub use interactive::InteractiveExecutor;
+pub(crate) use process::run_code_combined;
+pub use process::{invocation_preview, DEFAULT_SUDO_CMD};
+pub use report::{ExecutionReport, StepResult};
+pub use requirements::check_requirements;
+pub use selection::{StepSelection, TagFilter};
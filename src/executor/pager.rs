@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use crossterm::terminal;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// Pager used when `$PAGER` isn't set; `-R` keeps color escapes intact
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Show a step's output, through a pager if it's taller than the terminal
+///
+/// The pager is `$PAGER`, or `less -R`. Output that fits on screen, output
+/// that isn't going to a terminal, and output for a pager that can't be
+/// started are printed as they are.
+pub(crate) fn show_output(output: &str) -> Result<()> {
+    let rows = terminal::size().ok().map(|(_, rows)| rows as usize);
+    if io::stdout().is_terminal() && needs_paging(output, rows) {
+        let pager = env::var("PAGER")
+            .ok()
+            .filter(|pager| !pager.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_PAGER.to_string());
+        if page(&pager, output).is_ok() {
+            return Ok(());
+        }
+    }
+    print_output(output)
+}
+
+/// Write a step's output to stdout as it is
+pub(crate) fn print_output(output: &str) -> Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(output.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Write what a step printed to stderr back to stderr, as it is
+pub(crate) fn print_errors(errors: &str) -> Result<()> {
+    let mut stderr = io::stderr();
+    stderr.write_all(errors.as_bytes())?;
+    stderr.flush()?;
+    Ok(())
+}
+
+/// Whether `output` has more lines than the terminal's `rows`, if known
+fn needs_paging(output: &str, rows: Option<usize>) -> bool {
+    rows.is_some_and(|rows| output.lines().count() > rows)
+}
+
+/// Feed `output` to `pager` and wait for the user to close it
+fn page(pager: &str, output: &str) -> Result<()> {
+    let mut words = pager.split_whitespace();
+    let program = words.next().context("Empty pager command")?;
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start pager: {}", pager))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager early closes the pipe, which isn't an error
+        let _ = stdin.write_all(output.as_bytes());
+    }
+    child.wait().context("Failed to wait for pager")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_output_taller_than_the_terminal_is_paged() {
+        let output = "one\ntwo\nthree\n";
+        assert!(needs_paging(output, Some(2)));
+        assert!(!needs_paging(output, Some(3)));
+        assert!(!needs_paging(output, None));
+    }
+}
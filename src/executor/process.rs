@@ -0,0 +1,728 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::exporter::shell::HEREDOC_DELIMITER;
+use crate::model::CodeBlock;
+use crate::ui::tee::strip_ansi;
+
+/// How often a running step is polled while waiting on a timeout
+const TIMEOUT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Command that `{sudo}` steps are run under unless configured otherwise
+pub const DEFAULT_SUDO_CMD: &str = "sudo";
+
+/// Line prepended to bash and zsh steps under `--strict-shell`
+const STRICT_PREAMBLE: &str = "set -euo pipefail";
+
+/// Line prepended to sh steps under `--strict-shell`; `pipefail` isn't POSIX
+const STRICT_PREAMBLE_POSIX: &str = "set -eu";
+
+/// Make a shell step abort on the first failed command or unset variable
+///
+/// Goes by the program the step actually runs under, so a shebang or an
+/// `interpreters` override counts, not the fence language. Steps run by
+/// anything but bash, zsh or sh (including fish, which has no equivalent)
+/// are returned unchanged.
+pub(crate) fn strict_script(
+    code: &CodeBlock,
+    script: &str,
+    interpreters: &HashMap<String, Vec<String>>,
+) -> String {
+    let argv = code.interpreter_argv_with(interpreters);
+    let program = argv.first().and_then(|program| program.rsplit('/').next());
+    match program {
+        Some("bash" | "zsh") => format!("{}\n{}", STRICT_PREAMBLE, script),
+        Some("sh") => format!("{}\n{}", STRICT_PREAMBLE_POSIX, script),
+        _ => script.to_string(),
+    }
+}
+
+/// Fail if the block references variables that can't be resolved
+pub(crate) fn check_undefined_variables(
+    code: &CodeBlock,
+    vars: &HashMap<String, String>,
+    env: &HashMap<String, String>,
+) -> Result<()> {
+    let undefined = code.undefined_variables(vars, env);
+    if !undefined.is_empty() {
+        bail!(
+            "Undefined variable(s) in code block at line {}: {}",
+            code.line_number,
+            undefined.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// The step's local working directory, which must exist if one is set
+///
+/// A remote step's `dir=` is on the remote host, handled by `command_line`,
+/// so it has none here.
+pub(crate) fn working_dir(code: &CodeBlock) -> Result<Option<PathBuf>> {
+    if code.remote_host.is_some() {
+        return Ok(None);
+    }
+    match code.working_dir() {
+        Some(dir) if !dir.is_dir() => bail!(
+            "Working directory for step at line {} does not exist: {}",
+            code.line_number,
+            dir.display()
+        ),
+        dir => Ok(dir),
+    }
+}
+
+/// Pipe `script` into the block's interpreter and wait for it to finish
+///
+/// The interpreter runs in the block's `dir=` directory, if it has one, and
+/// `env` is added to its environment. `{sudo}` steps run under `sudo_cmd`.
+/// `interpreters` replaces the built-in command for a fence language, as in
+/// [`CodeBlock::interpreter_argv_with`].
+/// Returns `None` if the process outlived `timeout` and was killed.
+pub(crate) fn run_code(
+    code: &CodeBlock,
+    script: &str,
+    env: &HashMap<String, String>,
+    timeout: Option<Duration>,
+    sudo_cmd: &str,
+    interpreters: &HashMap<String, Vec<String>>,
+) -> Result<Option<ExitStatus>> {
+    let (status, _, _) = spawn_and_wait(
+        code,
+        script,
+        env,
+        timeout,
+        sudo_cmd,
+        interpreters,
+        Output::Inherit,
+    )?;
+    Ok(status)
+}
+
+/// Like [`run_code`], but also return what the step printed to stdout
+///
+/// The output is still echoed to the terminal as it arrives.
+pub(crate) fn run_code_capturing(
+    code: &CodeBlock,
+    script: &str,
+    env: &HashMap<String, String>,
+    timeout: Option<Duration>,
+    sudo_cmd: &str,
+    interpreters: &HashMap<String, Vec<String>>,
+) -> Result<(Option<ExitStatus>, String)> {
+    let (status, stdout, _) = spawn_and_wait(
+        code,
+        script,
+        env,
+        timeout,
+        sudo_cmd,
+        interpreters,
+        Output::Tee,
+    )?;
+    Ok((status, stdout.unwrap_or_default()))
+}
+
+/// Like [`run_code_capturing`], but without echoing anything, for the caller to show
+///
+/// Returns stdout and stderr separately, so either can be masked before it
+/// is shown.
+pub(crate) fn run_code_collecting(
+    code: &CodeBlock,
+    script: &str,
+    env: &HashMap<String, String>,
+    timeout: Option<Duration>,
+    sudo_cmd: &str,
+    interpreters: &HashMap<String, Vec<String>>,
+) -> Result<(Option<ExitStatus>, String, String)> {
+    let (status, stdout, stderr) = spawn_and_wait(
+        code,
+        script,
+        env,
+        timeout,
+        sudo_cmd,
+        interpreters,
+        Output::Collect,
+    )?;
+    Ok((
+        status,
+        stdout.unwrap_or_default(),
+        stderr.unwrap_or_default(),
+    ))
+}
+
+/// Like [`run_code_collecting`], but capturing stderr too, interleaved with stdout
+///
+/// Nothing reaches the terminal, so this is safe to call while the TUI owns it.
+/// Setting `cancel` kills the step, as a timeout does.
+pub(crate) fn run_code_combined(
+    code: &CodeBlock,
+    script: &str,
+    env: &HashMap<String, String>,
+    timeout: Option<Duration>,
+    sudo_cmd: &str,
+    interpreters: &HashMap<String, Vec<String>>,
+    cancel: &AtomicBool,
+) -> Result<(Option<ExitStatus>, String)> {
+    let (status, output, _) = spawn_and_wait(
+        code,
+        script,
+        env,
+        timeout,
+        sudo_cmd,
+        interpreters,
+        Output::Combined(cancel),
+    )?;
+    Ok((status, output.unwrap_or_default()))
+}
+
+/// Like [`run_code_capturing`], but also copying stdout and stderr into `transcript`
+///
+/// Both streams still reach the terminal as they arrive; only stdout is returned.
+pub(crate) fn run_code_transcribed(
+    code: &CodeBlock,
+    script: &str,
+    env: &HashMap<String, String>,
+    timeout: Option<Duration>,
+    sudo_cmd: &str,
+    interpreters: &HashMap<String, Vec<String>>,
+    transcript: &File,
+) -> Result<(Option<ExitStatus>, String)> {
+    let (status, stdout, _) = spawn_and_wait(
+        code,
+        script,
+        env,
+        timeout,
+        sudo_cmd,
+        interpreters,
+        Output::Transcribe(transcript),
+    )?;
+    Ok((status, stdout.unwrap_or_default()))
+}
+
+/// What happens to a step's stdout
+#[derive(Debug, Clone, Copy)]
+enum Output<'a> {
+    /// Written straight to the terminal
+    Inherit,
+    /// Written to the terminal as it arrives, and captured
+    Tee,
+    /// Only captured, with stderr captured separately
+    Collect,
+    /// Captured together with stderr; setting the flag kills the step
+    Combined(&'a AtomicBool),
+    /// Like `Tee`, with stdout and stderr both also copied into a transcript
+    Transcribe(&'a File),
+}
+
+fn spawn_and_wait(
+    code: &CodeBlock,
+    script: &str,
+    env: &HashMap<String, String>,
+    timeout: Option<Duration>,
+    sudo_cmd: &str,
+    interpreters: &HashMap<String, Vec<String>>,
+    output: Output<'_>,
+) -> Result<(Option<ExitStatus>, Option<String>, Option<String>)> {
+    let interpreter = code.interpreter_argv_with(interpreters);
+    if interpreter.is_empty() {
+        bail!(
+            "No interpreter for '{}' at line {}; this block is display-only",
+            code.language,
+            code.line_number
+        );
+    }
+    let dir = working_dir(code)?;
+    let cancel = match output {
+        Output::Combined(cancel) => Some(cancel),
+        _ => None,
+    };
+    let argv = command_line(code, &interpreter, env, sudo_cmd);
+    let (program, args) = argv.split_first().context("Empty interpreter command")?;
+    let mut command = Command::new(program);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    command.args(args).envs(env).stdin(Stdio::piped());
+    // A step that can be killed gets a process group of its own, so whatever
+    // it started (an `ssh`, say) is killed along with it
+    #[cfg(unix)]
+    if timeout.is_some() || cancel.is_some() {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    // Both streams share one pipe so their lines stay in order
+    let combined = match output {
+        Output::Inherit => {
+            command.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+            None
+        }
+        Output::Tee => {
+            command.stdout(Stdio::piped()).stderr(Stdio::inherit());
+            None
+        }
+        Output::Collect | Output::Transcribe(_) => {
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+            None
+        }
+        Output::Combined(_) => {
+            let (reader, writer) = io::pipe().context("Failed to create output pipe")?;
+            command.stdout(writer.try_clone()?).stderr(writer);
+            Some(reader)
+        }
+    };
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("Failed to spawn interpreter: {}", interpreter.join(" ")))?;
+    // Close our ends of the combined pipe, or reading it would never see EOF
+    drop(command);
+    // Combined output goes to the TUI, which keeps the terminal for itself
+    let _foreground = match output {
+        Output::Combined(_) => None,
+        _ if timeout.is_some() => Some(Foreground::claim(&child)),
+        _ => None,
+    };
+
+    // Start reading before writing the script so a chatty step can't fill the pipe
+    let transcript = match output {
+        Output::Transcribe(file) => Some(file),
+        _ => None,
+    };
+    let stdout_copies = || -> Result<Vec<Box<dyn Write + Send>>> {
+        let mut copies: Vec<Box<dyn Write + Send>> = Vec::new();
+        if matches!(output, Output::Tee | Output::Transcribe(_)) {
+            copies.push(Box::new(io::stdout()));
+        }
+        if let Some(file) = transcript {
+            copies.push(Box::new(Transcript(file.try_clone()?)));
+        }
+        Ok(copies)
+    };
+    let reader = match (combined, child.stdout.take()) {
+        (Some(out), _) => Some(thread::spawn(move || read_output(out, Vec::new()))),
+        (None, Some(out)) => {
+            let copies = stdout_copies()?;
+            Some(thread::spawn(move || read_output(out, copies)))
+        }
+        (None, None) => None,
+    };
+    // Transcribed stderr is only copied, collected stderr only captured
+    let stderr_reader = match (transcript, child.stderr.take()) {
+        (Some(file), Some(err)) => {
+            let copies: Vec<Box<dyn Write + Send>> = vec![
+                Box::new(io::stderr()),
+                Box::new(Transcript(file.try_clone()?)),
+            ];
+            Some(thread::spawn(move || read_output(err, copies)))
+        }
+        (None, Some(err)) => Some(thread::spawn(move || read_output(err, Vec::new()))),
+        _ => None,
+    };
+
+    // Write the script from a thread too, or a step that stops reading stdin
+    // with more of the script left than fits the pipe would hang us before
+    // the timeout starts
+    let writer = child.stdin.take().map(|mut stdin| {
+        let script = format!("{}\n", script);
+        // stdin is closed when dropped so the interpreter sees EOF
+        thread::spawn(move || stdin.write_all(script.as_bytes()))
+    });
+
+    let status = wait_with_timeout(&mut child, timeout, cancel)?;
+    if let Some(writer) = writer.filter(|_| status.is_some()) {
+        // A step may exit without reading all of its script
+        match writer.join() {
+            Ok(Err(e)) if e.kind() != io::ErrorKind::BrokenPipe => {
+                return Err(e).context("Failed to write the step to its interpreter")
+            }
+            _ => {}
+        }
+    }
+    let stderr = stderr_reader
+        .filter(|_| status.is_some())
+        .map(|reader| reader.join().unwrap_or_default())
+        .filter(|_| matches!(output, Output::Collect));
+    let captured = match reader {
+        // Leftover background processes may still hold the pipe open after a kill
+        Some(_) if status.is_none() => Some(String::new()),
+        Some(reader) => Some(reader.join().unwrap_or_default()),
+        None => None,
+    };
+    Ok((status, captured, stderr))
+}
+
+/// How `code` would be run, as a shell command reading a quoted heredoc
+///
+/// The command is the one the step is started with, so `dir=`, `{sudo}`
+/// (as `sudo_cmd`), `host=` and `interpreters` overrides all show. The
+/// heredoc delimiter is the shell export's. Display-only blocks say that
+/// they aren't run.
+pub fn invocation_preview(
+    code: &CodeBlock,
+    env: &HashMap<String, String>,
+    sudo_cmd: &str,
+    interpreters: &HashMap<String, Vec<String>>,
+) -> String {
+    let interpreter = code.interpreter_argv_with(interpreters);
+    if interpreter.is_empty() {
+        return format!("not run: no interpreter for '{}'", code.language);
+    }
+
+    let argv = command_line(code, &interpreter, env, sudo_cmd);
+    let mut command: Vec<String> = argv.iter().map(|word| shell_quote(word)).collect();
+    if code.remote_host.is_none() {
+        if let Some(dir) = code.working_dir() {
+            command.insert(0, format!("cd {} &&", shell_quote(&dir.to_string_lossy())));
+        }
+    }
+    format!(
+        "{} <<'{}'\n{}\n{}",
+        command.join(" "),
+        HEREDOC_DELIMITER,
+        code.content,
+        HEREDOC_DELIMITER
+    )
+}
+
+/// The program and arguments that run `code`, which reads its script from stdin
+///
+/// `{sudo}` steps are prefixed with `sudo_cmd`. Since sudo resets the
+/// environment and ssh doesn't forward it, `env` is passed through
+/// `env KEY=VALUE` in those cases. The step's content never appears on the
+/// command line, so it needs no quoting.
+fn command_line(
+    code: &CodeBlock,
+    interpreter: &[String],
+    env: &HashMap<String, String>,
+    sudo_cmd: &str,
+) -> Vec<String> {
+    let mut argv: Vec<String> = Vec::new();
+    if code.requires_sudo() {
+        argv.extend(sudo_cmd.split_whitespace().map(str::to_string));
+    }
+    if (code.requires_sudo() || code.remote_host.is_some()) && !env.is_empty() {
+        let mut vars: Vec<String> = env.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        vars.sort();
+        argv.push("env".to_string());
+        argv.extend(vars);
+    }
+    argv.extend(interpreter.iter().cloned());
+
+    match &code.remote_host {
+        Some(host) => remote_command_line(host, code.working_dir().as_deref(), &argv),
+        None => argv,
+    }
+}
+
+/// Run `argv` on `host` over SSH, in `dir` if given
+///
+/// ssh hands the remote shell a single command string, so every word is quoted.
+/// `--` keeps a host starting with `-` from being read as an ssh option.
+fn remote_command_line(host: &str, dir: Option<&Path>, argv: &[String]) -> Vec<String> {
+    let mut command: Vec<String> = argv.iter().map(|word| shell_quote(word)).collect();
+    if let Some(dir) = dir {
+        let cd = format!("cd {} &&", shell_quote(&dir.to_string_lossy()));
+        command.insert(0, cd);
+    }
+    vec![
+        "ssh".to_string(),
+        "--".to_string(),
+        host.to_string(),
+        command.join(" "),
+    ]
+}
+
+/// Quote `word` for a POSIX shell, leaving plain words as they are
+fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@+%".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// Read a child's output to the end, writing it to each of `copies` as it arrives
+fn read_output(mut out: impl Read, mut copies: Vec<Box<dyn Write + Send>>) -> String {
+    let mut captured = Vec::new();
+    let mut buf = [0u8; 4096];
+    while let Ok(n) = out.read(&mut buf) {
+        if n == 0 {
+            break;
+        }
+        for copy in &mut copies {
+            let _ = copy.write_all(&buf[..n]);
+            let _ = copy.flush();
+        }
+        captured.extend_from_slice(&buf[..n]);
+    }
+    String::from_utf8_lossy(&captured).into_owned()
+}
+
+/// A transcript file, which gets step output with ANSI escapes stripped
+struct Transcript(File);
+
+impl Write for Transcript {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write_all(&strip_ansi(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Wait for `child`, killing it if it outlives `timeout` or `cancel` is set
+fn wait_with_timeout(
+    child: &mut Child,
+    timeout: Option<Duration>,
+    cancel: Option<&AtomicBool>,
+) -> Result<Option<ExitStatus>> {
+    if timeout.is_none() && cancel.is_none() {
+        return child.wait().map(Some).context("Failed to wait for step");
+    }
+
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for step")? {
+            return Ok(Some(status));
+        }
+
+        let expired = timeout.is_some_and(|limit| started.elapsed() >= limit);
+        if expired || cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+            // The process may have exited between try_wait and kill
+            kill_group(child);
+            let _ = child.wait();
+            return Ok(None);
+        }
+
+        thread::sleep(TIMEOUT_POLL_INTERVAL);
+    }
+}
+
+/// Kill `child` and the rest of its process group
+#[cfg(unix)]
+fn kill_group(child: &mut Child) {
+    // SAFETY: the child hasn't been waited for, so its pid (and with it the
+    // group id) can't have been reused
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(child: &mut Child) {
+    let _ = child.kill();
+}
+
+/// Hands the terminal to a step's process group until dropped
+///
+/// A step in a group of its own is otherwise a background job: Ctrl-C never
+/// reaches it, and prompting on the terminal (as `sudo` or `ssh` do) stops it.
+struct Foreground {
+    /// Whether the terminal was handed over and must be taken back
+    #[cfg_attr(not(unix), allow(dead_code))]
+    claimed: bool,
+}
+
+impl Foreground {
+    #[cfg(unix)]
+    fn claim(child: &Child) -> Self {
+        let group = child.id() as libc::pid_t;
+        // SAFETY: plain calls on stdin's descriptor and process ids we own
+        let claimed = unsafe {
+            libc::isatty(libc::STDIN_FILENO) == 1
+                && libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp()
+                && libc::tcsetpgrp(libc::STDIN_FILENO, group) == 0
+        };
+        if claimed {
+            // It may have been stopped for using the terminal before it had it
+            // SAFETY: as above
+            unsafe {
+                libc::kill(-group, libc::SIGCONT);
+            }
+        }
+        Self { claimed }
+    }
+
+    #[cfg(not(unix))]
+    fn claim(_child: &Child) -> Self {
+        Self { claimed: false }
+    }
+}
+
+impl Drop for Foreground {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if self.claimed {
+            // Taking the terminal back from the background would stop us
+            // SAFETY: only the disposition of SIGTTOU changes, and is restored
+            unsafe {
+                let previous = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+                libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+                libc::signal(libc::SIGTTOU, previous);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sudo_steps_run_under_sudo_cmd() {
+        let mut code = CodeBlock::new("python", "print(1)", 1);
+        let env = HashMap::from([("APP".to_string(), "api".to_string())]);
+        assert_eq!(
+            command_line(&code, &["python3".into()], &env, "sudo"),
+            vec!["python3"]
+        );
+
+        code.flags.push("sudo".to_string());
+        assert_eq!(
+            command_line(&code, &["python3".into()], &HashMap::new(), "doas"),
+            vec!["doas", "python3"]
+        );
+        assert_eq!(
+            command_line(&code, &["python3".into()], &env, "sudo -n"),
+            vec!["sudo", "-n", "env", "APP=api", "python3"]
+        );
+    }
+
+    #[test]
+    fn test_strict_script_only_changes_shell_steps() {
+        let none = HashMap::new();
+        let bash = CodeBlock::new("bash", "false | true", 1);
+        assert_eq!(
+            strict_script(&bash, &bash.content, &none),
+            "set -euo pipefail\nfalse | true"
+        );
+        let sh = CodeBlock::new("sh", "ls", 1);
+        assert_eq!(strict_script(&sh, &sh.content, &none), "set -eu\nls");
+        for language in ["fish", "python"] {
+            let code = CodeBlock::new(language, "echo hi", 1);
+            assert_eq!(strict_script(&code, &code.content, &none), "echo hi");
+        }
+    }
+
+    #[test]
+    fn test_strict_script_follows_the_resolved_interpreter() {
+        let none = HashMap::new();
+        let python = CodeBlock::new("bash", "#!/usr/bin/env python3\nprint(1)", 1);
+        assert_eq!(
+            strict_script(&python, &python.content, &none),
+            python.content
+        );
+        let bash = CodeBlock::new("sh", "#!/bin/bash\nls", 1);
+        assert_eq!(
+            strict_script(&bash, &bash.content, &none),
+            "set -euo pipefail\n#!/bin/bash\nls"
+        );
+
+        let overrides = HashMap::from([("sh".to_string(), vec!["dash".to_string()])]);
+        let sh = CodeBlock::new("sh", "ls", 1);
+        assert_eq!(strict_script(&sh, &sh.content, &overrides), "ls");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_kills_what_the_step_started() {
+        let marker = std::env::temp_dir().join(format!("sysadmin-orphan-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        let script = format!("(sleep 1; touch '{}') &\nsleep 5", marker.display());
+        let code = CodeBlock::new("bash", &script, 1);
+        let (env, interpreters) = (HashMap::new(), HashMap::new());
+
+        let timeout = Some(Duration::from_millis(200));
+        let status = run_code(&code, &script, &env, timeout, "sudo", &interpreters).unwrap();
+        assert!(status.is_none());
+        thread::sleep(Duration::from_millis(1500));
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn test_timeout_covers_a_step_that_stops_reading_its_script() {
+        // bash reads its script a line at a time, so the rest stays unread
+        let script = format!("sleep 5\n{}", "# padding\n".repeat(20_000));
+        let code = CodeBlock::new("bash", &script, 1);
+        let (env, interpreters) = (HashMap::new(), HashMap::new());
+
+        let started = Instant::now();
+        let timeout = Some(Duration::from_millis(200));
+        let status = run_code(&code, &script, &env, timeout, "sudo", &interpreters).unwrap();
+        assert!(status.is_none());
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_invocation_preview() {
+        let env = HashMap::new();
+        let none = HashMap::new();
+        let python = CodeBlock::new("python", "print(1)", 1);
+        assert_eq!(
+            invocation_preview(&python, &env, "sudo", &none),
+            "python3 <<'SYSADMIN_EOF'\nprint(1)\nSYSADMIN_EOF"
+        );
+        let overrides = HashMap::from([("python".to_string(), vec!["uv".into(), "run".into()])]);
+        assert!(invocation_preview(&python, &env, "sudo", &overrides).starts_with("uv run <<"));
+
+        let mut root = CodeBlock::new("bash", "systemctl restart nginx", 1);
+        root.flags.push("sudo".to_string());
+        root.attributes
+            .insert("dir".to_string(), "/srv/my app".to_string());
+        assert_eq!(
+            invocation_preview(&root, &env, "doas", &none),
+            "cd '/srv/my app' && doas bash <<'SYSADMIN_EOF'\nsystemctl restart nginx\nSYSADMIN_EOF"
+        );
+        root.remote_host = Some("web01".to_string());
+        assert!(invocation_preview(&root, &env, "sudo", &none)
+            .starts_with("ssh -- web01 'cd '\\''/srv/my app'\\'' && sudo bash' <<"));
+
+        let yaml = CodeBlock::new("yaml", "replicas: 3", 1);
+        assert_eq!(
+            invocation_preview(&yaml, &env, "sudo", &none),
+            "not run: no interpreter for 'yaml'"
+        );
+    }
+
+    #[test]
+    fn test_remote_steps_run_over_ssh() {
+        let mut code = CodeBlock::new("bash", "df -h", 1);
+        code.remote_host = Some("backup01".to_string());
+        assert_eq!(
+            command_line(&code, &["bash".into()], &HashMap::new(), "sudo"),
+            vec!["ssh", "--", "backup01", "bash"]
+        );
+
+        code.flags.push("sudo".to_string());
+        code.attributes
+            .insert("dir".to_string(), "/srv/my backups".to_string());
+        let env = HashMap::from([("NOTE".to_string(), "it's late".to_string())]);
+        assert_eq!(
+            command_line(&code, &["bash".into()], &env, "sudo"),
+            vec![
+                "ssh",
+                "--",
+                "backup01",
+                "cd '/srv/my backups' && sudo env 'NOTE=it'\\''s late' bash"
+            ]
+        );
+        // The remote directory is never looked for locally
+        assert_eq!(working_dir(&code).unwrap(), None);
+
+        code.remote_host = Some("-oProxyCommand=touch /tmp/pwned".to_string());
+        let argv = command_line(&code, &["bash".into()], &HashMap::new(), "sudo");
+        assert_eq!(&argv[..3], ["ssh", "--", "-oProxyCommand=touch /tmp/pwned"]);
+    }
+}
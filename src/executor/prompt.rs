@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Prompt for bash, sh and other shells that read `PS1` from the environment
+const PS1_PROMPT: &str = "\x1b[1;35m[sysadmin]\x1b[0m $ ";
+
+/// zsh startup file sourcing the user's `.zshenv`
+///
+/// zsh resets `PROMPT` from its rc files, so an environment variable alone
+/// doesn't last; `ZDOTDIR` points zsh at these files instead.
+const ZSHENV: &str = r#"ZDOTDIR="$SYSADMIN_USER_ZDOTDIR"
+[[ -f "$ZDOTDIR/.zshenv" ]] && source "$ZDOTDIR/.zshenv"
+# The user's .zshenv may move ZDOTDIR, and their .zshrc is then read from there
+SYSADMIN_USER_ZDOTDIR="$ZDOTDIR"
+ZDOTDIR="$SYSADMIN_ZDOTDIR"
+"#;
+/// zsh startup file sourcing the user's `.zshrc`, then setting the prompt
+const ZSHRC: &str = r#"ZDOTDIR="$SYSADMIN_USER_ZDOTDIR"
+unset SYSADMIN_USER_ZDOTDIR SYSADMIN_ZDOTDIR
+[[ -f "$ZDOTDIR/.zshrc" ]] && source "$ZDOTDIR/.zshrc"
+PROMPT='%F{magenta}[sysadmin]%f $ '
+"#;
+
+/// Run by fish after its config files, replacing the user's `fish_prompt`
+const FISH_INIT: &str = "set -g fish_greeting ''; \
+    function fish_prompt; set_color -o magenta; echo -n '[sysadmin]'; \
+    set_color normal; echo -n ' $ '; end";
+
+/// Counter keeping the zsh startup directories of concurrent shells apart
+static ZDOTDIR_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Files written for a shell's prompt, removed again when dropped
+pub(crate) struct ShellPrompt {
+    dir: Option<PathBuf>,
+}
+
+impl ShellPrompt {
+    /// Make the shell started by `cmd` show a `[sysadmin]` marker in its prompt
+    ///
+    /// `shell_name` is the file name of the shell binary, e.g. `zsh`.
+    pub(crate) fn configure(cmd: &mut Command, shell_name: &str) -> Result<Self> {
+        let mut prompt = Self { dir: None };
+        match shell_name {
+            "zsh" => {
+                let dir = env::temp_dir().join(format!(
+                    "sysadmin-zsh-{}-{}",
+                    std::process::id(),
+                    ZDOTDIR_COUNT.fetch_add(1, Ordering::Relaxed)
+                ));
+                fs::create_dir_all(&dir)
+                    .with_context(|| format!("Failed to create {}", dir.display()))?;
+                prompt.dir = Some(dir.clone());
+                fs::write(dir.join(".zshenv"), ZSHENV)
+                    .and_then(|_| fs::write(dir.join(".zshrc"), ZSHRC))
+                    .context("Failed to write zsh startup files")?;
+
+                let user_dir = env::var_os("ZDOTDIR")
+                    .or_else(|| env::var_os("HOME"))
+                    .unwrap_or_default();
+                cmd.env("SYSADMIN_USER_ZDOTDIR", user_dir);
+                cmd.env("SYSADMIN_ZDOTDIR", &dir);
+                cmd.env("ZDOTDIR", &dir);
+            }
+            "fish" => {
+                cmd.arg("--init-command").arg(FISH_INIT);
+            }
+            _ => {
+                // bash, sh, and most others use PS1
+                cmd.env("PS1", PS1_PROMPT);
+            }
+        }
+        Ok(prompt)
+    }
+}
+
+impl Drop for ShellPrompt {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    fn env_var<'a>(cmd: &'a Command, key: &str) -> Option<&'a OsStr> {
+        cmd.get_envs()
+            .find(|(k, _)| *k == key)
+            .and_then(|(_, value)| value)
+    }
+
+    #[test]
+    fn test_zsh_prompt_is_set_after_user_rc() {
+        let mut cmd = Command::new("zsh");
+        let prompt = ShellPrompt::configure(&mut cmd, "zsh").unwrap();
+        let dir = PathBuf::from(env_var(&cmd, "ZDOTDIR").unwrap());
+
+        let zshrc = fs::read_to_string(dir.join(".zshrc")).unwrap();
+        assert!(zshrc.ends_with("PROMPT='%F{magenta}[sysadmin]%f $ '\n"));
+        assert!(env_var(&cmd, "PS1").is_none());
+
+        drop(prompt);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_fish_gets_a_prompt_function() {
+        let mut cmd = Command::new("fish");
+        let _prompt = ShellPrompt::configure(&mut cmd, "fish").unwrap();
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args[0], "--init-command");
+        assert!(args[1].to_str().unwrap().contains("function fish_prompt"));
+    }
+
+    #[test]
+    fn test_other_shells_use_ps1() {
+        let mut cmd = Command::new("bash");
+        let _prompt = ShellPrompt::configure(&mut cmd, "bash").unwrap();
+        assert_eq!(env_var(&cmd, "PS1"), Some(OsStr::new(PS1_PROMPT)));
+    }
+}
@@ -0,0 +1,172 @@
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::time::Duration;
+
+/// Exit code reported for a step that was killed for running too long
+pub const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// The outcome of one step of a batch run
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct StepResult {
+    /// 1-based step number
+    pub index: usize,
+    pub language: String,
+    /// The code as run, with variables substituted
+    pub content: String,
+    /// Exit code of the process, if it exited normally
+    pub exit_code: Option<i32>,
+    /// Whether the step was killed for exceeding the timeout
+    pub timed_out: bool,
+    /// Whether the step exited cleanly but printed something other than expected
+    pub output_mismatch: bool,
+    /// Whether the step was left out by the step selection or tag filter
+    pub skipped: bool,
+    /// When the step started, in seconds since the Unix epoch; `None` if it was skipped
+    pub started_at: Option<u64>,
+    /// How long the step took
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
+}
+
+impl StepResult {
+    pub fn succeeded(&self) -> bool {
+        !self.skipped && !self.timed_out && !self.output_mismatch && self.exit_code == Some(0)
+    }
+
+    /// Whether the step ran and didn't succeed
+    pub fn failed(&self) -> bool {
+        !self.skipped && !self.succeeded()
+    }
+}
+
+/// What happened during a batch run
+///
+/// Serializes to JSON for `run --ci --report`, with the total duration in
+/// milliseconds and whether the run succeeded.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExecutionReport {
+    /// Results of the steps that ran or were skipped, in order
+    pub steps: Vec<StepResult>,
+    /// Number of steps selected to run, including any never reached
+    pub total_steps: usize,
+    /// How long the whole run took
+    pub total_duration: Duration,
+}
+
+impl ExecutionReport {
+    /// Number of steps that ran and succeeded
+    pub fn succeeded(&self) -> usize {
+        self.steps.iter().filter(|s| s.succeeded()).count()
+    }
+
+    /// Step numbers that ran and failed
+    pub fn failed_steps(&self) -> Vec<usize> {
+        self.steps
+            .iter()
+            .filter(|s| s.failed())
+            .map(|s| s.index)
+            .collect()
+    }
+
+    /// Whether every selected step ran and succeeded
+    pub fn is_success(&self) -> bool {
+        self.succeeded() == self.total_steps
+    }
+
+    /// Process exit code for the run: 0 on success, else the first failure's code
+    pub fn exit_code(&self) -> i32 {
+        match self.steps.iter().find(|s| s.failed()) {
+            None if self.is_success() => 0,
+            None => 1,
+            Some(step) if step.timed_out => TIMEOUT_EXIT_CODE,
+            // A process killed by a signal has no exit code
+            Some(step) => step.exit_code.filter(|c| *c != 0).unwrap_or(1),
+        }
+    }
+}
+
+impl Serialize for ExecutionReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut report = serializer.serialize_struct("ExecutionReport", 4)?;
+        report.serialize_field("steps", &self.steps)?;
+        report.serialize_field("total_steps", &self.total_steps)?;
+        report.serialize_field("total_duration_ms", &self.total_duration.as_millis())?;
+        report.serialize_field("success", &self.is_success())?;
+        report.end()
+    }
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(index: usize, exit_code: Option<i32>) -> StepResult {
+        StepResult {
+            index,
+            exit_code,
+            ..StepResult::default()
+        }
+    }
+
+    #[test]
+    fn test_report_all_succeeded() {
+        let report = ExecutionReport {
+            steps: vec![result(1, Some(0)), result(2, Some(0))],
+            total_steps: 2,
+            ..ExecutionReport::default()
+        };
+        assert!(report.is_success());
+        assert_eq!(report.exit_code(), 0);
+    }
+
+    #[test]
+    fn test_report_failures() {
+        let report = ExecutionReport {
+            steps: vec![result(1, Some(0)), result(2, Some(3)), result(3, None)],
+            total_steps: 4,
+            ..ExecutionReport::default()
+        };
+        assert_eq!(report.succeeded(), 1);
+        assert_eq!(report.failed_steps(), vec![2, 3]);
+        assert!(!report.is_success());
+        assert_eq!(report.exit_code(), 3);
+    }
+
+    #[test]
+    fn test_report_output_mismatch_fails() {
+        let mut mismatched = result(1, Some(0));
+        mismatched.output_mismatch = true;
+        let report = ExecutionReport {
+            steps: vec![mismatched],
+            total_steps: 1,
+            ..ExecutionReport::default()
+        };
+        assert_eq!(report.failed_steps(), vec![1]);
+        assert_eq!(report.exit_code(), 1);
+    }
+
+    #[test]
+    fn test_report_skipped_steps_are_not_failures() {
+        let skipped = StepResult {
+            skipped: true,
+            ..result(1, None)
+        };
+        let report = ExecutionReport {
+            steps: vec![skipped, result(2, Some(0))],
+            total_steps: 1,
+            total_duration: Duration::from_millis(1500),
+        };
+        assert!(report.failed_steps().is_empty());
+        assert!(report.is_success());
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["success"], true);
+        assert_eq!(json["total_duration_ms"], 1500);
+        assert_eq!(json["steps"][0]["skipped"], true);
+        assert_eq!(json["steps"][1]["duration_ms"], 0);
+        assert!(json["steps"][1].get("duration").is_none());
+    }
+}
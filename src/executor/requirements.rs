@@ -0,0 +1,84 @@
+use anyhow::{bail, Result};
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::model::Document;
+
+/// Fail unless every required tool can be found on `PATH`
+///
+/// Entries containing a `/` are treated as paths and checked directly.
+/// The error lists every missing tool, not just the first.
+pub fn check_requirements(required: &[String]) -> Result<()> {
+    let missing: Vec<&str> = required
+        .iter()
+        .map(String::as_str)
+        .filter(|tool| find_executable(tool).is_none())
+        .collect();
+
+    if !missing.is_empty() {
+        bail!(
+            "Missing required tool(s): {} (not found on PATH; use --skip-checks to run anyway)",
+            missing.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Check the tools listed under `requires` in the document's frontmatter
+pub(crate) fn check_document_requirements(doc: &Document) -> Result<()> {
+    match &doc.metadata {
+        Some(metadata) => check_requirements(&metadata.requires),
+        None => Ok(()),
+    }
+}
+
+/// Locate an executable the way a shell would, like `which`
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    if name.contains('/') {
+        let path = PathBuf::from(name);
+        return is_executable(&path).then_some(path);
+    }
+
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_executable_on_path() {
+        assert!(find_executable("sh").is_some());
+        assert!(find_executable("definitely-not-a-real-tool-xyz").is_none());
+    }
+
+    #[test]
+    fn test_check_requirements_lists_missing_tools() {
+        assert!(check_requirements(&["sh".to_string()]).is_ok());
+
+        let required = vec![
+            "sh".to_string(),
+            "no-such-tool-a".to_string(),
+            "no-such-tool-b".to_string(),
+        ];
+        let message = check_requirements(&required).unwrap_err().to_string();
+        assert!(message.contains("no-such-tool-a, no-such-tool-b"));
+        assert!(!message.contains("sh,"));
+    }
+}
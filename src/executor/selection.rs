@@ -0,0 +1,131 @@
+use anyhow::{bail, Result};
+
+use crate::model::CodeBlock;
+
+/// Which steps of a document to execute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StepSelection {
+    /// Every step (the default)
+    #[default]
+    All,
+    /// A single 1-based step; other steps' text and headers are suppressed
+    Only(usize),
+    /// An inclusive, 1-based range of steps; surrounding text is still shown
+    Range(usize, usize),
+}
+
+impl StepSelection {
+    /// Check whether a 1-based step number is selected
+    pub fn contains(&self, step: usize) -> bool {
+        match *self {
+            StepSelection::All => true,
+            StepSelection::Only(only) => step == only,
+            StepSelection::Range(first, last) => (first..=last).contains(&step),
+        }
+    }
+
+    /// Whether documentation for unselected steps should be hidden
+    pub fn is_focused(&self) -> bool {
+        matches!(self, StepSelection::Only(_))
+    }
+
+    /// Ensure the selection refers to steps that exist
+    pub fn validate(&self, total_steps: usize) -> Result<()> {
+        match *self {
+            StepSelection::All => {}
+            StepSelection::Only(step) => {
+                if step == 0 || step > total_steps {
+                    bail!(
+                        "Cannot run step {}: document has {} step(s)",
+                        step,
+                        total_steps
+                    );
+                }
+            }
+            StepSelection::Range(first, last) => {
+                if first == 0 || first > total_steps {
+                    bail!(
+                        "Cannot start from step {}: document has {} step(s)",
+                        first,
+                        total_steps
+                    );
+                }
+                if last < first || last > total_steps {
+                    bail!(
+                        "Invalid step range {}-{}: document has {} step(s)",
+                        first,
+                        last,
+                        total_steps
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Which steps to execute based on their `tags=` attribute
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TagFilter {
+    /// If non-empty, only steps with at least one of these tags run
+    pub include: Vec<String>,
+    /// Steps with any of these tags never run
+    pub exclude: Vec<String>,
+}
+
+impl TagFilter {
+    pub fn new(include: Vec<String>, exclude: Vec<String>) -> Self {
+        Self { include, exclude }
+    }
+
+    /// Check whether a step passes the filter
+    pub fn matches(&self, code: &CodeBlock) -> bool {
+        let tags = code.tags();
+        let has_any = |wanted: &[String]| wanted.iter().any(|w| tags.contains(&w.as_str()));
+
+        (self.include.is_empty() || has_any(&self.include)) && !has_any(&self.exclude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains() {
+        assert!(StepSelection::All.contains(7));
+        assert!(StepSelection::Only(3).contains(3));
+        assert!(!StepSelection::Only(3).contains(4));
+        assert!(StepSelection::Range(2, 4).contains(4));
+        assert!(!StepSelection::Range(2, 4).contains(1));
+    }
+
+    #[test]
+    fn test_tag_filter() {
+        let mut rollback = CodeBlock::new("bash", "undo", 1);
+        rollback
+            .attributes
+            .insert("tags".to_string(), "rollback,slow".to_string());
+        let untagged = CodeBlock::new("bash", "ls", 5);
+
+        assert!(TagFilter::default().matches(&untagged));
+
+        let only_rollback = TagFilter::new(vec!["rollback".to_string()], vec![]);
+        assert!(only_rollback.matches(&rollback));
+        assert!(!only_rollback.matches(&untagged));
+
+        let skip_slow = TagFilter::new(vec![], vec!["slow".to_string()]);
+        assert!(!skip_slow.matches(&rollback));
+        assert!(skip_slow.matches(&untagged));
+    }
+
+    #[test]
+    fn test_validate_bounds() {
+        assert!(StepSelection::All.validate(0).is_ok());
+        assert!(StepSelection::Only(2).validate(2).is_ok());
+        assert!(StepSelection::Only(3).validate(2).is_err());
+        assert!(StepSelection::Only(0).validate(2).is_err());
+        assert!(StepSelection::Range(2, 5).validate(5).is_ok());
+        assert!(StepSelection::Range(6, 6).validate(5).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use pulldown_cmark::{html, Event, Options, Parser};
+
+use super::markdown::step_anchor;
+use crate::model::{danger_patterns, Block, CalloutKind, CodeBlock, Document};
+
+/// Stylesheet inlined into every exported page
+const STYLESHEET: &str = "\
+body { font-family: sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.5; }
+pre { background: #f5f5f5; border-left: 4px solid #888; padding: 0.75rem; overflow-x: auto; }
+pre.danger { border-left-color: #c0392b; background: #fdf0ef; }
+pre.listing, pre.output { border-left-color: #ccc; color: #555; }
+.step-title { font-weight: bold; margin-top: 1.5rem; }
+.step-title .danger { color: #c0392b; }
+.step-title .note { font-weight: normal; color: #777; }
+.callout { border-left: 4px solid #888; margin: 1rem 0; padding: 0.25rem 1rem; }
+.callout.warning { border-left-color: #e67e22; }
+.callout.danger { border-left-color: #c0392b; }
+.callout.tip { border-left-color: #27ae60; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.25rem 0.5rem; }
+ul.checklist { list-style: none; padding-left: 1rem; }
+";
+
+/// Export a document as a self-contained HTML page
+///
+/// Code is wrapped in `<pre><code class="language-...">` for client-side
+/// highlighters, and dangerous steps get a `danger` class. Steps carry the
+/// same `step-N` anchors as the markdown export, and headers their section ids.
+/// Raw HTML in the runbook's text is left out, so the page runs no scripts.
+pub fn to_html(doc: &Document) -> String {
+    to_html_with(doc, &danger_patterns(&[]))
+}
+
+/// Like [`to_html`], marking steps dangerous by `patterns` from [`danger_patterns`]
+pub fn to_html_with(doc: &Document, patterns: &[String]) -> String {
+    let title = doc
+        .metadata
+        .as_ref()
+        .and_then(|m| m.title.as_deref())
+        .or_else(|| doc.sections.iter().find_map(|s| s.header.as_deref()))
+        .unwrap_or("Runbook");
+
+    let mut page = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    page.push_str(&format!("<title>{}</title>\n", escape(title)));
+    page.push_str(&format!("<style>\n{}</style>\n", STYLESHEET));
+    page.push_str("</head>\n<body>\n");
+
+    let mut step = 0;
+    for section in &doc.sections {
+        if let Some(header) = &section.header {
+            let level = section.header_level.unwrap_or(1).clamp(1, 6);
+            let id = section
+                .id
+                .as_ref()
+                .map(|id| format!(" id=\"{}\"", escape(id)))
+                .unwrap_or_default();
+            page.push_str(&format!("<h{0}{1}>{2}</h{0}>\n", level, id, escape(header)));
+        }
+
+        for block in &section.blocks {
+            match block {
+                Block::Text { raw: Some(raw), .. } => push_markdown(&mut page, raw),
+                Block::Text { text, raw: None } => {
+                    for paragraph in text.lines().filter(|line| !line.trim().is_empty()) {
+                        page.push_str(&format!("<p>{}</p>\n", escape(paragraph)));
+                    }
+                }
+                Block::Table { headers, rows } => push_table(&mut page, headers, rows),
+                Block::Callout { kind, text } => push_callout(&mut page, *kind, text),
+                Block::Checklist { items } => push_checklist(&mut page, items),
+                Block::Code(code) if code.is_executable() => {
+                    step += 1;
+                    push_step(&mut page, step, code, patterns);
+                }
+                Block::Code(code) => {
+                    page.push_str(&format!(
+                        "<pre class=\"listing\"><code class=\"language-{}\">{}</code></pre>\n",
+                        escape(&code.language),
+                        escape(&code.content)
+                    ));
+                }
+            }
+        }
+    }
+
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+/// A numbered step with its anchor, code and any expected output
+fn push_step(page: &mut String, step: usize, code: &CodeBlock, patterns: &[String]) {
+    let dangerous = code.is_dangerous_with(patterns);
+    let marker = if dangerous {
+        " <span class=\"danger\">⚠ dangerous</span>"
+    } else {
+        ""
+    };
+    let note = code
+        .note()
+        .map(|note| format!(" <span class=\"note\">{}</span>", escape(note)))
+        .unwrap_or_default();
+    page.push_str(&format!(
+        "<div class=\"step-title\" id=\"{}\">Step {} [{}]{}{}</div>\n",
+        step_anchor(step),
+        step,
+        escape(&code.language),
+        marker,
+        note
+    ));
+    let class = if dangerous { " class=\"danger\"" } else { "" };
+    page.push_str(&format!(
+        "<pre{}><code class=\"language-{}\">{}</code></pre>\n",
+        class,
+        escape(&code.language),
+        escape(&code.content)
+    ));
+    if let Some(expected) = &code.expected_output {
+        page.push_str(&format!(
+            "<pre class=\"output\"><code>{}</code></pre>\n",
+            escape(expected)
+        ));
+    }
+}
+
+/// Render a text block's source markdown, dropping any raw HTML in it
+fn push_markdown(page: &mut String, markdown: &str) {
+    let parser = Parser::new_ext(markdown, Options::ENABLE_TABLES)
+        .filter(|event| !matches!(event, Event::Html(_) | Event::InlineHtml(_)));
+    html::push_html(page, parser);
+}
+
+fn push_table(page: &mut String, headers: &[String], rows: &[Vec<String>]) {
+    page.push_str("<table>\n<tr>");
+    for header in headers {
+        page.push_str(&format!("<th>{}</th>", escape(header)));
+    }
+    page.push_str("</tr>\n");
+    for row in rows {
+        page.push_str("<tr>");
+        for cell in row {
+            page.push_str(&format!("<td>{}</td>", escape(cell)));
+        }
+        page.push_str("</tr>\n");
+    }
+    page.push_str("</table>\n");
+}
+
+fn push_checklist(page: &mut String, items: &[(bool, String)]) {
+    page.push_str("<ul class=\"checklist\">\n");
+    for (checked, text) in items {
+        let checked = if *checked { " checked" } else { "" };
+        page.push_str(&format!(
+            "<li><input type=\"checkbox\" disabled{}> {}</li>\n",
+            checked,
+            escape(text)
+        ));
+    }
+    page.push_str("</ul>\n");
+}
+
+fn push_callout(page: &mut String, kind: CalloutKind, text: &str) {
+    let class = kind.label().unwrap_or("quote").to_lowercase();
+    page.push_str(&format!("<blockquote class=\"callout {}\">\n", class));
+    if let Some(label) = kind.label() {
+        page.push_str(&format!("<strong>{}:</strong> ", label));
+    }
+    page.push_str(&escape(text).replace('\n', "<br>\n"));
+    page.push_str("\n</blockquote>\n");
+}
+
+/// Escape text for use in HTML content and attribute values
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::SysadminParser;
+
+    #[test]
+    fn test_html_export_wraps_code_for_highlighters() {
+        let content = "---\ntitle: Cleanup & restart\n---\n# Cleanup\n\nFree some **space**.\n\n```bash\nrm -rf /tmp/cache\n```\n\n```python\nprint(1 < 2)\n```\n\n```yaml\nkey: value\n```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+
+        let page = to_html(&doc);
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<title>Cleanup &amp; restart</title>"));
+        assert!(page.contains("<h1 id=\"cleanup\">Cleanup</h1>"));
+        assert!(page.contains("<p>Free some <strong>space</strong>.</p>"));
+        assert!(page.contains(
+            "<pre class=\"danger\"><code class=\"language-bash\">rm -rf /tmp/cache</code></pre>"
+        ));
+        assert!(page.contains("<pre><code class=\"language-python\">print(1 &lt; 2)</code></pre>"));
+        assert!(page.contains("<pre class=\"listing\"><code class=\"language-yaml\">"));
+        assert!(page.contains("id=\"step-1\"") && page.contains("id=\"step-2\""));
+        assert!(!page.contains("id=\"step-3\""));
+    }
+
+    #[test]
+    fn test_html_export_drops_raw_html() {
+        let content = "# Restart\n\n<script>alert(1)</script>\n\nClick <img src=x onerror=\"alert(2)\"> **here**. <!-- sysadmin: owner=ops -->\n\n```bash\nsystemctl restart nginx\n```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+
+        let page = to_html(&doc);
+        assert!(
+            !page.contains("<script") && !page.contains("<img"),
+            "{}",
+            page
+        );
+        assert!(!page.contains("<!--"));
+        assert!(
+            page.contains("<p>Click  <strong>here</strong>. </p>"),
+            "{}",
+            page
+        );
+    }
+
+    #[test]
+    fn test_html_export_uses_configured_danger_patterns() {
+        let doc = SysadminParser::parse(
+            "```bash
+systemctl restart nginx
+```
+",
+        )
+        .unwrap();
+        assert!(!to_html(&doc).contains("pre class=\"danger\""));
+
+        let page = to_html_with(&doc, &danger_patterns(&["systemctl restart".to_string()]));
+        assert!(page.contains("<pre class=\"danger\">"));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::model::{Block, Document};
+
+/// The anchor id of step `index` (1-based), e.g. `step-3`
+///
+/// Steps are numbered as in a run, so the same id links to the same step
+/// in every export format.
+pub fn step_anchor(index: usize) -> String {
+    format!("step-{}", index)
+}
+
+/// Export a document as normalized markdown with an anchor before each step
+///
+/// Each step is preceded by `<a id="step-N"></a>`, so a ticket can link
+/// straight to `runbook.md#step-N`. Everything else is written as `sysadmin
+/// fmt` would write it.
+pub fn to_markdown(doc: &Document) -> String {
+    let mut anchored = doc.clone();
+    let mut step = 0;
+    for section in &mut anchored.sections {
+        let blocks = std::mem::take(&mut section.blocks);
+        for block in blocks {
+            if matches!(&block, Block::Code(code) if code.is_executable()) {
+                step += 1;
+                let anchor = format!("<a id=\"{}\"></a>", step_anchor(step));
+                section.blocks.push(Block::Text {
+                    text: anchor.clone(),
+                    raw: Some(anchor),
+                });
+            }
+            section.blocks.push(block);
+        }
+    }
+    anchored.to_markdown()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::SysadminParser;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_each_step_gets_a_unique_anchor() {
+        let content = "# Deploy\n\n```bash\nls\n```\n\n```yaml\nkey: value\n```\n\n## Verify\n\n```bash\ncurl localhost\n```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+
+        let markdown = to_markdown(&doc);
+        let anchors: Vec<&str> = markdown
+            .lines()
+            .filter(|line| line.starts_with("<a id="))
+            .collect();
+        assert_eq!(
+            anchors,
+            vec!["<a id=\"step-1\"></a>", "<a id=\"step-2\"></a>"]
+        );
+        assert_eq!(anchors.iter().collect::<HashSet<_>>().len(), anchors.len());
+        assert!(markdown.contains("<a id=\"step-2\"></a>\n\n```bash\ncurl localhost\n```"));
+
+        // The anchors are plain text to the parser, not steps
+        assert_eq!(SysadminParser::parse(&markdown).unwrap().step_count(), 2);
+    }
+}
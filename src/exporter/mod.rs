@@ -0,0 +1,7 @@
+pub mod html;
+pub mod markdown;
+pub mod shell;
+
+pub use html::{to_html, to_html_with};
+pub use markdown::{step_anchor, to_markdown};
+pub use shell::to_shell_script;
@@ -0,0 +1,146 @@
+use crate::model::{Block, CodeBlock, Document};
+
+/// Delimiter for heredocs wrapping non-shell steps
+pub(crate) const HEREDOC_DELIMITER: &str = "SYSADMIN_EOF";
+
+/// Flatten a document's steps into a single bash script
+///
+/// `bash` and `sh` steps are emitted inline. Steps in any other language, or
+/// with their own shebang line, are piped to their interpreter through a
+/// quoted heredoc so their content is passed through untouched. Blocks in a
+/// language with no known interpreter are included as comments.
+pub fn to_shell_script(doc: &Document) -> String {
+    let mut script = String::from("#!/usr/bin/env bash\n");
+    match doc.metadata.as_ref().and_then(|m| m.title.as_deref()) {
+        Some(title) => script.push_str(&format!("# {}\n", title)),
+        None => script.push_str("# Exported runbook\n"),
+    }
+    script.push_str("# Generated by sysadmin export - edit the source runbook instead.\n");
+
+    let mut step = 0;
+    for section in &doc.sections {
+        for block in &section.blocks {
+            let Block::Code(code) = block else {
+                continue;
+            };
+            if !code.is_executable() {
+                script.push('\n');
+                push_display_only(&mut script, code);
+                continue;
+            }
+            step += 1;
+
+            script.push('\n');
+            script.push_str(&format!("# {}\n", "-".repeat(60)));
+            match &section.header {
+                Some(header) => script.push_str(&format!("# Step {}: {}\n", step, header)),
+                None => script.push_str(&format!("# Step {}\n", step)),
+            }
+            script.push_str(&format!("# {}\n", "-".repeat(60)));
+            push_step(&mut script, code);
+        }
+    }
+
+    script
+}
+
+fn push_step(script: &mut String, code: &CodeBlock) {
+    let inline = matches!(code.language.as_str(), "bash" | "sh")
+        && code.shebang_interpreter().is_none()
+        && !code.requires_sudo()
+        && code.remote_host.is_none();
+    if inline {
+        script.push_str(&code.content);
+        script.push('\n');
+        return;
+    }
+
+    let Some(interpreter) = code.effective_interpreter() else {
+        push_display_only(script, code);
+        return;
+    };
+
+    // Root and remote steps wrap the interpreter, with the content still in a heredoc
+    let ssh = match &code.remote_host {
+        Some(host) => format!("ssh {} ", host),
+        None => String::new(),
+    };
+    let sudo = if code.requires_sudo() { "sudo " } else { "" };
+    script.push_str(&format!(
+        "{}{}{} <<'{}'\n",
+        ssh, sudo, interpreter, HEREDOC_DELIMITER
+    ));
+    script.push_str(&code.content);
+    script.push('\n');
+    script.push_str(HEREDOC_DELIMITER);
+    script.push('\n');
+}
+
+/// Keep a display-only block for reference, commented out so it never runs
+fn push_display_only(script: &mut String, code: &CodeBlock) {
+    script.push_str(&format!(
+        "# Display-only {} block, not executed:\n",
+        code.language
+    ));
+    for line in code.content.lines() {
+        script.push_str(&format!("# {}\n", line));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::SysadminParser;
+
+    #[test]
+    fn test_shell_steps_are_inlined_with_section_headers() {
+        let doc =
+            SysadminParser::parse("# Deploy\n\n## Stop app\n\n```bash\nsystemctl stop app\n```\n")
+                .unwrap();
+
+        let script = to_shell_script(&doc);
+        assert!(script.starts_with("#!/usr/bin/env bash\n"));
+        assert!(script.contains("# Step 1: Stop app\n"));
+        assert!(script.contains("\nsystemctl stop app\n"));
+        assert!(!script.contains(HEREDOC_DELIMITER));
+    }
+
+    #[test]
+    fn test_non_shell_steps_use_interpreter_heredoc() {
+        let doc = SysadminParser::parse("```python\nprint('hi')\n```\n").unwrap();
+
+        let script = to_shell_script(&doc);
+        assert!(script.contains("# Step 1\n"));
+        assert!(script.contains("python3 <<'SYSADMIN_EOF'\nprint('hi')\nSYSADMIN_EOF\n"));
+    }
+
+    #[test]
+    fn test_sudo_steps_run_their_interpreter_under_sudo() {
+        let doc = SysadminParser::parse("```bash {sudo}\necho 'it''s' \"$HOME\"\n```\n").unwrap();
+
+        let script = to_shell_script(&doc);
+        assert!(
+            script.contains("sudo bash <<'SYSADMIN_EOF'\necho 'it''s' \"$HOME\"\nSYSADMIN_EOF\n")
+        );
+    }
+
+    #[test]
+    fn test_remote_steps_are_piped_over_ssh() {
+        let doc =
+            SysadminParser::parse("## Backup {host=backup01}\n\n```bash {sudo}\ndf -h\n```\n")
+                .unwrap();
+
+        let script = to_shell_script(&doc);
+        assert!(script.contains("ssh backup01 sudo bash <<'SYSADMIN_EOF'\ndf -h\nSYSADMIN_EOF\n"));
+    }
+
+    #[test]
+    fn test_unknown_languages_are_commented_out() {
+        let doc = SysadminParser::parse("```yaml\nreplicas: 3\n```\n").unwrap();
+
+        let script = to_shell_script(&doc);
+        assert!(script.contains("# Display-only yaml block, not executed:\n# replicas: 3\n"));
+        assert!(!script.contains("# Step"));
+        assert!(!script.contains(HEREDOC_DELIMITER));
+    }
+}
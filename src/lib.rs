@@ -1,8 +1,12 @@
+pub mod config;
 pub mod executor;
+pub mod exporter;
 pub mod model;
 pub mod parser;
 pub mod ui;
+pub mod validator;
 
 // Re-export commonly used types
-pub use model::{Block, CodeBlock, Document, Section};
+pub use model::{Block, CodeBlock, Document, DocumentMetadata, Section};
 pub use parser::SysadminParser;
+pub use validator::{Diagnostic, Severity};
@@ -1,27 +1,52 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use clap::Parser;
-use std::fs;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
 
 mod cli;
-mod executor;
-mod model;
-mod parser;
-mod ui;
 
-use cli::{Cli, Commands};
-use executor::InteractiveExecutor;
-use parser::SysadminParser;
-use ui::TuiApp;
+use cli::{Cli, Commands, ExportFormat};
+use usr_bin_sysadmin::config::Config;
+use usr_bin_sysadmin::executor::{
+    invocation_preview, AuditLog, BatchExecutor, Checkpoint, InteractiveExecutor, StepSelection,
+    TagFilter,
+};
+use usr_bin_sysadmin::exporter;
+use usr_bin_sysadmin::model::{danger_patterns, Block, Document, Redactor};
+use usr_bin_sysadmin::parser::{
+    expand_includes_with_map, runbook_files, split_frontmatter, ParseOptions, ParseWarning,
+    SourceMap, SysadminParser,
+};
+use usr_bin_sysadmin::ui::{Renderer, Theme, TuiApp};
+use usr_bin_sysadmin::validator;
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let config = Config::load()?;
+    // `--theme` overrides the config file, which overrides NO_COLOR
+    let theme = match cli.theme.as_deref().or(config.theme.as_deref()) {
+        Some(name) => Theme::from_name(name)?,
+        None => Theme::from_env(),
+    };
 
     // Determine which file to process
     let file_path = match &cli.command {
-        Some(Commands::Run { file }) => file,
-        Some(Commands::Tui { file }) => file,
-        Some(Commands::DryRun { file }) => file,
-        Some(Commands::View { file }) => file,
+        Some(Commands::Run { file, .. }) => file,
+        Some(Commands::Tui { file, .. }) => file,
+        Some(Commands::DryRun { file, .. }) => file,
+        Some(Commands::List { file }) => file,
+        Some(Commands::Stats { file }) => file,
+        Some(Commands::View { file, .. }) => file,
+        Some(Commands::Validate { file }) => file,
+        Some(Commands::Fmt { file, .. }) => file,
+        Some(Commands::Export { file, .. }) => file,
         None => {
             if let Some(file) = &cli.file {
                 file
@@ -32,48 +57,747 @@ fn main() -> Result<()> {
                 eprintln!("       sysadmin run <file.sysadmin>");
                 eprintln!("       sysadmin tui <file.sysadmin>");
                 eprintln!("       sysadmin dry-run <file.sysadmin>");
+                eprintln!("       sysadmin list <file.sysadmin>");
+                eprintln!("       sysadmin stats <file.sysadmin>");
                 eprintln!("       sysadmin view <file.sysadmin>");
+                eprintln!("       sysadmin validate <file.sysadmin>");
+                eprintln!("       sysadmin fmt <file.sysadmin> [--check]");
+                eprintln!("       sysadmin export <file.sysadmin> [--format sh] [-o out.sh]");
                 std::process::exit(1);
             }
         }
     };
 
-    // Read the file
-    let content = fs::read_to_string(file_path)
-        .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
+    // Read the file, or stdin for `-`
+    let from_stdin = file_path.as_os_str() == "-";
+    let from_dir = !from_stdin && file_path.is_dir();
+    if from_dir
+        && matches!(
+            cli.command,
+            Some(Commands::Fmt { .. })
+                | Some(Commands::Validate { .. })
+                | Some(Commands::View { raw: true, .. })
+        )
+    {
+        bail!(
+            "{} is a directory; this command works on a single runbook",
+            file_path.display()
+        );
+    }
+    let content = if from_stdin {
+        io::read_to_string(io::stdin()).context("Failed to read runbook from stdin")?
+    } else if from_dir {
+        // Only used to tell whether a checkpoint is still current
+        let mut content = String::new();
+        for path in runbook_files(file_path)? {
+            content += &fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        }
+        content
+    } else {
+        fs::read_to_string(file_path)
+            .with_context(|| format!("Failed to read file: {}", file_path.display()))?
+    };
+
+    // Expand `@include` lines, except where the file itself is wanted
+    let verbatim = matches!(
+        cli.command,
+        Some(Commands::Fmt { .. }) | Some(Commands::View { raw: true, .. })
+    );
+    let (content, source_map) = if from_stdin || from_dir || verbatim {
+        (content, None)
+    } else {
+        let (expanded, map) = expand_includes_with_map(&content, file_path)?;
+        (expanded, Some(map))
+    };
+
+    // Prompts and dropped-to shells need a terminal, which stdin no longer is
+    let interactive = matches!(
+        cli.command,
+        None | Some(Commands::Tui { .. }) | Some(Commands::Run { ci: false, .. })
+    );
+    if from_stdin && interactive && !io::stdin().is_terminal() {
+        bail!(
+            "Runbook was read from stdin, so it can't be used for interactive control; \
+             use `sysadmin run --ci -` or pass a file path"
+        );
+    }
 
     // Parse the document
-    let document =
-        SysadminParser::parse(&content).context("Failed to parse .sysadmin document")?;
+    let options = ParseOptions {
+        indented_language: cli.allow_indented.clone(),
+        ..ParseOptions::default()
+    };
+    if let Some(Commands::View { watch: true, .. }) = &cli.command {
+        if from_stdin {
+            bail!("--watch needs a file to watch, not stdin");
+        }
+        return watch_view(file_path, &options, theme, !cli.no_warnings);
+    }
+    let (mut document, warnings) = if from_dir {
+        SysadminParser::parse_dir_with_warnings(file_path, &options)?
+    } else {
+        SysadminParser::parse_with_warnings(&content, &options)
+            .context("Failed to parse .sysadmin document")?
+    };
+
+    // The validate command reports these itself
+    if !cli.no_warnings && !matches!(cli.command, Some(Commands::Validate { .. })) {
+        print_warnings(&warnings, source_map.as_ref(), file_path);
+    }
+    // Validation reads the expanded text, so it runs before lines are mapped back
+    let diagnostics = match (&cli.command, &source_map) {
+        (Some(Commands::Validate { .. }), Some(map)) => {
+            validator::validate_expanded(&content, &document, map)
+        }
+        (Some(Commands::Validate { .. }), None) => validator::validate(&content, &document),
+        _ => Vec::new(),
+    };
+    if let Some(map) = &source_map {
+        map.remap(&mut document);
+    }
 
     // Execute based on command
     match &cli.command {
-        None | Some(Commands::Run { .. }) => {
+        None => {
             // Default: interactive execution
-            let mut executor = InteractiveExecutor::new();
+            let mut executor = configured_executor(&config, &document, None)
+                .with_theme(theme)
+                .with_env(step_env(&document, &[]))
+                .with_redactor(document_redactor(&document)?)
+                .with_warnings(!cli.no_warnings);
+            if !from_stdin {
+                let (checkpoint, resume_from) =
+                    resume_checkpoint(file_path, &content, document.step_count())?;
+                if let Some(step) = resume_from {
+                    executor =
+                        executor.with_selection(StepSelection::Range(step, document.step_count()));
+                }
+                executor = executor.with_checkpoint(checkpoint);
+            }
+            executor.execute(&document)?;
+        }
+        Some(Commands::Run {
+            vars,
+            env,
+            strict_vars,
+            log,
+            transcript,
+            from,
+            only,
+            from_section,
+            danger_patterns,
+            auto_run,
+            no_auto_run,
+            timeout,
+            ci,
+            continue_on_error,
+            report,
+            pager,
+            section_gate,
+            skip_checks,
+            tags,
+            skip_tags,
+            sudo_cmd,
+            strict_shell,
+            shell,
+            ..
+        }) => {
+            let from = match from_section {
+                Some(id) => Some(section_start(&document, id)?),
+                None => *from,
+            };
+            let mut selection = match (only, from) {
+                (Some(step), _) => StepSelection::Only(*step),
+                (None, Some(step)) => StepSelection::Range(step, document.step_count().max(step)),
+                (None, None) => StepSelection::All,
+            };
+            let tag_filter = TagFilter::new(tags.clone(), skip_tags.clone());
+            // CLI flags override the config file
+            let timeout = timeout.map(Duration::from_secs).or(config.timeout());
+            let redactor = document_redactor(&document)?;
+            let audit_log = log
+                .as_deref()
+                .map(AuditLog::open)
+                .transpose()?
+                .map(|log| log.with_redactor(redactor.clone()));
+            let transcript = transcript
+                .as_deref()
+                .map(|path| {
+                    File::create(path)
+                        .with_context(|| format!("Failed to create transcript: {}", path.display()))
+                })
+                .transpose()?;
+
+            if *ci {
+                let mut executor = BatchExecutor::new()
+                    .with_theme(theme)
+                    .with_vars(vars.iter().cloned().collect())
+                    .with_env(step_env(&document, env))
+                    .with_strict_vars(*strict_vars)
+                    .with_selection(selection)
+                    .with_skip_checks(*skip_checks)
+                    .with_tag_filter(tag_filter)
+                    .with_sudo_cmd(sudo_cmd.clone())
+                    .with_interpreters(step_interpreters(shell.as_deref(), &document, &config))
+                    .with_strict_shell(*strict_shell)
+                    .with_continue_on_error(*continue_on_error)
+                    .with_pager(*pager)
+                    .with_redactor(redactor);
+                if let Some(timeout) = timeout {
+                    executor = executor.with_timeout(timeout);
+                }
+                if let Some(log) = audit_log {
+                    executor = executor.with_audit_log(log);
+                }
+                if let Some(file) = transcript {
+                    executor = executor.with_transcript(file);
+                }
+                // Steps an interrupted run of this content completed satisfy `needs=`
+                if !from_stdin {
+                    if let Some(checkpoint) =
+                        Checkpoint::load(file_path)?.filter(|c| c.matches(&content))
+                    {
+                        executor = executor.with_succeeded_steps(checkpoint.succeeded_steps);
+                    }
+                }
+                let execution = executor.execute(&document)?;
+                if let Some(path) = report {
+                    let json = serde_json::to_string_pretty(&execution)
+                        .context("Failed to serialize execution report")?;
+                    fs::write(path, json + "\n")
+                        .with_context(|| format!("Failed to write report: {}", path.display()))?;
+                }
+                let exit_code = execution.exit_code();
+                if exit_code != 0 {
+                    std::process::exit(exit_code);
+                }
+                return Ok(());
+            }
+
+            let mut executor = configured_executor(&config, &document, shell.as_deref())
+                .with_theme(theme)
+                .with_vars(vars.iter().cloned().collect())
+                .with_env(step_env(&document, env))
+                .with_strict_vars(*strict_vars)
+                .with_skip_checks(*skip_checks)
+                .with_tag_filter(tag_filter)
+                .with_sudo_cmd(sudo_cmd.clone())
+                .with_strict_shell(*strict_shell)
+                .with_redactor(redactor)
+                .with_warnings(!cli.no_warnings)
+                .with_danger_patterns(
+                    &[config.danger_patterns.as_slice(), danger_patterns].concat(),
+                );
+            // Explicit --from/--only take precedence over an interrupted run
+            if !from_stdin && only.is_none() {
+                let (checkpoint, resume_from) = if from.is_none() {
+                    resume_checkpoint(file_path, &content, document.step_count())?
+                } else {
+                    (Checkpoint::new(file_path, &content), None)
+                };
+                if let Some(step) = resume_from {
+                    selection = StepSelection::Range(step, document.step_count());
+                }
+                executor = executor.with_checkpoint(checkpoint);
+            }
+            executor = executor.with_selection(selection);
+            if let Some(level) = section_gate {
+                executor = executor.with_section_gate(*level);
+            }
+            // CLI flags override the config file
+            if *auto_run || *no_auto_run {
+                executor = executor.with_auto_run(*auto_run);
+            }
+            if let Some(timeout) = timeout {
+                executor = executor.with_timeout(timeout);
+            }
+            if let Some(log) = audit_log {
+                executor = executor.with_audit_log(log);
+            }
+            if let Some(file) = transcript {
+                executor = executor.with_transcript(file);
+            }
             executor.execute(&document)?;
         }
-        Some(Commands::Tui { .. }) => {
+        Some(Commands::Tui {
+            danger_patterns,
+            env,
+            shell,
+            exit_on_interrupt,
+            timeout,
+            vars,
+            sudo_cmd,
+            ..
+        }) => {
             // TUI mode
-            let mut app = TuiApp::new(document);
+            let env = step_env(&document, env);
+            let interpreters = step_interpreters(shell.as_deref(), &document, &config);
+            let shell = drop_shell(shell.as_deref(), &document, &config);
+            let mut app = TuiApp::new(document)
+                .with_theme(theme)
+                .with_env(env)
+                .with_vars(vars.iter().cloned().collect())
+                .with_sudo_cmd(sudo_cmd.clone())
+                .with_exit_on_interrupt(*exit_on_interrupt)
+                .with_interpreters(interpreters)
+                .with_danger_patterns(
+                    &[config.danger_patterns.as_slice(), danger_patterns].concat(),
+                );
+            if let Some(shell) = shell {
+                app = app.with_shell(shell);
+            }
+            // CLI flags override the config file
+            if let Some(timeout) = timeout.map(Duration::from_secs).or(config.timeout()) {
+                app = app.with_timeout(timeout);
+            }
             app.run()?;
         }
-        Some(Commands::DryRun { .. }) => {
-            // Print all steps
+        Some(Commands::DryRun { json: true, .. }) => {
+            let json = serde_json::to_string_pretty(&document)
+                .context("Failed to serialize document to JSON")?;
+            println!("{}", json);
+        }
+        Some(Commands::DryRun { sudo_cmd, .. }) => {
+            // Print all steps, as they would be started
             println!("Dry run - {} steps found:\n", document.step_count());
+            let env = step_env(&document, &[]);
+            let interpreters = step_interpreters(None, &document, &config);
 
-            for (idx, code) in document.code_blocks().iter().enumerate() {
+            for (idx, code) in document.executable_blocks().iter().enumerate() {
                 println!("Step {} [{}]:", idx + 1, code.language);
-                for line in code.content.lines() {
+                let preview = invocation_preview(code, &env, sudo_cmd, &interpreters);
+                let mut lines = preview.lines();
+                if let Some(command) = lines.next() {
+                    println!("  → would run: {}", command);
+                }
+                for line in lines {
                     println!("  {}", line);
                 }
                 println!();
             }
         }
-        Some(Commands::View { .. }) => {
-            // Just print the content as-is
+        Some(Commands::List { .. }) => {
+            for step in document.steps() {
+                let first_line = step
+                    .code
+                    .content
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .unwrap_or("");
+                let tags = step.code.tags();
+                let tags = if tags.is_empty() {
+                    String::new()
+                } else {
+                    format!(" (tags: {})", tags.join(", "))
+                };
+                match step.section_header {
+                    Some(header) => println!(
+                        "{}. [{}] {} — {}{}",
+                        step.index, step.code.language, header, first_line, tags
+                    ),
+                    None => println!(
+                        "{}. [{}] {}{}",
+                        step.index, step.code.language, first_line, tags
+                    ),
+                }
+            }
+        }
+        Some(Commands::Stats { .. }) => {
+            let stats = document.stats();
+            let languages: Vec<String> = stats
+                .steps_by_language
+                .iter()
+                .map(|(language, count)| format!("{}: {}", language, count))
+                .collect();
+            println!("Sections:      {}", stats.sections);
+            if languages.is_empty() {
+                println!("Steps:         0");
+            } else {
+                println!(
+                    "Steps:         {} ({})",
+                    stats.step_count(),
+                    languages.join(", ")
+                );
+            }
+            println!("Lines of code: {}", stats.lines_of_code);
+            println!(
+                "Prose:         {} words, about {} min to read",
+                stats.prose_words,
+                stats.reading_minutes()
+            );
+        }
+        Some(Commands::View { raw: true, .. }) => {
             print!("{}", content);
         }
+        Some(Commands::View { .. }) => {
+            Renderer::new()
+                .with_theme(theme)
+                .render_document(&document)?;
+        }
+        Some(Commands::Validate { .. }) => {
+            for diagnostic in &diagnostics {
+                let file = diagnostic.file.as_deref().unwrap_or(file_path);
+                match diagnostic.line_number {
+                    Some(line) => print!("{}:{}: ", file.display(), line),
+                    None => print!("{}: ", file.display()),
+                }
+                println!("{}: {}", diagnostic.severity, diagnostic.message);
+            }
+
+            let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+            if diagnostics.is_empty() {
+                println!("{}: no problems found", file_path.display());
+            } else {
+                println!(
+                    "\n{} error(s), {} warning(s)",
+                    errors,
+                    diagnostics.len() - errors
+                );
+            }
+
+            if errors > 0 {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Fmt { check, .. }) => {
+            let formatted = format_runbook(&content, &document)?;
+            if formatted == content {
+                if *check {
+                    println!("{}: already formatted", file_path.display());
+                }
+            } else if *check {
+                eprintln!("{}: not formatted", file_path.display());
+                std::process::exit(1);
+            } else if from_stdin {
+                print!("{}", formatted);
+            } else {
+                fs::write(file_path, &formatted)
+                    .with_context(|| format!("Failed to write file: {}", file_path.display()))?;
+                println!("Formatted {}", file_path.display());
+            }
+        }
+        Some(Commands::Export { format, output, .. }) => {
+            let exported = match format {
+                ExportFormat::Sh => exporter::to_shell_script(&document),
+                ExportFormat::Md => exporter::to_markdown(&document),
+                ExportFormat::Html => {
+                    exporter::to_html_with(&document, &danger_patterns(&config.danger_patterns))
+                }
+            };
+
+            match output {
+                Some(path) if *format == ExportFormat::Sh => write_executable(path, &exported)?,
+                Some(path) => fs::write(path, &exported)
+                    .with_context(|| format!("Failed to write file: {}", path.display()))?,
+                None => print!("{}", exported),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The shell to drop into: `--shell`, then the frontmatter's `shell`, then the config file
+///
+/// `None` leaves the choice to `$SHELL`.
+fn drop_shell(cli_shell: Option<&str>, document: &Document, config: &Config) -> Option<String> {
+    cli_shell
+        .map(str::to_string)
+        .or_else(|| document.metadata.as_ref()?.shell.clone())
+        .or_else(|| config.shell.clone())
+}
+
+/// Interpreters for steps: the config file's, with `bash` and `sh` blocks run
+/// under `--shell` or else the frontmatter's `shell`, if either is set
+///
+/// Blocks fenced with another shell (`zsh`, `fish`) or with a shebang keep it.
+/// The config file's `shell` only picks the shell to drop into.
+fn step_interpreters(
+    cli_shell: Option<&str>,
+    document: &Document,
+    config: &Config,
+) -> HashMap<String, Vec<String>> {
+    let mut interpreters = config.interpreters.clone();
+    let shell = cli_shell
+        .map(str::to_string)
+        .or_else(|| document.metadata.as_ref()?.shell.clone());
+    if let Some(shell) = shell {
+        let argv: Vec<String> = shell.split_whitespace().map(str::to_string).collect();
+        if !argv.is_empty() {
+            interpreters.insert("bash".to_string(), argv.clone());
+            interpreters.insert("sh".to_string(), argv);
+        }
+    }
+    interpreters
+}
+
+/// The step number `--from-section` starts at: the first step in or after section `id`
+fn section_start(document: &Document, id: &str) -> Result<usize> {
+    let Some(section) = document.section_by_id(id) else {
+        let ids: Vec<_> = document
+            .sections
+            .iter()
+            .filter_map(|section| section.id.as_deref())
+            .collect();
+        bail!("No section with id '{}' (sections: {})", id, ids.join(", "));
+    };
+
+    let steps_before: usize = document
+        .sections
+        .iter()
+        .take_while(|candidate| !std::ptr::eq(*candidate, section))
+        .flat_map(|section| &section.blocks)
+        .filter(|block| matches!(block, Block::Code(code) if code.is_executable()))
+        .count();
+    if steps_before == document.step_count() {
+        bail!("No steps in or after section '{}'", id);
+    }
+    Ok(steps_before + 1)
+}
+
+/// An executor with defaults from the user's config file applied, using
+/// `--shell` (if given) as described at [`drop_shell`] and [`step_interpreters`]
+fn configured_executor(
+    config: &Config,
+    document: &Document,
+    cli_shell: Option<&str>,
+) -> InteractiveExecutor {
+    let shell = drop_shell(cli_shell, document, config);
+    let mut executor = InteractiveExecutor::new()
+        .with_danger_patterns(&config.danger_patterns)
+        .with_interpreters(step_interpreters(cli_shell, document, config))
+        .with_auto_run(config.auto_run);
+    if let Some(shell) = shell {
+        executor = executor.with_shell(shell);
+    }
+    if let Some(timeout) = config.timeout() {
+        executor = executor.with_timeout(timeout);
+    }
+    if let Some(message) = &config.shell_prompt_message {
+        executor = executor.with_shell_prompt_message(message.clone());
+    }
+    executor
+}
+
+/// The checkpoint for an interactive run of `path`, plus the step to resume from
+///
+/// If an earlier run of the same content was interrupted, the user is asked
+/// whether to pick up after its last completed step. A checkpoint left by a
+/// different version of the runbook is discarded with a warning.
+fn resume_checkpoint(
+    path: &Path,
+    content: &str,
+    total_steps: usize,
+) -> Result<(Checkpoint, Option<usize>)> {
+    let fresh = Checkpoint::new(path, content);
+    let Some(previous) = Checkpoint::load(path)? else {
+        return Ok((fresh, None));
+    };
+
+    if !previous.matches(content) {
+        eprintln!(
+            "warning: {} changed since the interrupted run; starting fresh",
+            path.display()
+        );
+        previous.clear()?;
+        return Ok((fresh, None));
+    }
+
+    let done = previous.last_completed_step;
+    if done == 0 || done >= total_steps {
+        return Ok((fresh, None));
+    }
+
+    print!(
+        "A previous run stopped after step {} of {}. Resume from step {}? [Y/n] ",
+        done,
+        total_steps,
+        done + 1
+    );
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .context("Failed to read answer")?;
+
+    match answer.trim().to_lowercase().as_str() {
+        "" | "y" | "yes" => Ok((previous, Some(done + 1))),
+        _ => {
+            previous.clear()?;
+            Ok((fresh, None))
+        }
+    }
+}
+
+/// Re-emit a runbook as normalized markdown, keeping any shebang and frontmatter
+///
+/// Refuses to produce output whose code blocks differ from the original's.
+fn format_runbook(content: &str, document: &Document) -> Result<String> {
+    let (_, body) = split_frontmatter(content);
+    let prefix = &content[..content.len() - body.len()];
+    let formatted = format!("{}{}", prefix, document.to_markdown());
+
+    let reparsed = SysadminParser::parse(&formatted)
+        .context("Formatted output no longer parses; leaving the file untouched")?;
+    let blocks = |doc: &Document| {
+        doc.code_blocks()
+            .into_iter()
+            .map(|c| {
+                (
+                    c.language.clone(),
+                    c.content.clone(),
+                    c.info_string(),
+                    c.expected_output.clone(),
+                    c.remote_host.clone(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    if blocks(&reparsed) != blocks(document) {
+        bail!("Formatting would change code blocks; leaving the file untouched");
+    }
+    Ok(formatted)
+}
+
+/// Environment for executed steps: frontmatter `env`, overridden by `--env`
+fn step_env(document: &Document, cli_env: &[(String, String)]) -> HashMap<String, String> {
+    document
+        .metadata
+        .iter()
+        .flat_map(|m| m.env.clone())
+        .chain(cli_env.iter().cloned())
+        .collect()
+}
+
+/// How long to let a burst of file events settle before re-rendering
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Render the runbook at `path` again each time it or a file it includes
+/// changes, until interrupted
+///
+/// A runbook that fails to parse shows the error instead, and watching carries on.
+fn watch_view(path: &Path, options: &ParseOptions, theme: Theme, warnings: bool) -> Result<()> {
+    let (events, changes) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(events).context("Failed to start watching")?;
+    // The runbook and every file it has included so far
+    let mut files = vec![path.to_path_buf()];
+    let mut watched: Vec<PathBuf> = Vec::new();
+
+    loop {
+        print!("\x1B[2J\x1B[1;1H"); // Clear screen, move to top
+        match load_runbook(path, options) {
+            Ok((document, parse_warnings, map)) => {
+                Renderer::new()
+                    .with_theme(theme)
+                    .render_document(&document)?;
+                if warnings {
+                    print_warnings(&parse_warnings, Some(&map), path);
+                }
+                for file in map.files() {
+                    if !files.contains(file) {
+                        files.push(file.clone());
+                    }
+                }
+            }
+            Err(e) => eprintln!("{}: error: {:#}", path.display(), e),
+        }
+
+        // Editors often save by replacing a file, so watch the directories they are in
+        for file in &files {
+            let dir = match file.parent() {
+                _ if file.is_dir() => file.as_path(),
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            if !watched.iter().any(|known| known == dir) {
+                watcher
+                    .watch(dir, RecursiveMode::NonRecursive)
+                    .with_context(|| format!("Failed to watch {}", dir.display()))?;
+                watched.push(dir.to_path_buf());
+            }
+        }
+        println!("\nWatching {} for changes (Ctrl-C to stop)", path.display());
+
+        loop {
+            let event = changes.recv().context("Stopped watching")??;
+            // Reading the file (as we just did) is an event too
+            let ours = event
+                .paths
+                .iter()
+                .any(|p| files.iter().any(|file| p.file_name() == file.file_name()));
+            if !event.kind.is_access() && (ours || path.is_dir()) {
+                break;
+            }
+        }
+        thread::sleep(WATCH_DEBOUNCE);
+        while changes.try_recv().is_ok() {}
+    }
+}
+
+/// Read and parse the runbook file or directory at `path`, as `view` does
+///
+/// Warnings refer to lines of the expanded text; the source map locates them.
+fn load_runbook(
+    path: &Path,
+    options: &ParseOptions,
+) -> Result<(Document, Vec<ParseWarning>, SourceMap)> {
+    if path.is_dir() {
+        let (document, warnings) = SysadminParser::parse_dir_with_warnings(path, options)?;
+        return Ok((document, warnings, SourceMap::default()));
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let (content, map) = expand_includes_with_map(&content, path)?;
+    let (mut document, warnings) = SysadminParser::parse_with_warnings(&content, options)
+        .context("Failed to parse .sysadmin document")?;
+    map.remap(&mut document);
+    Ok((document, warnings, map))
+}
+
+/// The file and line that `line` of the runbook read from `path` came from
+///
+/// Without a source map (or for a line it doesn't cover), that is `path` itself.
+fn source_location<'a>(
+    map: Option<&'a SourceMap>,
+    path: &'a Path,
+    line: usize,
+) -> (&'a Path, usize) {
+    map.and_then(|map| map.locate(line)).unwrap_or((path, line))
+}
+
+/// Print parse warnings as `file:line: warning: ...`, in the files they came from
+fn print_warnings(warnings: &[ParseWarning], map: Option<&SourceMap>, path: &Path) {
+    for warning in warnings {
+        let (file, line) = match &warning.file {
+            Some(file) => (file.as_path(), warning.line_number),
+            None => source_location(map, path, warning.line_number),
+        };
+        eprintln!("{}:{}: warning: {}", file.display(), line, warning.message);
+    }
+}
+
+/// Masks the secrets matching the runbook's `redact` patterns
+fn document_redactor(document: &Document) -> Result<Redactor> {
+    let patterns = document
+        .metadata
+        .as_ref()
+        .map(|m| m.redact.as_slice())
+        .unwrap_or_default();
+    Redactor::new(patterns).context("Invalid frontmatter")
+}
+
+/// Write `contents` to `path` and mark it executable
+fn write_executable(path: &Path, contents: &str) -> Result<()> {
+    fs::write(path, contents)
+        .with_context(|| format!("Failed to write file: {}", path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make executable: {}", path.display()))?;
     }
 
     Ok(())
@@ -1,14 +1,119 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
 /// A block in the document
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Block {
     /// Documentation/text content (markdown)
-    Text(String),
+    Text {
+        /// The text as rebuilt from markdown events, used for display
+        text: String,
+        /// The source markdown it was parsed from, when known
+        #[serde(skip_serializing_if = "Option::is_none")]
+        raw: Option<String>,
+    },
     /// Executable code block
     Code(CodeBlock),
+    /// A markdown table, with each cell's text
+    Table {
+        headers: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+    /// A block quote, e.g. `> **Warning:** don't skip this`
+    Callout {
+        kind: CalloutKind,
+        /// The quoted text, without its leading keyword
+        text: String,
+    },
+    /// A task list, e.g. `- [x] backups verified`: whether each item is checked, and its text
+    Checklist { items: Vec<(bool, String)> },
+}
+
+/// What a [`Block::Callout`] draws attention to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CalloutKind {
+    Note,
+    Tip,
+    Warning,
+    Danger,
+    /// A plain block quote with no leading keyword
+    Quote,
+}
+
+impl Block {
+    /// A text block with no source markdown attached
+    pub fn text(text: impl Into<String>) -> Self {
+        Block::Text {
+            text: text.into(),
+            raw: None,
+        }
+    }
+}
+
+impl CalloutKind {
+    /// Split a leading `Warning:`, `**Note:**` or `[!TIP]` keyword off quoted text
+    ///
+    /// GitHub's `Important` and `Caution` count as warning and danger. Text
+    /// without a recognized keyword is a plain quote.
+    pub fn detect(text: &str) -> (Self, &str) {
+        let trimmed = text.trim_start();
+
+        // GitHub alert syntax: `[!WARNING]`
+        if let Some((word, rest)) = trimmed
+            .strip_prefix("[!")
+            .and_then(|rest| rest.split_once(']'))
+        {
+            if let Some(kind) = Self::from_keyword(word) {
+                return (kind, rest.trim_start());
+            }
+        }
+
+        let bold = trimmed.starts_with("**");
+        let unbolded = trimmed.trim_start_matches('*');
+        let word_end = unbolded
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(unbolded.len());
+        let (word, rest) = unbolded.split_at(word_end);
+        if let Some(kind) = Self::from_keyword(word) {
+            let suffixes: &[&str] = if bold { &[":**", "**:", "**"] } else { &[":"] };
+            if let Some(rest) = suffixes.iter().find_map(|s| rest.strip_prefix(s)) {
+                return (kind, rest.trim_start());
+            }
+        }
+
+        (CalloutKind::Quote, trimmed)
+    }
+
+    fn from_keyword(word: &str) -> Option<Self> {
+        match word.to_lowercase().as_str() {
+            "note" | "info" => Some(CalloutKind::Note),
+            "tip" | "hint" => Some(CalloutKind::Tip),
+            "warning" | "important" => Some(CalloutKind::Warning),
+            "danger" | "caution" => Some(CalloutKind::Danger),
+            _ => None,
+        }
+    }
+
+    /// Title shown for the callout; `None` for a plain quote
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            CalloutKind::Note => Some("Note"),
+            CalloutKind::Tip => Some("Tip"),
+            CalloutKind::Warning => Some("Warning"),
+            CalloutKind::Danger => Some("Danger"),
+            CalloutKind::Quote => None,
+        }
+    }
 }
 
 /// An executable code block
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct CodeBlock {
     /// Language/interpreter (bash, sh, python, etc.)
     pub language: String,
@@ -16,12 +121,148 @@ pub struct CodeBlock {
     pub content: String,
     /// Line number where this block starts in the source file
     pub line_number: usize,
+    /// `key=value` attributes from the fence info string
+    pub attributes: BTreeMap<String, String>,
+    /// Bare flags from the fence info string (e.g. `danger`)
+    pub flags: Vec<String>,
+    /// What stdout must contain, from `expect=` or a following `output` block
+    pub expected_output: Option<String>,
+    /// Host to run the step on over SSH, from `host=` or the section's default
+    pub remote_host: Option<String>,
+    /// Written inside a `>` block quote, as an example rather than a step
+    pub quoted: bool,
+    /// Directory of the file the block was read from, for a relative `dir=`
+    #[serde(skip)]
+    pub base_dir: Option<PathBuf>,
 }
 
 impl CodeBlock {
-    /// Get the interpreter command for this language
-    pub fn interpreter(&self) -> &str {
-        match self.language.as_str() {
+    /// Create a code block with no attributes or flags
+    pub fn new(
+        language: impl Into<String>,
+        content: impl Into<String>,
+        line_number: usize,
+    ) -> Self {
+        CodeBlock {
+            language: language.into(),
+            content: content.into(),
+            line_number,
+            attributes: BTreeMap::new(),
+            flags: Vec::new(),
+            expected_output: None,
+            remote_host: None,
+            quoted: false,
+            base_dir: None,
+        }
+    }
+
+    /// Get the value of an info-string attribute
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(String::as_str)
+    }
+
+    /// Check whether a bare info-string flag is set
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+
+    /// Rebuild the fence info string, e.g. `bash {danger, id=stop-db}`
+    ///
+    /// Flags come first, then attributes in key order. Values containing
+    /// spaces, commas or braces are double-quoted.
+    pub fn info_string(&self) -> String {
+        let mut annotations: Vec<String> = self.flags.clone();
+        for (key, value) in &self.attributes {
+            let needs_quotes = value.is_empty()
+                || value.contains(|c: char| c.is_whitespace() || ",{}".contains(c));
+            if needs_quotes {
+                annotations.push(format!("{}=\"{}\"", key, value));
+            } else {
+                annotations.push(format!("{}={}", key, value));
+            }
+        }
+
+        if annotations.is_empty() {
+            self.language.clone()
+        } else {
+            format!("{} {{{}}}", self.language, annotations.join(", "))
+        }
+    }
+
+    /// Tags from the `tags=` attribute, which may hold several separated by
+    /// commas or spaces (`tags="rollback, slow"`)
+    pub fn tags(&self) -> Vec<&str> {
+        self.attribute("tags")
+            .map(|tags| {
+                tags.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|tag| !tag.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Ids of the steps that must succeed before this one, from `needs=`
+    ///
+    /// Separated like [`CodeBlock::tags`] (`needs="backup, run-migration"`).
+    pub fn needs(&self) -> Vec<&str> {
+        self.attribute("needs")
+            .map(|needs| {
+                needs
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|id| !id.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// A short remark shown with the step's header, from `note=`
+    pub fn note(&self) -> Option<&str> {
+        self.attribute("note")
+    }
+
+    /// Whether the step is flagged `{sudo}` and must run as root
+    pub fn requires_sudo(&self) -> bool {
+        self.has_flag("sudo")
+    }
+
+    /// Directory to run this step in, from the `dir=` attribute
+    ///
+    /// A relative directory is resolved against [`CodeBlock::base_dir`], the
+    /// directory of the runbook the step came from, if known. A remote step's
+    /// directory is on its host, so it's kept as written.
+    pub fn working_dir(&self) -> Option<PathBuf> {
+        let dir = Path::new(self.attribute("dir")?);
+        match &self.base_dir {
+            Some(base) if dir.is_relative() && self.remote_host.is_none() => Some(base.join(dir)),
+            _ => Some(dir.to_path_buf()),
+        }
+    }
+
+    /// How many times to re-run the step after it fails, from `retries=`
+    pub fn retries(&self) -> Result<u32> {
+        match self.attribute("retries") {
+            Some(value) => value
+                .parse()
+                .with_context(|| format!("Invalid retries value '{}'", value)),
+            None => Ok(0),
+        }
+    }
+
+    /// How long to wait before the first retry, from `retry-delay=` (e.g. `5s`)
+    pub fn retry_delay(&self) -> Result<Duration> {
+        match self.attribute("retry-delay") {
+            Some(value) => parse_duration(value)
+                .with_context(|| format!("Invalid retry-delay value '{}'", value)),
+            None => Ok(DEFAULT_RETRY_DELAY),
+        }
+    }
+
+    /// Get the interpreter command line for this language
+    ///
+    /// The command reads the script from stdin. Returns `None` for languages
+    /// with no known interpreter (e.g. `yaml`), which are display-only.
+    pub fn interpreter(&self) -> Option<&str> {
+        let interpreter = match self.language.as_str() {
             "bash" => "bash",
             "sh" => "sh",
             "python" | "python3" => "python3",
@@ -29,16 +270,325 @@ impl CodeBlock {
             "perl" => "perl",
             "zsh" => "zsh",
             "fish" => "fish",
-            _ => "bash", // default fallback
+            "javascript" | "js" => "node",
+            "typescript" | "ts" => "deno run -",
+            "powershell" | "pwsh" => "pwsh -NoProfile -Command -",
+            _ => return None,
+        };
+        Some(interpreter)
+    }
+
+    /// The program named by a leading `#!` line in the content, if any
+    ///
+    /// `#!/usr/bin/env python3` yields `python3`; `#!/bin/zsh` yields `/bin/zsh`.
+    pub fn shebang_interpreter(&self) -> Option<&str> {
+        let shebang = self.content.lines().next()?.strip_prefix("#!")?;
+        let mut words = shebang.split_whitespace();
+        let program = words.next()?;
+
+        if program.rsplit('/').next() != Some("env") {
+            return Some(program);
         }
+        // Skip env's own options (e.g. `-S`) and `NAME=value` assignments
+        words.find(|word| !word.starts_with('-') && !word.contains('='))
+    }
+
+    /// The interpreter to run this block with, honoring a leading shebang
+    ///
+    /// Falls back to the fence language mapping from [`CodeBlock::interpreter`].
+    pub fn effective_interpreter(&self) -> Option<String> {
+        self.shebang_interpreter()
+            .or_else(|| self.interpreter())
+            .map(str::to_string)
+    }
+
+    /// The program and arguments that run this block, reading the script from stdin
+    ///
+    /// [`CodeBlock::effective_interpreter`] split into words, e.g.
+    /// `["pwsh", "-NoProfile", "-Command", "-"]`. Empty for display-only blocks.
+    pub fn interpreter_argv(&self) -> Vec<String> {
+        self.interpreter_argv_with(&HashMap::new())
+    }
+
+    /// Like [`CodeBlock::interpreter_argv`], with `overrides` replacing the
+    /// built-in command for a fence language
+    ///
+    /// A shebang line still takes precedence, and languages with no built-in
+    /// interpreter stay display-only, so overrides never change which blocks
+    /// are steps.
+    pub fn interpreter_argv_with(&self, overrides: &HashMap<String, Vec<String>>) -> Vec<String> {
+        if let Some(program) = self.shebang_interpreter() {
+            return vec![program.to_string()];
+        }
+        let Some(interpreter) = self.interpreter() else {
+            return Vec::new();
+        };
+        match overrides.get(&self.language) {
+            Some(argv) if !argv.is_empty() => argv.clone(),
+            _ => interpreter.split_whitespace().map(str::to_string).collect(),
+        }
+    }
+
+    /// Check whether this block can be run, i.e. has a known interpreter
+    ///
+    /// Blocks in other languages (e.g. `yaml`) are display-only, as are
+    /// [`DISPLAY_LANGUAGES`] even when their content starts with a shebang.
+    /// Blocks quoted with `>` are never run.
+    pub fn is_executable(&self) -> bool {
+        !self.quoted
+            && !self.is_display_only()
+            && (self.shebang_interpreter().is_some() || self.interpreter().is_some())
+    }
+
+    /// Check whether this block documents a result rather than a command
+    pub fn is_display_only(&self) -> bool {
+        DISPLAY_LANGUAGES.contains(&self.language.as_str())
     }
 
     /// Check if this is a shell-like language
     pub fn is_shell(&self) -> bool {
-        matches!(
-            self.language.as_str(),
-            "bash" | "sh" | "zsh" | "fish"
-        )
+        matches!(self.language.as_str(), "bash" | "sh" | "zsh" | "fish")
+    }
+
+    /// Check if this step is flagged `{danger}` or looks destructive
+    pub fn is_dangerous(&self) -> bool {
+        self.has_flag("danger") || is_dangerous(&self.content)
+    }
+
+    /// Like [`CodeBlock::is_dangerous`], but checking against custom patterns
+    ///
+    /// `patterns` should come from [`danger_patterns`].
+    pub fn is_dangerous_with(&self, patterns: &[String]) -> bool {
+        self.has_flag("danger") || matches_danger_pattern(&self.content, patterns)
+    }
+
+    /// The program the first command line invokes (e.g. `rm`)
+    pub fn command_name(&self) -> Option<&str> {
+        self.content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .and_then(|line| line.split_whitespace().next())
+    }
+
+    /// Get the content with `${VAR}` and `$VAR` references substituted
+    ///
+    /// Values are looked up in `vars` first, then in `env` (the variables the
+    /// step will run with), then in the process environment. References that
+    /// resolve to none of them are left intact.
+    pub fn render_content(
+        &self,
+        vars: &HashMap<String, String>,
+        env: &HashMap<String, String>,
+    ) -> String {
+        let mut output = String::with_capacity(self.content.len());
+        for_each_variable(&self.content, |token| match token {
+            Token::Literal(text) => output.push_str(text),
+            Token::Variable { name, raw } => match lookup_variable(name, vars, env) {
+                Some(value) => output.push_str(&value),
+                None => output.push_str(raw),
+            },
+        });
+        output
+    }
+
+    /// Names of referenced variables found in neither `vars`, `env` nor the environment
+    pub fn undefined_variables(
+        &self,
+        vars: &HashMap<String, String>,
+        env: &HashMap<String, String>,
+    ) -> Vec<String> {
+        let mut undefined: Vec<String> = Vec::new();
+        for_each_variable(&self.content, |token| {
+            if let Token::Variable { name, .. } = token {
+                if lookup_variable(name, vars, env).is_none()
+                    && !undefined.iter().any(|u| u == name)
+                {
+                    undefined.push(name.to_string());
+                }
+            }
+        });
+        undefined
+    }
+}
+
+/// Languages of blocks that show output or data, never run as steps
+pub const DISPLAY_LANGUAGES: &[&str] =
+    &["output", "result", "text", "console", "log", "json", "yaml"];
+
+/// Wait before the first retry when a step has no `retry-delay=`
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest wait the doubling backoff grows to between retries
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// The wait before the next retry: double the last one, up to [`MAX_RETRY_DELAY`]
+///
+/// A `retry-delay=` already longer than the cap is kept as it is.
+pub fn next_retry_delay(delay: Duration) -> Duration {
+    delay
+        .checked_mul(2)
+        .unwrap_or(Duration::MAX)
+        .min(MAX_RETRY_DELAY.max(delay))
+}
+
+/// Parse a duration like `500ms`, `5s` or `2m`; a bare number is seconds
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .context("expected a number with an optional unit")?;
+    let seconds = |scale: u64| {
+        amount
+            .checked_mul(scale)
+            .map(Duration::from_secs)
+            .context("duration is too long")
+    };
+    match unit {
+        "ms" => Ok(Duration::from_millis(amount)),
+        "" | "s" => Ok(Duration::from_secs(amount)),
+        "m" => seconds(60),
+        "h" => seconds(3600),
+        _ => anyhow::bail!("unknown unit '{}' (expected ms, s, m or h)", unit),
+    }
+}
+
+/// Substrings that mark a command as potentially destructive
+pub const DEFAULT_DANGER_PATTERNS: &[&str] = &[
+    "rm -rf",
+    "drop table",
+    "drop database",
+    "delete ",
+    "--force",
+];
+
+/// Heuristic check for destructive commands (case-insensitive)
+pub fn is_dangerous(content: &str) -> bool {
+    let lower = content.to_lowercase();
+    DEFAULT_DANGER_PATTERNS
+        .iter()
+        .any(|pattern| lower.contains(pattern))
+}
+
+/// The built-in danger patterns merged with user-supplied ones, lowercased
+pub fn danger_patterns(extra: &[String]) -> Vec<String> {
+    let mut patterns: Vec<String> = DEFAULT_DANGER_PATTERNS
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    for pattern in extra {
+        let pattern = pattern.to_lowercase();
+        if !pattern.is_empty() && !patterns.contains(&pattern) {
+            patterns.push(pattern);
+        }
+    }
+    patterns
+}
+
+/// Check content against lowercased danger patterns (case-insensitive)
+pub fn matches_danger_pattern(content: &str, patterns: &[String]) -> bool {
+    let lower = content.to_lowercase();
+    patterns
+        .iter()
+        .any(|pattern| lower.contains(pattern.as_str()))
+}
+
+/// Check captured stdout against a step's expected output
+///
+/// An expectation wrapped in slashes (`/^ok$/`) is a regex; anything else must
+/// appear somewhere in the output.
+pub fn output_matches(expected: &str, actual: &str) -> Result<bool, regex::Error> {
+    match expected_pattern(expected) {
+        Some(pattern) => Ok(Regex::new(pattern)?.is_match(actual)),
+        None => Ok(actual.contains(expected)),
+    }
+}
+
+/// The regex inside a `/.../` expectation, if it is one
+pub fn expected_pattern(expected: &str) -> Option<&str> {
+    expected
+        .strip_prefix('/')
+        .and_then(|rest| rest.strip_suffix('/'))
+        .filter(|pattern| !pattern.is_empty())
+}
+
+/// A piece of code content, split around variable references
+enum Token<'a> {
+    Literal(&'a str),
+    Variable { name: &'a str, raw: &'a str },
+}
+
+fn lookup_variable(
+    name: &str,
+    vars: &HashMap<String, String>,
+    env: &HashMap<String, String>,
+) -> Option<String> {
+    vars.get(name)
+        .or_else(|| env.get(name))
+        .cloned()
+        .or_else(|| std::env::var(name).ok())
+}
+
+fn is_variable_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_variable_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Parse a variable reference following a `$`
+///
+/// Returns the variable name and the length of the reference including the
+/// `$`. Only `$NAME` and `${NAME}` with identifier names are recognised, so
+/// shell specials like `$1`, `$?` and `$(...)` are not variables.
+fn parse_variable(after_dollar: &str) -> Option<(&str, usize)> {
+    if let Some(braced) = after_dollar.strip_prefix('{') {
+        let end = braced.find('}')?;
+        let name = &braced[..end];
+        let valid = name.starts_with(is_variable_start) && name.chars().all(is_variable_char);
+        return valid.then_some((name, end + 3));
+    }
+
+    if !after_dollar.starts_with(is_variable_start) {
+        return None;
+    }
+    let end = after_dollar
+        .find(|c: char| !is_variable_char(c))
+        .unwrap_or(after_dollar.len());
+    Some((&after_dollar[..end], end + 1))
+}
+
+/// Walk `content`, calling `f` for each literal run and variable reference
+fn for_each_variable<'a>(content: &'a str, mut f: impl FnMut(Token<'a>)) {
+    let mut literal_start = 0;
+    let mut idx = 0;
+
+    while let Some(offset) = content[idx..].find('$') {
+        let dollar = idx + offset;
+        let Some((name, len)) = parse_variable(&content[dollar + 1..]) else {
+            idx = dollar + 1;
+            continue;
+        };
+
+        if literal_start < dollar {
+            f(Token::Literal(&content[literal_start..dollar]));
+        }
+        f(Token::Variable {
+            name,
+            raw: &content[dollar..dollar + len],
+        });
+
+        idx = dollar + len;
+        literal_start = idx;
+    }
+
+    if literal_start < content.len() {
+        f(Token::Literal(&content[literal_start..]));
     }
 }
 
@@ -46,30 +596,313 @@ impl CodeBlock {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_retry_attributes() {
+        let mut code = CodeBlock::new("bash", "kubectl get pods", 1);
+        assert_eq!(code.retries().unwrap(), 0);
+        assert_eq!(code.retry_delay().unwrap(), DEFAULT_RETRY_DELAY);
+
+        code.attributes
+            .insert("retries".to_string(), "3".to_string());
+        code.attributes
+            .insert("retry-delay".to_string(), "250ms".to_string());
+        assert_eq!(code.retries().unwrap(), 3);
+        assert_eq!(code.retry_delay().unwrap(), Duration::from_millis(250));
+
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse_duration("7").unwrap(), Duration::from_secs(7));
+        assert!(parse_duration("5 days").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration(&format!("{}h", u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_retry_backoff_is_capped() {
+        assert_eq!(
+            next_retry_delay(Duration::from_secs(5)),
+            Duration::from_secs(10)
+        );
+        assert_eq!(next_retry_delay(Duration::from_secs(200)), MAX_RETRY_DELAY);
+        assert_eq!(next_retry_delay(MAX_RETRY_DELAY), MAX_RETRY_DELAY);
+
+        // Twenty retries never grow past the cap, and huge delays don't overflow
+        let delay = (0..20).fold(DEFAULT_RETRY_DELAY, |delay, _| next_retry_delay(delay));
+        assert_eq!(delay, MAX_RETRY_DELAY);
+        assert_eq!(next_retry_delay(Duration::MAX), Duration::MAX);
+    }
+
+    #[test]
+    fn test_callout_kind_detection() {
+        assert_eq!(
+            CalloutKind::detect("**Warning:** don't skip this"),
+            (CalloutKind::Warning, "don't skip this")
+        );
+        assert_eq!(
+            CalloutKind::detect("**Tip**: use tmux"),
+            (CalloutKind::Tip, "use tmux")
+        );
+        assert_eq!(
+            CalloutKind::detect("danger: prod"),
+            (CalloutKind::Danger, "prod")
+        );
+        assert_eq!(
+            CalloutKind::detect("[!IMPORTANT] read first"),
+            (CalloutKind::Warning, "read first")
+        );
+        assert_eq!(
+            CalloutKind::detect("Notes from the last outage"),
+            (CalloutKind::Quote, "Notes from the last outage")
+        );
+        assert_eq!(
+            CalloutKind::detect("Note this"),
+            (CalloutKind::Quote, "Note this")
+        );
+    }
+
     #[test]
     fn test_interpreter_mapping() {
-        let code = CodeBlock {
-            language: "bash".to_string(),
-            content: "echo hello".to_string(),
-            line_number: 1,
-        };
-        assert_eq!(code.interpreter(), "bash");
+        let code = CodeBlock::new("bash", "echo hello", 1);
+        assert_eq!(code.interpreter(), Some("bash"));
+
+        let js = CodeBlock::new("js", "console.log(1)", 1);
+        assert_eq!(js.interpreter(), Some("node"));
+
+        let pwsh = CodeBlock::new("powershell", "Get-Date", 1);
+        assert_eq!(pwsh.interpreter(), Some("pwsh -NoProfile -Command -"));
+    }
+
+    #[test]
+    fn test_interpreter_argv() {
+        let pwsh = CodeBlock::new("pwsh", "Get-Date", 1);
+        assert_eq!(
+            pwsh.interpreter_argv(),
+            vec!["pwsh", "-NoProfile", "-Command", "-"]
+        );
+
+        let overrides = HashMap::from([
+            (
+                "python".to_string(),
+                vec!["python3".to_string(), "-u".to_string()],
+            ),
+            ("yaml".to_string(), vec!["yq".to_string()]),
+        ]);
+        let python = CodeBlock::new("python", "print(1)", 1);
+        assert_eq!(
+            python.interpreter_argv_with(&overrides),
+            vec!["python3", "-u"]
+        );
+        assert_eq!(
+            pwsh.interpreter_argv_with(&overrides),
+            pwsh.interpreter_argv()
+        );
+
+        // A shebang still wins, and languages without an interpreter stay display-only
+        let shebang = CodeBlock::new("python", "#!/usr/bin/env pypy3\nprint(1)", 1);
+        assert_eq!(shebang.interpreter_argv_with(&overrides), vec!["pypy3"]);
+        let yaml = CodeBlock::new("yaml", "key: value", 1);
+        assert!(yaml.interpreter_argv().is_empty());
+        assert!(yaml.interpreter_argv_with(&overrides).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_language_has_no_interpreter() {
+        let yaml = CodeBlock::new("yaml", "key: value", 1);
+        assert_eq!(yaml.interpreter(), None);
+        assert_eq!(yaml.effective_interpreter(), None);
+        assert!(!yaml.is_executable());
+
+        let shebang = CodeBlock::new("script", "#!/usr/bin/env python3\nprint(1)", 1);
+        assert!(shebang.is_executable());
+    }
+
+    #[test]
+    fn test_display_languages_are_never_executable() {
+        for language in DISPLAY_LANGUAGES {
+            let block = CodeBlock::new(*language, "#!/bin/bash\necho hi", 1);
+            assert!(block.is_display_only());
+            assert!(!block.is_executable());
+        }
+        assert!(!CodeBlock::new("bash", "echo hi", 1).is_display_only());
+    }
+
+    #[test]
+    fn test_info_string() {
+        let mut code = CodeBlock::new("bash", "ls", 1);
+        assert_eq!(code.info_string(), "bash");
+
+        code.flags.push("danger".to_string());
+        code.attributes
+            .insert("id".to_string(), "stop-db".to_string());
+        let note = "only on primary".to_string();
+        code.attributes.insert("note".to_string(), note);
+        assert_eq!(
+            code.info_string(),
+            "bash {danger, id=stop-db, note=\"only on primary\"}"
+        );
+    }
+
+    #[test]
+    fn test_tags_attribute() {
+        let mut code = CodeBlock::new("bash", "ls", 1);
+        assert!(code.tags().is_empty());
+
+        code.attributes
+            .insert("tags".to_string(), "rollback, slow prod".to_string());
+        assert_eq!(code.tags(), vec!["rollback", "slow", "prod"]);
+    }
+
+    #[test]
+    fn test_note_attribute() {
+        let mut code = CodeBlock::new("bash", "pg_ctl promote", 1);
+        assert_eq!(code.note(), None);
+
+        code.attributes
+            .insert("note".to_string(), "only on primary".to_string());
+        assert_eq!(code.note(), Some("only on primary"));
+    }
+
+    #[test]
+    fn test_working_dir_attribute() {
+        let mut code = CodeBlock::new("bash", "make", 1);
+        assert_eq!(code.working_dir(), None);
+
+        code.attributes
+            .insert("dir".to_string(), "/srv/app".to_string());
+        assert_eq!(code.working_dir(), Some(PathBuf::from("/srv/app")));
+
+        // Relative to the runbook, unless the step runs elsewhere
+        code.base_dir = Some(PathBuf::from("/opt/runbooks"));
+        assert_eq!(code.working_dir(), Some(PathBuf::from("/srv/app")));
+        code.attributes
+            .insert("dir".to_string(), "build".to_string());
+        assert_eq!(
+            code.working_dir(),
+            Some(PathBuf::from("/opt/runbooks/build"))
+        );
+        code.remote_host = Some("web01".to_string());
+        assert_eq!(code.working_dir(), Some(PathBuf::from("build")));
     }
 
     #[test]
     fn test_is_shell() {
-        let bash = CodeBlock {
-            language: "bash".to_string(),
-            content: "".to_string(),
-            line_number: 1,
-        };
+        let bash = CodeBlock::new("bash", "", 1);
         assert!(bash.is_shell());
 
-        let python = CodeBlock {
-            language: "python".to_string(),
-            content: "".to_string(),
-            line_number: 1,
-        };
+        let python = CodeBlock::new("python", "", 1);
         assert!(!python.is_shell());
     }
+
+    #[test]
+    fn test_is_dangerous() {
+        assert!(is_dangerous("sudo RM -RF /var/lib/old"));
+        assert!(is_dangerous("psql -c 'DROP TABLE users'"));
+        assert!(!is_dangerous("ls -la"));
+
+        let mut flagged = CodeBlock::new("bash", "systemctl stop postgresql", 1);
+        assert!(!flagged.is_dangerous());
+        flagged.flags.push("danger".to_string());
+        assert!(flagged.is_dangerous());
+    }
+
+    #[test]
+    fn test_effective_interpreter_honors_shebang() {
+        let env = CodeBlock::new("bash", "#!/usr/bin/env python3\nprint('hi')", 1);
+        assert_eq!(env.shebang_interpreter(), Some("python3"));
+        assert_eq!(env.effective_interpreter().as_deref(), Some("python3"));
+
+        let env_split = CodeBlock::new("bash", "#!/usr/bin/env -S ruby -w\nputs 1", 1);
+        assert_eq!(env_split.effective_interpreter().as_deref(), Some("ruby"));
+
+        let direct = CodeBlock::new("sh", "#!/bin/zsh\necho hi", 1);
+        assert_eq!(direct.effective_interpreter().as_deref(), Some("/bin/zsh"));
+
+        let plain = CodeBlock::new("python", "# not a shebang\nprint(1)", 1);
+        assert_eq!(plain.shebang_interpreter(), None);
+        assert_eq!(plain.effective_interpreter().as_deref(), Some("python3"));
+    }
+
+    #[test]
+    fn test_custom_danger_patterns_merge_with_defaults() {
+        let patterns = danger_patterns(&["MKFS".to_string(), "dd if=".to_string()]);
+
+        assert!(matches_danger_pattern("mkfs.ext4 /dev/sdb1", &patterns));
+        assert!(matches_danger_pattern(
+            "dd if=/dev/zero of=/dev/sdb",
+            &patterns
+        ));
+        assert!(matches_danger_pattern("rm -rf /tmp/x", &patterns));
+        assert!(!matches_danger_pattern("ls -la", &patterns));
+
+        let code = CodeBlock::new("bash", "mkfs.ext4 /dev/sdb1", 1);
+        assert!(!code.is_dangerous());
+        assert!(code.is_dangerous_with(&patterns));
+    }
+
+    #[test]
+    fn test_command_name_skips_comments() {
+        let code = CodeBlock::new("bash", "# clean up\n\n  rm -rf /tmp/build\n", 1);
+        assert_eq!(code.command_name(), Some("rm"));
+    }
+
+    #[test]
+    fn test_render_content_substitutes_variables() {
+        let code = CodeBlock::new("bash", "psql -h ${DB_HOST} -p $DB_PORT -c 'select $1'", 1);
+        let vars = HashMap::from([
+            ("DB_HOST".to_string(), "proddb.internal".to_string()),
+            ("DB_PORT".to_string(), "5432".to_string()),
+        ]);
+
+        assert_eq!(
+            code.render_content(&vars, &HashMap::new()),
+            "psql -h proddb.internal -p 5432 -c 'select $1'"
+        );
+    }
+
+    #[test]
+    fn test_render_content_leaves_undefined_variables() {
+        let code = CodeBlock::new(
+            "bash",
+            "echo ${SYSADMIN_TEST_UNDEFINED} $SYSADMIN_TEST_UNDEFINED",
+            1,
+        );
+        let vars = HashMap::new();
+
+        assert_eq!(code.render_content(&vars, &vars), code.content);
+        assert_eq!(
+            code.undefined_variables(&vars, &vars),
+            vec!["SYSADMIN_TEST_UNDEFINED".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_render_content_prefers_step_env_over_process_env() {
+        let code = CodeBlock::new("bash", "echo ${HOME} ${REGION} ${STAGE}", 1);
+        let vars = HashMap::from([("STAGE".to_string(), "prod".to_string())]);
+        let env = HashMap::from([
+            ("HOME".to_string(), "/srv/app".to_string()),
+            ("REGION".to_string(), "eu-west-1".to_string()),
+            ("STAGE".to_string(), "staging".to_string()),
+        ]);
+
+        assert_eq!(
+            code.render_content(&vars, &env),
+            "echo /srv/app eu-west-1 prod"
+        );
+        assert!(code.undefined_variables(&HashMap::new(), &env).is_empty());
+    }
+
+    #[test]
+    fn test_output_matches_substring() {
+        assert!(output_matches("active", "nginx: active (running)").unwrap());
+        assert!(!output_matches("active", "nginx: failed").unwrap());
+    }
+
+    #[test]
+    fn test_output_matches_regex() {
+        assert!(output_matches("/^ok$/", "ok").unwrap());
+        assert!(!output_matches("/^ok$/", "not ok").unwrap());
+        assert!(output_matches("/(/", "").is_err());
+        // A lone slash is a plain substring
+        assert!(output_matches("/", "/srv").unwrap());
+    }
 }
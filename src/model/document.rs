@@ -1,59 +1,378 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
 use super::block::{Block, CodeBlock};
+use super::metadata::DocumentMetadata;
+use super::stats::DocumentStats;
+use crate::validator::Diagnostic;
 
 /// A parsed .sysadmin document
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Document {
+    /// Metadata from the YAML frontmatter, if the document has any
+    pub metadata: Option<DocumentMetadata>,
     /// The sections of the document
     pub sections: Vec<Section>,
 }
 
 /// A section of a document (could be text, code, or mixed)
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Section {
     /// Optional header for this section
     pub header: Option<String>,
     /// The level of the header (1-6 for h1-h6)
     pub header_level: Option<u32>,
+    /// Slug of the header for linking to the section, unique within the document
+    pub id: Option<String>,
+    /// Default remote host for the section's steps, from `{host=...}` after the header
+    pub host: Option<String>,
     /// The blocks in this section
     pub blocks: Vec<Block>,
 }
 
+/// An executable code block together with its position and section context
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step<'a> {
+    /// 1-based step number, as shown to the user
+    pub index: usize,
+    pub code: &'a CodeBlock,
+    /// Header of the section the step belongs to
+    pub section_header: Option<&'a str>,
+    /// Level of that header (1-6 for h1-h6)
+    pub header_level: Option<u32>,
+    /// Number of steps in that section
+    pub section_steps: usize,
+}
+
 impl Document {
     pub fn new() -> Self {
         Document {
+            metadata: None,
             sections: Vec::new(),
         }
     }
 
-    /// Get all executable code blocks in order
+    /// Get all code blocks in order, including display-only ones
     pub fn code_blocks(&self) -> Vec<&CodeBlock> {
+        self.code_blocks_iter().collect()
+    }
+
+    /// Iterate over all code blocks in order, like [`Document::code_blocks`]
+    /// but without collecting them, for callers that run often
+    ///
+    /// Blocks quoted with `>` are examples, and are left out.
+    pub fn code_blocks_iter(&self) -> impl Iterator<Item = &CodeBlock> + '_ {
         self.sections
             .iter()
             .flat_map(|s| &s.blocks)
             .filter_map(|b| match b {
-                Block::Code(code) => Some(code),
+                Block::Code(code) if !code.quoted => Some(code),
                 _ => None,
             })
+    }
+
+    /// Get the code blocks that can be run, in order; these are the steps
+    pub fn executable_blocks(&self) -> Vec<&CodeBlock> {
+        self.code_blocks_iter()
+            .filter(|code| code.is_executable())
             .collect()
     }
 
+    /// Get the executable steps in order, with their section context
+    pub fn steps(&self) -> Vec<Step<'_>> {
+        self.sections
+            .iter()
+            .flat_map(|section| section.blocks.iter().map(move |block| (section, block)))
+            .filter_map(|(section, block)| match block {
+                Block::Code(code) if code.is_executable() => Some((section, code)),
+                _ => None,
+            })
+            .enumerate()
+            .map(|(idx, (section, code))| Step {
+                index: idx + 1,
+                code,
+                section_header: section.header.as_deref(),
+                header_level: section.header_level,
+                section_steps: section.step_count(),
+            })
+            .collect()
+    }
+
+    /// Find the section whose [`id`](Section::id) is `id`
+    pub fn section_by_id(&self, id: &str) -> Option<&Section> {
+        self.sections
+            .iter()
+            .find(|section| section.id.as_deref() == Some(id))
+    }
+
     /// Count total number of executable steps
     pub fn step_count(&self) -> usize {
-        self.code_blocks().len()
+        self.code_blocks_iter()
+            .filter(|code| code.is_executable())
+            .count()
+    }
+
+    /// Languages of all code blocks, display-only ones included
+    pub fn languages(&self) -> BTreeSet<String> {
+        self.code_blocks_iter()
+            .map(|code| code.language.clone())
+            .collect()
+    }
+
+    /// Whether the document has no headers and no content
+    ///
+    /// Frontmatter alone doesn't count as content.
+    pub fn is_empty(&self) -> bool {
+        self.sections
+            .iter()
+            .all(|s| s.header.is_none() && s.blocks.is_empty())
+    }
+
+    /// Check the runbook for structural problems without running it
+    ///
+    /// Problems that can only be seen in the raw source, like unclosed
+    /// fences, need [`validator::validate`](crate::validator::validate).
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        crate::validator::validate_document(self)
+    }
+
+    /// Tally the size of the document: sections, steps, code and prose
+    pub fn stats(&self) -> DocumentStats {
+        let mut stats = DocumentStats {
+            sections: self.sections.len(),
+            ..DocumentStats::default()
+        };
+        for block in self.sections.iter().flat_map(|s| &s.blocks) {
+            match block {
+                Block::Code(code) if code.is_executable() => {
+                    *stats
+                        .steps_by_language
+                        .entry(code.language.clone())
+                        .or_default() += 1;
+                    stats.lines_of_code += code
+                        .content
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .count();
+                }
+                Block::Text { text, .. } | Block::Callout { text, .. } => {
+                    // Bullets and other lone punctuation aren't words
+                    stats.prose_words += text
+                        .split_whitespace()
+                        .filter(|word| word.chars().any(char::is_alphanumeric))
+                        .count();
+                }
+                Block::Checklist { items } => {
+                    stats.prose_words += items
+                        .iter()
+                        .flat_map(|(_, text)| text.split_whitespace())
+                        .filter(|word| word.chars().any(char::is_alphanumeric))
+                        .count();
+                }
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    /// Serialize the sections back to normalized markdown
+    ///
+    /// Headers and fences get a blank line around them, list bullets become
+    /// `-`, and code block content is kept as-is. Text keeps its original
+    /// markdown where the parser recorded it. Frontmatter is not included.
+    pub fn to_markdown(&self) -> String {
+        let mut chunks: Vec<String> = Vec::new();
+
+        for section in &self.sections {
+            if let Some(header) = &section.header {
+                let level = section.header_level.unwrap_or(1) as usize;
+                match &section.host {
+                    Some(host) => chunks.push(format!(
+                        "{} {} {{host={}}}",
+                        "#".repeat(level),
+                        header,
+                        host
+                    )),
+                    None => chunks.push(format!("{} {}", "#".repeat(level), header)),
+                }
+            }
+
+            for block in &section.blocks {
+                match block {
+                    Block::Text { text, raw } => {
+                        let text = match raw {
+                            Some(raw) => normalize_raw(raw),
+                            None => normalize_text(text),
+                        };
+                        if !text.is_empty() {
+                            chunks.push(text);
+                        }
+                    }
+                    Block::Table { headers, rows } => {
+                        let row = |cells: &[String]| {
+                            let cells: Vec<String> =
+                                cells.iter().map(|c| c.replace('|', "\\|")).collect();
+                            format!("| {} |", cells.join(" | "))
+                        };
+                        let mut table =
+                            vec![row(headers), row(&vec!["---".to_string(); headers.len()])];
+                        table.extend(rows.iter().map(|cells| row(cells)));
+                        chunks.push(table.join("\n"));
+                    }
+                    Block::Callout { kind, text } => {
+                        let text = match kind.label() {
+                            Some(label) => format!("**{}:** {}", label, text),
+                            None => text.clone(),
+                        };
+                        let quoted: Vec<String> = normalize_text(&text)
+                            .lines()
+                            .map(|line| format!("> {}", line).trim_end().to_string())
+                            .collect();
+                        chunks.push(quoted.join("\n"));
+                    }
+                    Block::Checklist { items } => {
+                        let items: Vec<String> = items
+                            .iter()
+                            .map(|(checked, text)| {
+                                format!("- [{}] {}", if *checked { 'x' } else { ' ' }, text)
+                            })
+                            .collect();
+                        chunks.push(items.join("\n"));
+                    }
+                    Block::Code(code) => {
+                        let fence = code_fence(&code.content);
+                        let mut chunk = format!(
+                            "{}{}\n{}\n{}",
+                            fence,
+                            code.info_string(),
+                            code.content,
+                            fence
+                        );
+                        // Expectations not given by `expect=` came from an `output` block
+                        if let Some(expected) = &code.expected_output {
+                            if code.attribute("expect").is_none() {
+                                let fence = code_fence(expected);
+                                chunk.push_str(&format!(
+                                    "\n\n{}output\n{}\n{}",
+                                    fence, expected, fence
+                                ));
+                            }
+                        }
+                        if code.quoted {
+                            let quoted: Vec<String> = chunk
+                                .lines()
+                                .map(|line| format!("> {}", line).trim_end().to_string())
+                                .collect();
+                            chunk = quoted.join("\n");
+                        }
+                        chunks.push(chunk);
+                    }
+                }
+            }
+        }
+
+        if chunks.is_empty() {
+            return String::new();
+        }
+        chunks.join("\n\n") + "\n"
     }
 }
 
+/// Tidy a text block: `•` bullets become `-` and each paragraph line is
+/// separated by a blank line, while list items stay together
+fn normalize_text(text: &str) -> String {
+    let mut out = String::new();
+    let mut previous_was_item = false;
+    let lines = text.lines().map(str::trim_end).filter(|l| !l.is_empty());
+    for line in lines {
+        let item = line.strip_prefix("• ").or_else(|| line.strip_prefix("- "));
+        if !out.is_empty() {
+            let separator = if previous_was_item && item.is_some() {
+                "\n"
+            } else {
+                "\n\n"
+            };
+            out.push_str(separator);
+        }
+        match item {
+            Some(item) => {
+                out.push_str("- ");
+                out.push_str(item);
+            }
+            None => out.push_str(line),
+        }
+        previous_was_item = item.is_some();
+    }
+    out
+}
+
+/// Tidy a text block's source markdown: `*` and `+` bullets become `-`,
+/// trailing whitespace goes, and runs of blank lines collapse to one
+///
+/// Lines inside fenced code blocks are left alone.
+fn normalize_raw(raw: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in raw.lines().map(str::trim_end) {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker => fence = None,
+            _ => {}
+        }
+
+        if fence.is_none() && line.is_empty() && out.last().is_some_and(|l| l.is_empty()) {
+            continue;
+        }
+        let indent = &line[..line.len() - trimmed.len()];
+        let bullet = trimmed
+            .strip_prefix("* ")
+            .or_else(|| trimmed.strip_prefix("+ "))
+            // `* * *` is a thematic break, not a list
+            .filter(|_| !trimmed.chars().all(|c| c == '*' || c == ' '));
+        match bullet {
+            Some(item) if fence.is_none() => out.push(format!("{}- {}", indent, item)),
+            _ => out.push(line.to_string()),
+        }
+    }
+    out.join("\n")
+        .trim_start_matches('\n')
+        .trim_end()
+        .to_string()
+}
+
+/// A backtick fence longer than any backtick run in `content`
+fn code_fence(content: &str) -> String {
+    let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest_run.max(2) + 1)
+}
+
 impl Default for Document {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Parse a runbook with the default options, as [`SysadminParser::parse`] does
+///
+/// [`SysadminParser::parse`]: crate::parser::SysadminParser::parse
+impl FromStr for Document {
+    type Err = anyhow::Error;
+
+    fn from_str(content: &str) -> anyhow::Result<Self> {
+        crate::parser::SysadminParser::parse(content)
+    }
+}
+
 impl Section {
     pub fn new() -> Self {
         Section {
             header: None,
             header_level: None,
+            id: None,
+            host: None,
             blocks: Vec::new(),
         }
     }
@@ -62,9 +381,19 @@ impl Section {
         Section {
             header: Some(header),
             header_level: Some(level),
+            id: None,
+            host: None,
             blocks: Vec::new(),
         }
     }
+
+    /// Count the section's executable steps
+    pub fn step_count(&self) -> usize {
+        self.blocks
+            .iter()
+            .filter(|b| matches!(b, Block::Code(code) if code.is_executable()))
+            .count()
+    }
 }
 
 impl Default for Section {
@@ -84,23 +413,178 @@ mod tests {
         assert_eq!(doc.step_count(), 0);
     }
 
+    #[test]
+    fn test_code_blocks_iter_matches_code_blocks() {
+        let content = "# Deploy\n\n```bash\nls\n```\n\n```yaml\nkey: value\n```\n\n## Verify\n\n```python\nprint(1)\n```\n";
+        let doc = Document::from_str(content).unwrap();
+
+        let collected: Vec<&CodeBlock> = doc.code_blocks_iter().collect();
+        assert_eq!(collected, doc.code_blocks());
+        assert_eq!(collected.len(), 3);
+        assert_eq!(doc.step_count(), 2);
+    }
+
+    #[test]
+    fn test_section_step_count() {
+        let mut section = Section::with_header("Deploy".to_string(), 1);
+        assert_eq!(section.step_count(), 0);
+        section.blocks = vec![
+            Block::Code(CodeBlock::new("bash", "ls", 3)),
+            Block::Code(CodeBlock::new("yaml", "key: value", 7)),
+            Block::Code(CodeBlock::new("python", "print(1)", 11)),
+        ];
+        assert_eq!(section.step_count(), 2);
+
+        let mut doc = Document::new();
+        doc.sections.push(section);
+        assert!(doc.steps().iter().all(|step| step.section_steps == 2));
+    }
+
+    #[test]
+    fn test_document_from_str() {
+        let doc: Document = "# Setup\n\n```bash\nls\n```\n\n```yaml\nkey: value\n```\n"
+            .parse()
+            .unwrap();
+        assert!(!doc.is_empty());
+        assert_eq!(doc.step_count(), 1);
+        assert_eq!(
+            doc.languages().into_iter().collect::<Vec<_>>(),
+            vec!["bash", "yaml"]
+        );
+
+        let frontmatter_only: Document = "---\ntitle: Empty\n---\n".parse().unwrap();
+        assert!(frontmatter_only.is_empty());
+        assert!(Document::new().is_empty());
+    }
+
     #[test]
     fn test_code_blocks_extraction() {
         let mut doc = Document::new();
         let mut section = Section::new();
-        
-        section.blocks.push(Block::Text("Some text".to_string()));
-        section.blocks.push(Block::Code(CodeBlock {
-            language: "bash".to_string(),
-            content: "echo hello".to_string(),
-            line_number: 5,
-        }));
-        section.blocks.push(Block::Text("More text".to_string()));
-        
+
+        section.blocks.push(Block::text("Some text"));
+        section
+            .blocks
+            .push(Block::Code(CodeBlock::new("bash", "echo hello", 5)));
+        section.blocks.push(Block::text("More text"));
+
         doc.sections.push(section);
-        
+
         let code_blocks = doc.code_blocks();
         assert_eq!(code_blocks.len(), 1);
         assert_eq!(code_blocks[0].content, "echo hello");
     }
+
+    #[test]
+    fn test_display_only_blocks_are_not_steps() {
+        let mut doc = Document::new();
+        let mut section = Section::new();
+        section
+            .blocks
+            .push(Block::Code(CodeBlock::new("yaml", "replicas: 3", 1)));
+        section.blocks.push(Block::Code(CodeBlock::new(
+            "bash",
+            "kubectl apply -f app.yaml",
+            5,
+        )));
+        doc.sections.push(section);
+
+        assert_eq!(doc.code_blocks().len(), 2);
+        assert_eq!(doc.step_count(), 1);
+        assert_eq!(doc.executable_blocks()[0].language, "bash");
+    }
+
+    #[test]
+    fn test_steps_carry_section_context() {
+        let mut doc = Document::new();
+        let mut intro = Section::new();
+        intro
+            .blocks
+            .push(Block::Code(CodeBlock::new("bash", "uptime", 1)));
+        let mut backup = Section::with_header("Backup".to_string(), 2);
+        backup
+            .blocks
+            .push(Block::Code(CodeBlock::new("yaml", "keep: 7", 5)));
+        backup
+            .blocks
+            .push(Block::Code(CodeBlock::new("bash", "ls /backups", 9)));
+        doc.sections.push(intro);
+        doc.sections.push(backup);
+
+        let steps = doc.steps();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].index, 1);
+        assert_eq!(steps[0].section_header, None);
+        assert_eq!(steps[1].index, 2);
+        assert_eq!(steps[1].code.content, "ls /backups");
+        assert_eq!(steps[1].section_header, Some("Backup"));
+        assert_eq!(steps[1].header_level, Some(2));
+    }
+
+    #[test]
+    fn test_document_serializes_to_json() {
+        let mut doc = Document::new();
+        let mut section = Section::with_header("Backup".to_string(), 2);
+        section.blocks.push(Block::text("Check it"));
+        section
+            .blocks
+            .push(Block::Code(CodeBlock::new("bash", "ls /backups", 3)));
+        doc.sections.push(section);
+
+        let json: serde_json::Value = serde_json::to_value(&doc).unwrap();
+        let section = &json["sections"][0];
+        assert_eq!(section["header"], "Backup");
+        assert_eq!(section["header_level"], 2);
+        assert_eq!(section["blocks"][0]["text"]["text"], "Check it");
+        assert_eq!(section["blocks"][1]["code"]["language"], "bash");
+        assert_eq!(section["blocks"][1]["code"]["content"], "ls /backups");
+        assert_eq!(section["blocks"][1]["code"]["line_number"], 3);
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let mut doc = Document::new();
+        let mut section = Section::with_header("Backup".to_string(), 2);
+        let text = "Check it\n• one\n• two\n";
+        section.blocks.push(Block::text(text));
+        let code = CodeBlock::new("bash", "echo '```'\n  ls", 3);
+        section.blocks.push(Block::Code(code));
+        doc.sections.push(section);
+
+        assert_eq!(
+            doc.to_markdown(),
+            "## Backup\n\nCheck it\n\n- one\n- two\n\n````bash\necho '```'\n  ls\n````\n"
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut doc = Document::new();
+        let mut section = Section::with_header("Backup".to_string(), 2);
+        section
+            .blocks
+            .push(Block::text("Check the backups\n• first\n"));
+        section
+            .blocks
+            .push(Block::Code(CodeBlock::new("bash", "ls\n\ndf -h", 3)));
+        section
+            .blocks
+            .push(Block::Code(CodeBlock::new("yaml", "key: value", 8)));
+        section
+            .blocks
+            .push(Block::Code(CodeBlock::new("python", "print(1)", 12)));
+        doc.sections.push(section);
+        doc.sections
+            .push(Section::with_header("Done".to_string(), 2));
+
+        let stats = doc.stats();
+        assert_eq!(stats.sections, 2);
+        assert_eq!(stats.step_count(), 2);
+        assert_eq!(stats.steps_by_language["bash"], 1);
+        assert_eq!(stats.steps_by_language["python"], 1);
+        assert_eq!(stats.lines_of_code, 3);
+        assert_eq!(stats.prose_words, 4);
+        assert_eq!(stats.reading_minutes(), 1);
+        assert_eq!(Document::new().stats().reading_minutes(), 0);
+    }
 }
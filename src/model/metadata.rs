@@ -0,0 +1,167 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+
+/// Metadata from a document's YAML frontmatter
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct DocumentMetadata {
+    /// Human-readable title of the runbook
+    pub title: Option<String>,
+    /// Author or owning team
+    pub author: Option<String>,
+    /// Free-form tags for categorizing runbooks
+    pub tags: Vec<String>,
+    /// Environment variables set for every executed step
+    pub env: BTreeMap<String, String>,
+    /// Tools that must be on `PATH` before the runbook starts
+    pub requires: Vec<String>,
+    /// Shell to drop into for this runbook, instead of `$SHELL`, and to run
+    /// `bash` and `sh` steps with
+    pub shell: Option<String>,
+    /// Language to run 4-space indented code blocks as, instead of keeping them as text
+    pub allow_indented: Option<String>,
+    /// Regexes for secrets to mask in displayed output, transcripts and audit logs
+    pub redact: Vec<String>,
+    /// Any other keys, with values flattened to strings
+    pub extra: BTreeMap<String, String>,
+}
+
+impl DocumentMetadata {
+    /// Parse metadata from the YAML body of a frontmatter block
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        let value: Value = serde_yaml::from_str(yaml).context("Invalid YAML frontmatter")?;
+
+        let mut metadata = DocumentMetadata::default();
+        let mapping = match value {
+            Value::Mapping(mapping) => mapping,
+            // An empty frontmatter block is valid and carries no metadata
+            Value::Null => return Ok(metadata),
+            _ => bail!("YAML frontmatter must be a mapping of keys to values"),
+        };
+
+        for (key, value) in mapping {
+            let Some(key) = scalar_to_string(&key) else {
+                continue;
+            };
+
+            match key.as_str() {
+                "title" => metadata.title = scalar_to_string(&value),
+                "author" => metadata.author = scalar_to_string(&value),
+                "tags" => metadata.tags = string_list(&value),
+                "env" => metadata.env = string_map(&value)?,
+                "requires" => metadata.requires = string_list(&value),
+                "shell" => metadata.shell = scalar_to_string(&value),
+                "allow_indented" => metadata.allow_indented = scalar_to_string(&value),
+                "redact" => metadata.redact = string_list(&value),
+                _ => {
+                    metadata.extra.insert(key, value_to_string(&value));
+                }
+            }
+        }
+
+        Ok(metadata)
+    }
+}
+
+/// Convert a scalar YAML value to a string, if it is one
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Read a list of strings, accepting a single scalar as a one-item list
+fn string_list(value: &Value) -> Vec<String> {
+    match value {
+        Value::Sequence(items) => items.iter().filter_map(scalar_to_string).collect(),
+        other => scalar_to_string(other).into_iter().collect(),
+    }
+}
+
+/// Read a mapping of scalar keys to scalar values
+fn string_map(value: &Value) -> Result<BTreeMap<String, String>> {
+    let Value::Mapping(mapping) = value else {
+        bail!("Frontmatter `env` must be a mapping of names to values");
+    };
+
+    mapping
+        .iter()
+        .map(
+            |(key, value)| match (scalar_to_string(key), scalar_to_string(value)) {
+                (Some(key), Some(value)) => Ok((key, value)),
+                _ => bail!("Frontmatter `env` values must be plain strings or numbers"),
+            },
+        )
+        .collect()
+}
+
+/// Flatten any YAML value into a string for the `extra` map
+fn value_to_string(value: &Value) -> String {
+    scalar_to_string(value).unwrap_or_else(|| {
+        serde_yaml::to_string(value)
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_yaml_known_and_extra_fields() {
+        let yaml = "title: Failover\nauthor: dba-team\ntags: [db, prod]\nticket: OPS-42\n";
+        let metadata = DocumentMetadata::from_yaml(yaml).unwrap();
+
+        assert_eq!(metadata.title.as_deref(), Some("Failover"));
+        assert_eq!(metadata.author.as_deref(), Some("dba-team"));
+        assert_eq!(metadata.tags, vec!["db", "prod"]);
+        assert_eq!(
+            metadata.extra.get("ticket").map(String::as_str),
+            Some("OPS-42")
+        );
+    }
+
+    #[test]
+    fn test_from_yaml_requires() {
+        let metadata = DocumentMetadata::from_yaml("requires: [kubectl, psql]\n").unwrap();
+        assert_eq!(metadata.requires, vec!["kubectl", "psql"]);
+        assert!(metadata.extra.is_empty());
+    }
+
+    #[test]
+    fn test_from_yaml_redact() {
+        let yaml = "redact: [\"AKIA[0-9A-Z]{16}\", 'password=\\S+']\n";
+        let metadata = DocumentMetadata::from_yaml(yaml).unwrap();
+        assert_eq!(metadata.redact, vec!["AKIA[0-9A-Z]{16}", r"password=\S+"]);
+    }
+
+    #[test]
+    fn test_from_yaml_shell() {
+        let metadata = DocumentMetadata::from_yaml("shell: zsh\n").unwrap();
+        assert_eq!(metadata.shell.as_deref(), Some("zsh"));
+        assert!(metadata.extra.is_empty());
+    }
+
+    #[test]
+    fn test_from_yaml_env() {
+        let yaml = "env:\n  KUBECONFIG: /etc/k8s/admin\n  RETRIES: 3\n";
+        let metadata = DocumentMetadata::from_yaml(yaml).unwrap();
+
+        assert_eq!(
+            metadata.env.get("KUBECONFIG").map(String::as_str),
+            Some("/etc/k8s/admin")
+        );
+        assert_eq!(metadata.env.get("RETRIES").map(String::as_str), Some("3"));
+        assert!(DocumentMetadata::from_yaml("env: [a, b]\n").is_err());
+    }
+
+    #[test]
+    fn test_from_yaml_rejects_non_mapping() {
+        assert!(DocumentMetadata::from_yaml("- just\n- a list\n").is_err());
+    }
+}
@@ -1,5 +1,15 @@
 pub mod block;
 pub mod document;
+pub mod metadata;
+pub mod redact;
+pub mod stats;
 
-pub use block::{Block, CodeBlock};
-pub use document::{Document, Section};
+pub use block::{
+    danger_patterns, expected_pattern, is_dangerous, matches_danger_pattern, next_retry_delay,
+    output_matches, parse_duration, Block, CalloutKind, CodeBlock, DEFAULT_DANGER_PATTERNS,
+    DEFAULT_RETRY_DELAY, DISPLAY_LANGUAGES, MAX_RETRY_DELAY,
+};
+pub use document::{Document, Section, Step};
+pub use metadata::DocumentMetadata;
+pub use redact::{Redactor, REDACTED};
+pub use stats::DocumentStats;
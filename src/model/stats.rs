@@ -0,0 +1,30 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Reading speed assumed for the prose reading-time estimate
+pub const WORDS_PER_MINUTE: usize = 200;
+
+/// Size of a runbook, from [`Document::stats`](super::Document::stats)
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct DocumentStats {
+    /// Number of sections, including any text before the first header
+    pub sections: usize,
+    /// Number of executable steps for each language
+    pub steps_by_language: BTreeMap<String, usize>,
+    /// Non-blank lines across all executable steps
+    pub lines_of_code: usize,
+    /// Words of text and callouts, headers and code excluded
+    pub prose_words: usize,
+}
+
+impl DocumentStats {
+    /// Total number of executable steps
+    pub fn step_count(&self) -> usize {
+        self.steps_by_language.values().sum()
+    }
+
+    /// Rough time to read the prose, in whole minutes rounded up
+    pub fn reading_minutes(&self) -> usize {
+        self.prose_words.div_ceil(WORDS_PER_MINUTE)
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::options::ParseOptions;
+use super::sysadmin::{assign_section_ids, ParseWarning, SysadminParser};
+use crate::model::{Document, DocumentMetadata, Section};
+
+/// Extension of the files read from a runbook directory
+const RUNBOOK_EXTENSION: &str = "sysadmin";
+
+/// The `.sysadmin` files directly inside `dir`, in lexical order
+///
+/// Subdirectories are not searched, so shared files can live in one to be
+/// pulled in with `@include` without also running on their own.
+pub fn runbook_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry
+            .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+            .path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == RUNBOOK_EXTENSION) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Parse every runbook in `dir` into one document; see [`SysadminParser::parse_dir`]
+///
+/// Each file's frontmatter is merged into the document's; see [`merge_metadata`].
+pub(super) fn parse_dir(
+    dir: &Path,
+    options: &ParseOptions,
+) -> Result<(Document, Vec<ParseWarning>)> {
+    let files = runbook_files(dir)?;
+    if files.is_empty() {
+        bail!("No .{} files in {}", RUNBOOK_EXTENSION, dir.display());
+    }
+
+    let mut document = Document::new();
+    let mut warnings = Vec::new();
+    for path in files {
+        let (mut part, mut part_warnings) =
+            SysadminParser::parse_file_with_warnings(&path, options)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+        warnings.append(&mut part_warnings);
+
+        // A section named after the file marks where it starts
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        document
+            .sections
+            .push(Section::with_header(name.into_owned(), 1));
+        if let Some(metadata) = part.metadata.take() {
+            match &mut document.metadata {
+                Some(merged) => merge_metadata(merged, metadata)
+                    .with_context(|| format!("Conflicting frontmatter in {}", path.display()))?,
+                None => document.metadata = Some(metadata),
+            }
+        }
+        document.sections.append(&mut part.sections);
+    }
+
+    // Ids were only unique within each file
+    assign_section_ids(&mut document.sections);
+    Ok((document, warnings))
+}
+
+/// Fold a later file's frontmatter into the merged metadata
+///
+/// Lists (`tags`, `requires`, `redact`) and `env` are combined, so every
+/// file's steps run with their declared env and secret masking. An `env`
+/// variable or `shell` set differently by two files is an error. For the
+/// other fields, the first file to set one wins.
+fn merge_metadata(merged: &mut DocumentMetadata, part: DocumentMetadata) -> Result<()> {
+    for (key, value) in part.env {
+        match merged.env.get(&key) {
+            Some(existing) if *existing != value => bail!(
+                "env {} is '{}' in an earlier file but '{}' here",
+                key,
+                existing,
+                value
+            ),
+            _ => {
+                merged.env.insert(key, value);
+            }
+        }
+    }
+    match (&merged.shell, part.shell) {
+        (Some(existing), Some(shell)) if *existing != shell => bail!(
+            "shell is '{}' in an earlier file but '{}' here",
+            existing,
+            shell
+        ),
+        (None, shell) => merged.shell = shell,
+        _ => {}
+    }
+
+    let union = |merged: &mut Vec<String>, part: Vec<String>| {
+        for item in part {
+            if !merged.contains(&item) {
+                merged.push(item);
+            }
+        }
+    };
+    union(&mut merged.tags, part.tags);
+    union(&mut merged.requires, part.requires);
+    union(&mut merged.redact, part.redact);
+
+    merged.title = merged.title.take().or(part.title);
+    merged.author = merged.author.take().or(part.author);
+    merged.allow_indented = merged.allow_indented.take().or(part.allow_indented);
+    for (key, value) in part.extra {
+        merged.extra.entry(key).or_insert(value);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dir_concatenates_files_in_order() {
+        let dir = std::env::temp_dir().join(format!("sysadmin-dir-{}", std::process::id()));
+        fs::create_dir_all(dir.join("common")).unwrap();
+        fs::write(
+            dir.join("02-migrate.sysadmin"),
+            "## Verify\n\n```bash\n./migrate.sh\n```\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("01-backup.sysadmin"),
+            "---\ntitle: Maintenance\n---\n## Verify\n\n```bash\nls /backups\n```\n\n```bash\ndf -h\n```\n",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.md"), "```bash\necho ignored\n```\n").unwrap();
+        fs::write(
+            dir.join("common/shared.sysadmin"),
+            "```bash\necho shared\n```\n",
+        )
+        .unwrap();
+
+        let doc = SysadminParser::parse_dir(&dir).unwrap();
+        let headers: Vec<_> = doc.sections.iter().map(|s| s.header.as_deref()).collect();
+        assert_eq!(
+            headers,
+            vec![
+                Some("01-backup"),
+                Some("Verify"),
+                Some("02-migrate"),
+                Some("Verify"),
+            ]
+        );
+        let ids: Vec<_> = doc.sections.iter().map(|s| s.id.as_deref()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                Some("01-backup"),
+                Some("verify"),
+                Some("02-migrate"),
+                Some("verify-1"),
+            ]
+        );
+
+        let steps = doc.steps();
+        let contents: Vec<_> = steps.iter().map(|s| s.code.content.as_str()).collect();
+        assert_eq!(contents, vec!["ls /backups", "df -h", "./migrate.sh"]);
+        assert_eq!(steps[2].index, 3);
+        assert_eq!(
+            doc.metadata.and_then(|m| m.title).as_deref(),
+            Some("Maintenance")
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_dir_merges_frontmatter_and_keeps_warnings() {
+        let dir = std::env::temp_dir().join(format!("sysadmin-dir-meta-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("01-backup.sysadmin"),
+            "---\nenv:\n  REGION: eu-west-1\nrequires: [pg_dump]\n---\n```bash\npg_dump app\n```\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("02-rotate.sysadmin"),
+            "---\nenv:\n  VAULT_ADDR: https://vault\nrequires: [vault]\nredact: ['s\\.[a-z]+']\n---\n```\nnot a step\n```\n",
+        )
+        .unwrap();
+
+        let (doc, warnings) =
+            SysadminParser::parse_dir_with_warnings(&dir, &ParseOptions::default()).unwrap();
+        let metadata = doc.metadata.unwrap();
+        assert_eq!(metadata.env.len(), 2);
+        assert_eq!(metadata.requires, vec!["pg_dump", "vault"]);
+        assert_eq!(metadata.redact, vec!["s\\.[a-z]+"]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].file, Some(dir.join("02-rotate.sysadmin")));
+        assert_eq!(warnings[0].line_number, 7);
+
+        // The same variable set two ways can't apply to the whole run
+        fs::write(
+            dir.join("03-verify.sysadmin"),
+            "---\nenv:\n  REGION: us-east-1\n---\n```bash\ntrue\n```\n",
+        )
+        .unwrap();
+        let err = SysadminParser::parse_dir(&dir).unwrap_err();
+        assert!(format!("{:#}", err).contains("env REGION is 'eu-west-1'"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_dir_without_runbooks_fails() {
+        let dir = std::env::temp_dir().join(format!("sysadmin-empty-dir-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let err = SysadminParser::parse_dir(&dir).unwrap_err();
+        assert!(err.to_string().contains("No .sysadmin files"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
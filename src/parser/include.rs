@@ -0,0 +1,363 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::sysadmin::split_frontmatter;
+use crate::model::{Block, Document};
+
+/// How deeply includes may nest, as a backstop against runaway recursion
+pub const MAX_INCLUDE_DEPTH: usize = 32;
+
+/// A file being expanded: its canonical path, and the path as it was named
+type IncludeStack = Vec<(PathBuf, PathBuf)>;
+
+/// Where each line of an expanded runbook came from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// Every file that contributed lines, the including file first
+    files: Vec<PathBuf>,
+    /// For each expanded line, its file's index in `files` and its 1-based line there
+    lines: Vec<(usize, usize)>,
+}
+
+impl SourceMap {
+    /// The files the runbook was expanded from, as they were named
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// The file and 1-based line that 1-based `line` of the expanded text came from
+    pub fn locate(&self, line: usize) -> Option<(&Path, usize)> {
+        let (file, line) = *self.lines.get(line.checked_sub(1)?)?;
+        Some((&self.files[file], line))
+    }
+
+    /// Point the line numbers of a document parsed from the expanded text
+    /// back into the files they came from
+    ///
+    /// Each step then carries its line within its own file, which for an
+    /// included step is the included file, and that file's directory as its
+    /// [`base_dir`](crate::model::CodeBlock::base_dir).
+    pub fn remap(&self, document: &mut Document) {
+        for section in &mut document.sections {
+            for block in &mut section.blocks {
+                if let Block::Code(code) = block {
+                    if let Some((file, line)) = self.locate(code.line_number) {
+                        code.line_number = line;
+                        code.base_dir = file.parent().map(Path::to_path_buf);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Replace `@include <path>` paragraphs in `content` with the included files
+///
+/// `path` is the file `content` was read from; include paths are resolved
+/// relative to its directory. Included files are expanded recursively, with
+/// their own shebang and frontmatter dropped. Lines inside code blocks are
+/// never treated as directives.
+///
+/// Fails if a file (directly or indirectly) includes itself, naming the
+/// cycle, or if includes nest more than [`MAX_INCLUDE_DEPTH`] deep.
+pub fn expand_includes(content: &str, path: &Path) -> Result<String> {
+    expand_includes_with_map(content, path).map(|(expanded, _)| expanded)
+}
+
+/// Like [`expand_includes`], also returning where each expanded line came from
+pub fn expand_includes_with_map(content: &str, path: &Path) -> Result<(String, SourceMap)> {
+    let mut expanded = String::with_capacity(content.len());
+    let mut map = SourceMap::default();
+    expand(content, 1, path, &mut Vec::new(), &mut expanded, &mut map)?;
+    Ok((expanded, map))
+}
+
+/// Append `content`, whose first line is line `first_line` of `path`, to
+/// `expanded` with its includes expanded
+fn expand(
+    content: &str,
+    first_line: usize,
+    path: &Path,
+    stack: &mut IncludeStack,
+    expanded: &mut String,
+    map: &mut SourceMap,
+) -> Result<()> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve path: {}", path.display()))?;
+    if let Some(start) = stack.iter().position(|(open, _)| *open == canonical) {
+        let cycle: Vec<String> = stack[start..]
+            .iter()
+            .map(|(_, named)| named.display().to_string())
+            .chain([path.display().to_string()])
+            .collect();
+        bail!("Include cycle: {}", cycle.join(" -> "));
+    }
+    if stack.len() >= MAX_INCLUDE_DEPTH {
+        bail!(
+            "Includes nested more than {} deep at {}",
+            MAX_INCLUDE_DEPTH,
+            path.display()
+        );
+    }
+    stack.push((canonical, path.to_path_buf()));
+    let file = map.files.len();
+    map.files.push(path.to_path_buf());
+
+    let base = path.parent().unwrap_or(Path::new(""));
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut fence: Option<(char, usize)> = None;
+
+    for (idx, line) in lines.iter().enumerate() {
+        fence = next_fence_state(fence, line);
+        let blank = |line: Option<&&str>| line.is_none_or(|l| l.trim().is_empty());
+        let own_paragraph =
+            blank(idx.checked_sub(1).and_then(|i| lines.get(i))) && blank(lines.get(idx + 1));
+
+        match include_target(line) {
+            Some(target) if fence.is_none() && own_paragraph => {
+                let included_path = base.join(target);
+                let included = fs::read_to_string(&included_path).with_context(|| {
+                    format!(
+                        "Failed to read included file {} (from {}:{})",
+                        included_path.display(),
+                        path.display(),
+                        first_line + idx
+                    )
+                })?;
+                let body = included_body(&included);
+                let skipped = included[..included.len() - body.len()]
+                    .matches('\n')
+                    .count();
+                expand(body, skipped + 1, &included_path, stack, expanded, map)?;
+                if !expanded.is_empty() && !expanded.ends_with('\n') {
+                    expanded.push('\n');
+                }
+            }
+            _ => {
+                expanded.push_str(line);
+                map.lines.push((file, first_line + idx));
+            }
+        }
+    }
+
+    stack.pop();
+    Ok(())
+}
+
+/// The file named by an `@include` line, if `line` is one
+fn include_target(line: &str) -> Option<&str> {
+    // Four spaces of indentation make an indented code block
+    if line.starts_with("    ") || line.starts_with('\t') {
+        return None;
+    }
+    let target = line.trim().strip_prefix("@include")?;
+    if !target.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(target.trim()).filter(|t| !t.is_empty())
+}
+
+/// Track whether `line` opens or closes a ``` / ~~~ fence
+fn next_fence_state(fence: Option<(char, usize)>, line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start();
+    let Some(marker) = trimmed.chars().next().filter(|c| *c == '`' || *c == '~') else {
+        return fence;
+    };
+    let length = trimmed.chars().take_while(|c| *c == marker).count();
+    if length < 3 {
+        return fence;
+    }
+
+    match fence {
+        None => Some((marker, length)),
+        Some((open, open_length))
+            if open == marker && length >= open_length && trimmed.trim_end().len() == length =>
+        {
+            None
+        }
+        Some(open) => Some(open),
+    }
+}
+
+/// An included file without its shebang line and frontmatter
+fn included_body(content: &str) -> &str {
+    let (_, body) = split_frontmatter(content);
+    if body.starts_with("#!") {
+        body.find('\n').map_or("", |idx| &body[idx + 1..])
+    } else {
+        body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Block;
+    use crate::parser::{ParseOptions, SysadminParser};
+
+    /// A fresh scratch directory for one test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sysadmin-{}-{}", name, std::process::id()));
+        fs::create_dir_all(dir.join("common")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_include_is_expanded_relative_to_including_file() {
+        let dir = scratch_dir("include");
+        fs::write(
+            dir.join("common/backup.sysadmin"),
+            "#!/usr/bin/env sysadmin\n---\ntitle: Backup\n---\n## Verify backup\n\n```bash\nls /backups\n```\n",
+        )
+        .unwrap();
+        let main = dir.join("deploy.sysadmin");
+        fs::write(
+            &main,
+            "# Deploy\n\n@include common/backup.sysadmin\n\n```bash\n@include not/a/directive\n```\n",
+        )
+        .unwrap();
+
+        let doc = SysadminParser::parse_file(&main).unwrap();
+        let headers: Vec<_> = doc.sections.iter().map(|s| s.header.as_deref()).collect();
+        assert_eq!(headers, vec![Some("Deploy"), Some("Verify backup")]);
+        let steps: Vec<_> = doc
+            .code_blocks()
+            .iter()
+            .map(|c| c.content.clone())
+            .collect();
+        assert_eq!(steps, vec!["ls /backups", "@include not/a/directive"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_line_numbers_point_into_each_file_after_an_include() {
+        let dir = scratch_dir("include-lines");
+        fs::write(
+            dir.join("common/backup.sysadmin"),
+            "---\ntitle: Backup\n---\n## Verify backup\n\n```bash\nls /backups\n```\n",
+        )
+        .unwrap();
+        let main = dir.join("main.sysadmin");
+        let content = "# Deploy\n\n@include common/backup.sysadmin\n\n```bash\n./deploy.sh\n```\n";
+        fs::write(&main, content).unwrap();
+
+        let doc = SysadminParser::parse_file(&main).unwrap();
+        let lines: Vec<_> = doc.code_blocks().iter().map(|c| c.line_number).collect();
+        assert_eq!(lines, vec![6, 5]);
+
+        let (expanded, map) = expand_includes_with_map(content, &main).unwrap();
+        let deploy = expanded.lines().position(|l| l == "./deploy.sh").unwrap() + 1;
+        assert_eq!(map.locate(deploy), Some((main.as_path(), 6)));
+        let backup = expanded.lines().position(|l| l == "ls /backups").unwrap() + 1;
+        let included = dir.join("common/backup.sysadmin");
+        assert_eq!(map.locate(backup), Some((included.as_path(), 7)));
+        assert_eq!(map.files(), [main.clone(), included.clone()]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_relative_dirs_resolve_against_each_file() {
+        let dir = scratch_dir("include-dirs");
+        fs::write(
+            dir.join("common/build.sysadmin"),
+            "```bash {dir=out}\nmake\n```\n",
+        )
+        .unwrap();
+        let main = dir.join("main.sysadmin");
+        fs::write(
+            &main,
+            "@include common/build.sysadmin\n\n```bash {dir=releases}\nls\n```\n",
+        )
+        .unwrap();
+
+        let doc = SysadminParser::parse_file(&main).unwrap();
+        let dirs: Vec<_> = doc
+            .code_blocks()
+            .iter()
+            .map(|c| c.working_dir().unwrap())
+            .collect();
+        assert_eq!(dirs, vec![dir.join("common/out"), dir.join("releases")]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_includes_can_be_left_unexpanded() {
+        let dir = scratch_dir("no-expand");
+        let main = dir.join("deploy.sysadmin");
+        fs::write(&main, "# Deploy\n\n@include missing.sysadmin\n").unwrap();
+
+        let options = ParseOptions::default().with_expand_includes(false);
+        let doc = SysadminParser::parse_file_with_options(&main, &options).unwrap();
+        assert_eq!(
+            doc.sections[0].blocks,
+            vec![Block::Text {
+                text: "@include missing.sysadmin\n".to_string(),
+                raw: Some("@include missing.sysadmin".to_string()),
+            }]
+        );
+        assert!(SysadminParser::parse_file(&main).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_must_be_its_own_paragraph() {
+        let content = "Run this first:\n@include common/backup.sysadmin\n";
+        let expanded = expand_includes(content, Path::new(file!())).unwrap();
+        assert_eq!(expanded, content);
+    }
+
+    #[test]
+    fn test_missing_include_names_the_including_line() {
+        let dir = scratch_dir("include-missing");
+        let main = dir.join("deploy.sysadmin");
+        fs::write(&main, "# Deploy\n\n@include nope.sysadmin\n").unwrap();
+
+        let err = SysadminParser::parse_file(&main).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("deploy.sysadmin:3"),
+            "{:#}",
+            err
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_cycle_is_rejected() {
+        let dir = scratch_dir("include-cycle");
+        let a = dir.join("a.sysadmin");
+        fs::write(&a, "# A\n\n@include b.sysadmin\n").unwrap();
+        fs::write(dir.join("b.sysadmin"), "# B\n\n@include a.sysadmin\n").unwrap();
+
+        let err = format!("{:#}", SysadminParser::parse_file(&a).unwrap_err());
+        assert!(err.contains("Include cycle"), "{}", err);
+        assert!(err.contains("a.sysadmin -> "), "{}", err);
+        assert!(err.contains("b.sysadmin -> "), "{}", err);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_depth_is_capped() {
+        let dir = scratch_dir("include-depth");
+        for level in 0..=MAX_INCLUDE_DEPTH {
+            let next = format!("@include level{}.sysadmin\n", level + 1);
+            fs::write(dir.join(format!("level{}.sysadmin", level)), next).unwrap();
+        }
+
+        let err = SysadminParser::parse_file(&dir.join("level0.sysadmin")).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("nested more than 32"),
+            "{:#}",
+            err
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,3 +1,11 @@
+mod dir;
+mod include;
+mod options;
+mod steps;
 pub mod sysadmin;
 
-pub use sysadmin::SysadminParser;
+pub use dir::runbook_files;
+pub use include::{expand_includes, expand_includes_with_map, SourceMap, MAX_INCLUDE_DEPTH};
+pub use options::ParseOptions;
+pub use steps::Steps;
+pub use sysadmin::{split_frontmatter, ParseWarning, SysadminParser};
@@ -0,0 +1,41 @@
+/// Settings that change how a document is parsed
+///
+/// Passed to [`SysadminParser::parse_with_options`](super::SysadminParser::parse_with_options)
+/// and its siblings. The [`Default`] matches [`SysadminParser::parse`](super::SysadminParser::parse):
+/// indented code blocks are text, and `@include` lines in files are expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Treat 4-space indented code blocks as steps in this language
+    ///
+    /// Overrides `allow_indented` in the frontmatter. When neither is set,
+    /// indented blocks are kept as text.
+    pub indented_language: Option<String>,
+    /// Expand `@include` lines when parsing a file
+    ///
+    /// Only files have a location to resolve includes against; content
+    /// parsed from a string is never expanded.
+    pub expand_includes: bool,
+}
+
+impl ParseOptions {
+    /// Run indented code blocks as steps in `language`
+    pub fn with_indented_language(mut self, language: impl Into<String>) -> Self {
+        self.indented_language = Some(language.into());
+        self
+    }
+
+    /// Whether `@include` lines in parsed files are expanded
+    pub fn with_expand_includes(mut self, expand: bool) -> Self {
+        self.expand_includes = expand;
+        self
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            indented_language: None,
+            expand_includes: true,
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use pulldown_cmark::{CodeBlockKind, Event, OffsetIter, Options, Parser, Tag, TagEnd};
+
+use super::options::ParseOptions;
+use super::sysadmin::{split_frontmatter, InfoString};
+use crate::model::{CodeBlock, DocumentMetadata};
+
+/// Executable code blocks of a runbook, parsed lazily
+///
+/// Created by [`SysadminParser::steps`](super::SysadminParser::steps). Only
+/// the step being read is held in memory; sections, text and frontmatter
+/// are skipped over.
+pub struct Steps<'a> {
+    events: OffsetIter<'a>,
+    /// The markdown after any frontmatter
+    body: &'a str,
+    /// Line of the file at which `body` starts
+    body_line: usize,
+    /// How far into `body` newlines have been counted, and how many there were
+    scanned: (usize, usize),
+    /// The code block being read: its info string, content and line
+    code: Option<(InfoString, String, usize)>,
+    /// Host set on the current section's header
+    section_host: Option<String>,
+    /// Whether the current header's text is being read
+    in_heading: bool,
+    /// How many `>` block quotes the parser is inside
+    quote_depth: usize,
+    /// The last step, held back in case an `output` block follows it
+    pending: Option<CodeBlock>,
+    /// Language that indented code blocks are run as, if they are steps at all
+    indented_language: Option<String>,
+}
+
+impl<'a> Steps<'a> {
+    pub(super) fn new(content: &'a str, options: &ParseOptions) -> Self {
+        let (frontmatter, body) = split_frontmatter(content);
+        let preamble = &content[..content.len() - body.len()];
+        let indented_language = options.indented_language.clone().or_else(|| {
+            frontmatter
+                .and_then(|yaml| DocumentMetadata::from_yaml(yaml).ok())
+                .and_then(|m| m.allow_indented)
+        });
+        let options = Options::ENABLE_TABLES | Options::ENABLE_HEADING_ATTRIBUTES;
+        Self {
+            events: Parser::new_ext(body, options).into_offset_iter(),
+            body,
+            body_line: preamble.matches('\n').count() + 1,
+            scanned: (0, 0),
+            code: None,
+            section_host: None,
+            in_heading: false,
+            quote_depth: 0,
+            pending: None,
+            indented_language,
+        }
+    }
+
+    /// 1-based line in the file of byte `offset` in the body
+    ///
+    /// Offsets only move forward, so each part of the body is scanned once.
+    fn line_of(&mut self, offset: usize) -> usize {
+        let (scanned, newlines) = self.scanned;
+        let newlines = newlines + self.body[scanned..offset].matches('\n').count();
+        self.scanned = (offset, newlines);
+        self.body_line + newlines
+    }
+
+    /// A finished code block: hold it back as a step, or attach it as expected output
+    ///
+    /// Returns the step it displaces, if any.
+    fn finish_code(&mut self, info: InfoString, content: String, line: usize) -> Option<CodeBlock> {
+        if info.is_output() {
+            if let Some(step) = self
+                .pending
+                .as_mut()
+                .filter(|s| s.expected_output.is_none())
+            {
+                step.expected_output = Some(content.trim_end().to_string());
+                return None;
+            }
+        }
+        if info.language.is_empty() && content.trim().is_empty() {
+            return None;
+        }
+
+        let remote_host = info
+            .attributes
+            .get("host")
+            .or(self.section_host.as_ref())
+            .cloned();
+        let code = CodeBlock {
+            language: info.language,
+            content: content.trim_end().to_string(),
+            line_number: line,
+            expected_output: info.attributes.get("expect").cloned(),
+            remote_host,
+            attributes: info.attributes,
+            flags: info.flags,
+            quoted: self.quote_depth > 0,
+            base_dir: None,
+        };
+        let previous = self.pending.take();
+        if code.is_executable() {
+            self.pending = Some(code);
+        }
+        previous
+    }
+}
+
+impl Iterator for Steps<'_> {
+    type Item = CodeBlock;
+
+    fn next(&mut self) -> Option<CodeBlock> {
+        while let Some((event, range)) = self.events.next() {
+            match event {
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    let info = match kind {
+                        CodeBlockKind::Fenced(info) => InfoString::parse(&info),
+                        CodeBlockKind::Indented => InfoString {
+                            language: self.indented_language.clone().unwrap_or_default(),
+                            ..InfoString::default()
+                        },
+                    };
+                    let line = self.line_of(range.start);
+                    self.code = Some((info, String::new(), line));
+                }
+                Event::Text(text) => {
+                    if let Some((_, content, _)) = &mut self.code {
+                        content.push_str(&text);
+                    }
+                }
+                Event::End(TagEnd::CodeBlock) => {
+                    if let Some((info, content, line)) = self.code.take() {
+                        if let Some(step) = self.finish_code(info, content, line) {
+                            return Some(step);
+                        }
+                    }
+                }
+                Event::Start(Tag::Heading { attrs, .. }) => {
+                    self.in_heading = true;
+                    self.section_host = attrs
+                        .iter()
+                        .find(|(key, _)| key.as_ref() == "host")
+                        .and_then(|(_, value)| value.as_ref())
+                        .map(|value| value.to_string());
+                    // A new section: an `output` block can't belong to the last step
+                    if let Some(step) = self.pending.take() {
+                        return Some(step);
+                    }
+                }
+                Event::End(TagEnd::Heading(_)) => self.in_heading = false,
+                Event::End(TagEnd::BlockQuote) => self.quote_depth -= 1,
+                // Anything between a step and an `output` block separates them
+                Event::Start(
+                    Tag::Paragraph | Tag::List(_) | Tag::Table(_) | Tag::BlockQuote(_),
+                ) if !self.in_heading => {
+                    if matches!(event, Event::Start(Tag::BlockQuote(_))) {
+                        self.quote_depth += 1;
+                    }
+                    if let Some(step) = self.pending.take() {
+                        return Some(step);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.pending.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{ParseOptions, SysadminParser};
+
+    #[test]
+    fn test_steps_match_parsed_document() {
+        let content = "#!/usr/bin/env sysadmin\n---\ntitle: Deploy\n---\n# Deploy {host=web01}\n\nIntro\n\n```bash\nsystemctl is-active nginx\n```\n\n```output\nactive\n```\n\n```yaml\nkey: value\n```\n\n- item\n\n```python {host=db01}\nprint(1)\n```\n\n## Local\n\n```sh\nls\n```\n\n```output\nstray\n```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+        let expected: Vec<_> = doc.executable_blocks().into_iter().cloned().collect();
+
+        let steps: Vec<_> = SysadminParser::steps(content).collect();
+        assert_eq!(steps, expected);
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].expected_output.as_deref(), Some("active"));
+        assert_eq!(steps[0].line_number, 9);
+        assert_eq!(steps[1].remote_host.as_deref(), Some("db01"));
+        assert_eq!(steps[2].expected_output.as_deref(), Some("stray"));
+    }
+
+    #[test]
+    fn test_steps_are_lazy() {
+        let content = "```bash\necho one\n```\n\n```bash\necho two\n```\n";
+        let mut steps = SysadminParser::steps(content);
+        assert_eq!(steps.next().unwrap().content, "echo one");
+        assert_eq!(steps.next().unwrap().content, "echo two");
+        assert!(steps.next().is_none());
+    }
+
+    #[test]
+    fn test_steps_with_options_match_parse_with_options() {
+        let content = "---\nallow_indented: sh\n---\n# Legacy\n\n    uptime\n\n```bash\nls\n```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+        let steps: Vec<_> = SysadminParser::steps(content).collect();
+        assert_eq!(steps.iter().collect::<Vec<_>>(), doc.executable_blocks());
+        assert_eq!(steps[0].language, "sh");
+
+        let options = ParseOptions::default().with_indented_language("bash");
+        let doc = SysadminParser::parse_with_options(content, &options).unwrap();
+        let steps: Vec<_> = SysadminParser::steps_with_options(content, &options).collect();
+        assert_eq!(steps.iter().collect::<Vec<_>>(), doc.executable_blocks());
+        assert_eq!(steps[0].language, "bash");
+    }
+}
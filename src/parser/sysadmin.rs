@@ -1,38 +1,230 @@
-use anyhow::Result;
-use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag, TagEnd};
+use anyhow::{Context, Result};
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
-use crate::model::{Block, CodeBlock, Document, Section};
+use super::dir::parse_dir;
+use super::include::expand_includes_with_map;
+use super::options::ParseOptions;
+use super::steps::Steps;
+
+use crate::model::{Block, CalloutKind, CodeBlock, Document, DocumentMetadata, Section};
 
 pub struct SysadminParser;
 
+/// A non-fatal problem noticed while parsing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    /// 1-based source line the warning refers to
+    pub line_number: usize,
+    pub message: String,
+    /// The file the line is in, when the warning came from reading one
+    pub file: Option<PathBuf>,
+}
+
 impl SysadminParser {
     /// Parse a .sysadmin file into a Document
     pub fn parse(content: &str) -> Result<Document> {
+        Self::parse_with_options(content, &ParseOptions::default())
+    }
+
+    /// Parse a .sysadmin file into a Document, with non-default settings
+    pub fn parse_with_options(content: &str, options: &ParseOptions) -> Result<Document> {
+        Self::parse_with_warnings(content, options).map(|(document, _)| document)
+    }
+
+    /// Read and parse a .sysadmin file, expanding `@include` directives
+    ///
+    /// Included sections appear in the document as if they had been written
+    /// inline; see [`expand_includes`](super::expand_includes). Each step's
+    /// line number is its line in the file it was written in.
+    pub fn parse_file(path: &Path) -> Result<Document> {
+        Self::parse_file_with_options(path, &ParseOptions::default())
+    }
+
+    /// Read and parse a .sysadmin file, with non-default settings
+    ///
+    /// `@include` directives are expanded unless
+    /// [`ParseOptions::expand_includes`] is off.
+    pub fn parse_file_with_options(path: &Path, options: &ParseOptions) -> Result<Document> {
+        Self::parse_file_with_warnings(path, options).map(|(document, _)| document)
+    }
+
+    /// Read and parse a .sysadmin file, also returning non-fatal warnings
+    ///
+    /// Each warning names the file and line it refers to, which may be an
+    /// included file.
+    pub fn parse_file_with_warnings(
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<(Document, Vec<ParseWarning>)> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        if !options.expand_includes {
+            let (mut document, mut warnings) = Self::parse_with_warnings(&content, options)?;
+            let base_dir = path.parent().map(Path::to_path_buf);
+            for block in document.sections.iter_mut().flat_map(|s| &mut s.blocks) {
+                if let Block::Code(code) = block {
+                    code.base_dir = base_dir.clone();
+                }
+            }
+            for warning in &mut warnings {
+                warning.file = Some(path.to_path_buf());
+            }
+            return Ok((document, warnings));
+        }
+
+        let (expanded, map) = expand_includes_with_map(&content, path)?;
+        let (mut document, mut warnings) = Self::parse_with_warnings(&expanded, options)?;
+        map.remap(&mut document);
+        for warning in &mut warnings {
+            let (file, line) = map
+                .locate(warning.line_number)
+                .unwrap_or((path, warning.line_number));
+            warning.file = Some(file.to_path_buf());
+            warning.line_number = line;
+        }
+        Ok((document, warnings))
+    }
+
+    /// Parse every `.sysadmin` file in a directory, in lexical order, into one Document
+    ///
+    /// Each file's sections are preceded by a level-1 section named after the
+    /// file (without its extension), and steps are numbered straight through. The first frontmatter
+    /// found applies to the whole document; later ones are ignored.
+    pub fn parse_dir(dir: &Path) -> Result<Document> {
+        Self::parse_dir_with_options(dir, &ParseOptions::default())
+    }
+
+    /// Parse every `.sysadmin` file in a directory, with non-default settings
+    pub fn parse_dir_with_options(dir: &Path, options: &ParseOptions) -> Result<Document> {
+        parse_dir(dir, options).map(|(document, _)| document)
+    }
+
+    /// Parse every `.sysadmin` file in a directory, also returning non-fatal
+    /// warnings, each naming the file it refers to
+    pub fn parse_dir_with_warnings(
+        dir: &Path,
+        options: &ParseOptions,
+    ) -> Result<(Document, Vec<ParseWarning>)> {
+        parse_dir(dir, options)
+    }
+
+    /// Iterate over the executable steps of a .sysadmin file without building a [`Document`]
+    ///
+    /// Steps are parsed lazily, one at a time, which keeps memory flat for
+    /// very large files. They match [`Document::executable_blocks`] from
+    /// [`parse`](Self::parse); frontmatter is only read for settings, and
+    /// invalid frontmatter is ignored rather than reported.
+    pub fn steps(content: &str) -> Steps<'_> {
+        Self::steps_with_options(content, &ParseOptions::default())
+    }
+
+    /// Iterate over the executable steps lazily, with non-default settings
+    ///
+    /// The steps match [`parse_with_options`](Self::parse_with_options) given the same options.
+    pub fn steps_with_options<'a>(content: &'a str, options: &ParseOptions) -> Steps<'a> {
+        Steps::new(content, options)
+    }
+
+    /// Parse a .sysadmin file, also returning anything suspicious that was skipped
+    ///
+    /// Fenced code blocks without a language are kept as text rather than
+    /// steps; each one produces a warning.
+    pub fn parse_with_warnings(
+        content: &str,
+        options: &ParseOptions,
+    ) -> Result<(Document, Vec<ParseWarning>)> {
         let mut document = Document::new();
+        let mut warnings = Vec::new();
         let mut current_section = Section::new();
 
         let mut text_buffer = String::new();
+        // Byte range in `body` of the markdown behind `text_buffer`
+        let mut text_range: Option<Range<usize>> = None;
         let mut in_code_block = false;
         let mut code_buffer = String::new();
-        let mut code_language = String::new();
-        let mut line_number = 1;
+        let mut code_info = InfoString::default();
+        let mut code_fenced = false;
+        let mut code_line_number = 1;
         let mut in_heading = false;
         let mut heading_level = 1;
+        let mut heading_host = None;
+        let mut link_url: Option<String> = None;
+        let mut quote_depth = 0;
+        let mut table_headers = Vec::new();
+        let mut table_rows = Vec::new();
+        let mut table_row = Vec::new();
+        // Items of the task list being read, if any, and how deep in lists we are
+        let mut checklist: Option<Vec<(bool, String)>> = None;
+        let mut list_depth = 0;
+        let mut item_checked = false;
+        // Whether a block inside the current checklist item already closed it off
+        let mut item_split = false;
+        // An HTML block being read, which may be a multi-line comment
+        let mut html_buffer = String::new();
+
+        let (frontmatter, body) = split_frontmatter(content);
+        if let Some(yaml) = frontmatter {
+            document.metadata = Some(DocumentMetadata::from_yaml(yaml)?);
+        }
+        let indented_language = options.indented_language.clone().or_else(|| {
+            document
+                .metadata
+                .as_ref()
+                .and_then(|m| m.allow_indented.clone())
+        });
+        // Roughly one section per header line; comments in code blocks overcount a little
+        let headers = body.lines().filter(|line| line.starts_with('#')).count();
+        document.sections.reserve(headers + 1);
+
+        // Line numbers are reported against the full file, frontmatter included
+        let lines = LineIndex::new(content);
+        let body_offset = content.len() - body.len();
+
+        let options =
+            Options::ENABLE_TABLES | Options::ENABLE_HEADING_ATTRIBUTES | Options::ENABLE_TASKLISTS;
+        let parser = Parser::new_ext(body, options).into_offset_iter();
 
-        let parser = Parser::new(content);
+        for (event, range) in parser {
+            // A top-level list whose first item has a checkbox is a checklist
+            let starts_checklist = quote_depth == 0
+                && list_depth == 0
+                && matches!(event, Event::Start(Tag::List(_)))
+                && is_task_list(&body[range.clone()]);
+
+            // Remember where top-level text came from, for `Block::Text::raw`,
+            // including sysadmin comments so formatting keeps their metadata
+            let carries_metadata = matches!(event, Event::Start(Tag::HtmlBlock))
+                && !comment_metadata(&body[range.clone()]).is_empty();
+            if quote_depth == 0
+                && checklist.is_none()
+                && !starts_checklist
+                && (carries_metadata
+                    || matches!(event, Event::Start(Tag::Paragraph | Tag::List(_))))
+            {
+                extend_range(&mut text_range, range.clone());
+            }
 
-        for event in parser {
             match event {
-                Event::Start(Tag::Heading { level, .. }) => {
+                Event::Start(Tag::Heading { level, attrs, .. }) => {
                     // Flush any accumulated text
-                    if !text_buffer.trim().is_empty() {
-                        current_section
-                            .blocks
-                            .push(Block::Text(text_buffer.clone()));
-                        text_buffer.clear();
-                    }
+                    flush_text(
+                        &mut current_section.blocks,
+                        &mut text_buffer,
+                        &mut text_range,
+                        body,
+                    );
                     in_heading = true;
                     heading_level = level as u32;
+                    // `## Restore {host=backup01}` runs the section's steps there
+                    heading_host = attrs
+                        .iter()
+                        .find(|(key, _)| key.as_ref() == "host")
+                        .and_then(|(_, value)| value.as_ref())
+                        .map(|value| value.to_string());
                 }
 
                 Event::End(TagEnd::Heading(_)) => {
@@ -44,45 +236,179 @@ impl SysadminParser {
                     }
 
                     // Start new section with this header
-                    current_section = Section::with_header(text_buffer.trim().to_string(), heading_level);
+                    current_section =
+                        Section::with_header(text_buffer.trim().to_string(), heading_level);
+                    current_section.host = heading_host.take();
                     text_buffer.clear();
                 }
 
+                Event::Start(Tag::BlockQuote(_)) => {
+                    if let Some(items) = checklist.as_mut() {
+                        split_checklist(
+                            &mut current_section.blocks,
+                            items,
+                            &mut text_buffer,
+                            item_checked,
+                        );
+                        item_split = true;
+                    }
+                    // Nested quotes are folded into the outermost one
+                    if quote_depth == 0 {
+                        // Flush any text before the quote
+                        flush_text(
+                            &mut current_section.blocks,
+                            &mut text_buffer,
+                            &mut text_range,
+                            body,
+                        );
+                    }
+                    quote_depth += 1;
+                }
+
+                Event::End(TagEnd::BlockQuote) => {
+                    quote_depth -= 1;
+                    if quote_depth == 0 {
+                        flush_callout(&mut current_section.blocks, &mut text_buffer);
+                    }
+                }
+
+                Event::Start(Tag::Table(_)) => {
+                    if let Some(items) = checklist.as_mut() {
+                        split_checklist(
+                            &mut current_section.blocks,
+                            items,
+                            &mut text_buffer,
+                            item_checked,
+                        );
+                        item_split = true;
+                    }
+                    // Flush any text before the table
+                    flush_text(
+                        &mut current_section.blocks,
+                        &mut text_buffer,
+                        &mut text_range,
+                        body,
+                    );
+                }
+
+                Event::End(TagEnd::TableCell) => {
+                    table_row.push(text_buffer.trim().to_string());
+                    text_buffer.clear();
+                }
+
+                Event::End(TagEnd::TableHead) => {
+                    table_headers = std::mem::take(&mut table_row);
+                }
+
+                Event::End(TagEnd::TableRow) => {
+                    table_rows.push(std::mem::take(&mut table_row));
+                }
+
+                Event::End(TagEnd::Table) => {
+                    current_section.blocks.push(Block::Table {
+                        headers: std::mem::take(&mut table_headers),
+                        rows: std::mem::take(&mut table_rows),
+                    });
+                }
+
                 Event::Start(Tag::CodeBlock(kind)) => {
+                    if let Some(items) = checklist.as_mut() {
+                        split_checklist(
+                            &mut current_section.blocks,
+                            items,
+                            &mut text_buffer,
+                            item_checked,
+                        );
+                        item_split = true;
+                    }
                     // Flush any text before code block
-                    if !text_buffer.trim().is_empty() {
-                        current_section
-                            .blocks
-                            .push(Block::Text(text_buffer.clone()));
-                        text_buffer.clear();
+                    if quote_depth > 0 {
+                        flush_callout(&mut current_section.blocks, &mut text_buffer);
+                    } else {
+                        flush_text(
+                            &mut current_section.blocks,
+                            &mut text_buffer,
+                            &mut text_range,
+                            body,
+                        );
                     }
 
                     in_code_block = true;
-                    code_language = match kind {
-                        CodeBlockKind::Fenced(lang) => lang.to_string(),
-                        CodeBlockKind::Indented => String::new(),
+                    code_line_number = lines.line_of(body_offset + range.start);
+                    code_fenced = matches!(kind, CodeBlockKind::Fenced(_));
+                    code_info = match kind {
+                        CodeBlockKind::Fenced(info) => InfoString::parse(&info),
+                        CodeBlockKind::Indented => InfoString {
+                            language: indented_language.clone().unwrap_or_default(),
+                            ..InfoString::default()
+                        },
                     };
                 }
 
                 Event::End(TagEnd::CodeBlock) => {
                     in_code_block = false;
 
-                    // Only add code blocks with a language identifier
-                    if !code_language.is_empty() {
+                    if let Some(fence) = code_fenced
+                        .then(|| unclosed_fence(&body[range.clone()]))
+                        .flatten()
+                    {
+                        warnings.push(ParseWarning {
+                            line_number: code_line_number,
+                            message: format!("unclosed code block: {}", fence),
+                            file: None,
+                        });
+                    }
+
+                    // An `output` block right after a step is what that step should print
+                    let expecting_step = match current_section.blocks.last_mut() {
+                        Some(Block::Code(previous))
+                            if code_info.is_output() && previous.expected_output.is_none() =>
+                        {
+                            Some(previous)
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(previous) = expecting_step {
+                        previous.expected_output = Some(code_buffer.trim_end().to_string());
+                    } else if !code_info.language.is_empty() {
+                        // Only add code blocks with a language identifier
+                        let info = std::mem::take(&mut code_info);
                         current_section.blocks.push(Block::Code(CodeBlock {
-                            language: code_language.clone(),
+                            language: info.language,
                             content: code_buffer.trim_end().to_string(),
-                            line_number,
+                            line_number: code_line_number,
+                            expected_output: info.attributes.get("expect").cloned(),
+                            remote_host: info
+                                .attributes
+                                .get("host")
+                                .or(current_section.host.as_ref())
+                                .cloned(),
+                            attributes: info.attributes,
+                            flags: info.flags,
+                            quoted: quote_depth > 0,
+                            base_dir: None,
                         }));
-                    } else if !code_buffer.trim().is_empty() {
-                        // Code blocks without language go into text
-                        text_buffer.push_str("```\n");
-                        text_buffer.push_str(&code_buffer);
-                        text_buffer.push_str("```\n");
+                    } else {
+                        if code_fenced {
+                            warnings.push(ParseWarning {
+                                line_number: code_line_number,
+                                message: "code block has no language and was not added as a step"
+                                    .to_string(),
+                                file: None,
+                            });
+                        }
+                        if !code_buffer.trim().is_empty() {
+                            extend_range(&mut text_range, range);
+                            // Code blocks without language go into text
+                            text_buffer.push_str("```\n");
+                            text_buffer.push_str(&code_buffer);
+                            text_buffer.push_str("```\n");
+                        }
                     }
 
                     code_buffer.clear();
-                    code_language.clear();
+                    code_info = InfoString::default();
                 }
 
                 Event::Text(text) => {
@@ -93,19 +419,16 @@ impl SysadminParser {
                     }
                 }
 
-                Event::Code(text) => {
+                Event::Code(text) if !in_code_block => {
                     // Inline code
-                    if !in_code_block {
-                        text_buffer.push('`');
-                        text_buffer.push_str(&text);
-                        text_buffer.push('`');
-                    }
+                    text_buffer.push('`');
+                    text_buffer.push_str(&text);
+                    text_buffer.push('`');
                 }
 
                 Event::SoftBreak => {
                     if in_code_block {
                         code_buffer.push('\n');
-                        line_number += 1;
                     } else if !in_heading {
                         text_buffer.push(' ');
                     }
@@ -117,31 +440,103 @@ impl SysadminParser {
                     } else {
                         text_buffer.push('\n');
                     }
-                    line_number += 1;
                 }
 
-                Event::Start(Tag::Paragraph) => {
-                    if !text_buffer.is_empty() && !text_buffer.ends_with('\n') {
-                        text_buffer.push('\n');
-                    }
+                Event::Start(Tag::Paragraph)
+                    if !text_buffer.is_empty() && !text_buffer.ends_with('\n') =>
+                {
+                    text_buffer.push('\n');
                 }
 
                 Event::End(TagEnd::Paragraph) => {
                     text_buffer.push('\n');
                 }
 
-                Event::Start(Tag::List(_)) | Event::End(TagEnd::List(_)) => {
+                Event::Start(Tag::List(_)) if starts_checklist => {
+                    // Flush any text before the checklist
+                    flush_text(
+                        &mut current_section.blocks,
+                        &mut text_buffer,
+                        &mut text_range,
+                        body,
+                    );
+                    checklist = Some(Vec::new());
+                    list_depth += 1;
+                }
+
+                Event::Start(Tag::List(_)) => {
+                    list_depth += 1;
                     text_buffer.push('\n');
                 }
 
+                Event::End(TagEnd::List(_)) => {
+                    list_depth -= 1;
+                    match checklist.take_if(|_| list_depth == 0) {
+                        Some(items) => {
+                            if !items.is_empty() {
+                                current_section.blocks.push(Block::Checklist { items });
+                            }
+                            text_buffer.clear();
+                        }
+                        None => text_buffer.push('\n'),
+                    }
+                }
+
+                Event::Start(Tag::Item) if checklist.is_some() && list_depth == 1 => {
+                    item_checked = false;
+                    item_split = false;
+                }
+
                 Event::Start(Tag::Item) => {
                     text_buffer.push_str("• ");
                 }
 
+                Event::TaskListMarker(checked) if checklist.is_some() && list_depth == 1 => {
+                    item_checked = checked;
+                }
+
+                // A checkbox in a list that isn't a checklist stays visible in its text
+                Event::TaskListMarker(checked) => {
+                    text_buffer.push_str(if checked { "[x] " } else { "[ ] " });
+                }
+
+                Event::End(TagEnd::Item) if list_depth == 1 => match checklist.as_mut() {
+                    Some(items) => {
+                        // Nothing after the block that split this item: it is already stored
+                        if !(item_split && text_buffer.trim().is_empty()) {
+                            items.push((item_checked, text_buffer.trim().to_string()));
+                        }
+                        text_buffer.clear();
+                    }
+                    None => text_buffer.push('\n'),
+                },
+
                 Event::End(TagEnd::Item) => {
                     text_buffer.push('\n');
                 }
 
+                Event::Start(Tag::Link { dest_url, .. }) => {
+                    link_url = Some(dest_url.to_string());
+                }
+
+                Event::End(TagEnd::Link) => {
+                    // Keep the URL after the link text: `runbook wiki (https://...)`,
+                    // unless the text already is the URL, as with `<https://...>`
+                    if let Some(url) = link_url.take() {
+                        if !text_buffer.ends_with(url.as_str()) {
+                            text_buffer.push_str(&format!(" ({})", url));
+                        }
+                    }
+                }
+
+                // HTML is never shown, but `<!-- sysadmin: key=value -->` comments carry metadata
+                Event::Html(html) => html_buffer.push_str(&html),
+                Event::End(TagEnd::HtmlBlock) => {
+                    add_comment_metadata(&mut document, &html_buffer);
+                    html_buffer.clear();
+                }
+                Event::InlineHtml(html) => add_comment_metadata(&mut document, &html),
+
                 Event::Start(Tag::Emphasis) => text_buffer.push('*'),
                 Event::End(TagEnd::Emphasis) => text_buffer.push('*'),
 
@@ -155,16 +550,345 @@ impl SysadminParser {
         }
 
         // Flush remaining content
-        if !text_buffer.trim().is_empty() {
-            current_section.blocks.push(Block::Text(text_buffer));
-        }
+        flush_text(
+            &mut current_section.blocks,
+            &mut text_buffer,
+            &mut text_range,
+            body,
+        );
 
         if !current_section.blocks.is_empty() || current_section.header.is_some() {
             document.sections.push(current_section);
         }
+        assign_section_ids(&mut document.sections);
+
+        Ok((document, warnings))
+    }
+}
+
+/// Add the metadata of any sysadmin comments in `html` to the document's `extra`
+///
+/// Keys set in the frontmatter keep their values.
+fn add_comment_metadata(document: &mut Document, html: &str) {
+    let pairs = comment_metadata(html);
+    if pairs.is_empty() {
+        return;
+    }
+    let metadata = document
+        .metadata
+        .get_or_insert_with(DocumentMetadata::default);
+    for (key, value) in pairs {
+        metadata.extra.entry(key).or_insert(value);
+    }
+}
+
+/// Push the accumulated text, with its source markdown, as a text block
+///
+/// Whitespace-only text is dropped, unless its source holds sysadmin comments.
+/// Either way the buffer and range are reset.
+fn flush_text(
+    blocks: &mut Vec<Block>,
+    text: &mut String,
+    range: &mut Option<Range<usize>>,
+    body: &str,
+) {
+    let raw = range.take().map(|range| body[range].trim_end().to_string());
+    let has_metadata = raw
+        .as_deref()
+        .is_some_and(|raw| !comment_metadata(raw).is_empty());
+    if !text.trim().is_empty() || has_metadata {
+        blocks.push(Block::Text {
+            text: std::mem::take(text),
+            raw,
+        });
+    }
+    text.clear();
+}
+
+/// Push the accumulated text of a block quote as a callout
+///
+/// Whitespace-only text is dropped. Either way the buffer is reset.
+fn flush_callout(blocks: &mut Vec<Block>, text: &mut String) {
+    let (kind, callout) = CalloutKind::detect(text);
+    if !callout.trim().is_empty() {
+        blocks.push(Block::Callout {
+            kind,
+            text: callout.trim().to_string(),
+        });
+    }
+    text.clear();
+}
+
+/// Give every section with a header an id, unique across `sections`
+pub(super) fn assign_section_ids(sections: &mut [Section]) {
+    let mut taken = HashSet::new();
+    for section in sections {
+        section.id = section_id(section, &mut taken);
+    }
+}
+
+/// A unique id for `section`, from its header
+///
+/// The header is lowercased, spaces become hyphens and other punctuation is
+/// dropped. A repeated slug gets `-1`, `-2`, ... appended, like GitHub's
+/// heading anchors. Ids already handed out are kept in `taken`.
+fn section_id(section: &Section, taken: &mut HashSet<String>) -> Option<String> {
+    let slug: String = section
+        .header
+        .as_deref()?
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' | '-' => Some('-'),
+            c if c.is_alphanumeric() || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect();
+    if slug.is_empty() {
+        return None;
+    }
+
+    let mut id = slug.clone();
+    let mut suffix = 0;
+    while taken.contains(&id) {
+        suffix += 1;
+        id = format!("{}-{}", slug, suffix);
+    }
+    taken.insert(id.clone());
+    Some(id)
+}
+
+/// The opening line of a fenced code block's source, if it is never closed
+///
+/// pulldown-cmark quietly ends a fence left open at the end of the document
+/// (or its container), so this looks for the closing fence itself.
+fn unclosed_fence(block: &str) -> Option<&str> {
+    let mut lines = block.lines();
+    let opening = lines.next()?.trim();
+    let fence = opening.trim_start_matches(|c: char| c == '>' || c.is_whitespace());
+    let marker = fence.chars().next()?;
+    let len = fence.chars().take_while(|c| *c == marker).count();
+
+    let closed = lines.next_back().is_some_and(|last| {
+        let last = last.trim_start_matches(|c: char| c == '>' || c.is_whitespace());
+        let last = last.trim_end();
+        last.len() >= len && last.chars().all(|c| c == marker)
+    });
+    (!closed).then_some(opening)
+}
+
+/// Whether a list's first item starts with a checkbox: `- [ ] ...` or `1. [x] ...`
+fn is_task_list(list: &str) -> bool {
+    let item = list.trim_start();
+    let after_marker = match item.strip_prefix(['-', '*', '+']) {
+        Some(rest) => rest,
+        None => item
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .strip_prefix(['.', ')'])
+            .unwrap_or(item),
+    };
+    if after_marker.len() == item.len() || !after_marker.starts_with([' ', '\t']) {
+        return false;
+    }
+    let text = after_marker.trim_start();
+    ["[ ]", "[x]", "[X]"]
+        .iter()
+        .any(|checkbox| text.starts_with(checkbox))
+}
+
+/// Close off a checklist where a block, e.g. a code block, appears inside an item
+///
+/// The item's text so far becomes an item, and the items read so far a
+/// checklist of their own; items after the block go into a new one.
+fn split_checklist(
+    blocks: &mut Vec<Block>,
+    items: &mut Vec<(bool, String)>,
+    text: &mut String,
+    checked: bool,
+) {
+    let item = std::mem::take(text);
+    if !item.trim().is_empty() {
+        items.push((checked, item.trim().to_string()));
+    }
+    if !items.is_empty() {
+        blocks.push(Block::Checklist {
+            items: std::mem::take(items),
+        });
+    }
+}
+
+/// Grow `range` to cover `other`
+///
+/// Nested elements lie inside their parents, so covering each element seen
+/// gives the span from the first to the last.
+fn extend_range(range: &mut Option<Range<usize>>, other: Range<usize>) {
+    *range = Some(match range.take() {
+        Some(range) => range.start.min(other.start)..range.end.max(other.end),
+        None => other,
+    });
+}
+
+/// A parsed fence info string, e.g. `bash {danger, id=stop-db}`
+///
+/// The first word is the language. Anything after it (optionally wrapped in
+/// braces) is a comma- or space-separated list of `key=value` attributes and
+/// bare flags. Values may be double-quoted to include spaces or commas.
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct InfoString {
+    pub(super) language: String,
+    pub(super) attributes: BTreeMap<String, String>,
+    pub(super) flags: Vec<String>,
+}
+
+impl InfoString {
+    pub(super) fn parse(info: &str) -> Self {
+        let info = info.trim();
+        let lang_end = info
+            .find(|c: char| c.is_whitespace() || c == '{')
+            .unwrap_or(info.len());
+        let language = info[..lang_end].to_string();
+
+        let rest = info[lang_end..].trim();
+        let rest = rest
+            .strip_prefix('{')
+            .map(|r| r.strip_suffix('}').unwrap_or(r))
+            .unwrap_or(rest);
+
+        let mut parsed = InfoString {
+            language,
+            ..Default::default()
+        };
+
+        for token in split_attribute_tokens(rest) {
+            match split_attribute(&token) {
+                Some((key, value)) => {
+                    parsed.attributes.insert(key, value);
+                }
+                None => parsed.flags.push(token),
+            }
+        }
+
+        parsed
+    }
+
+    /// Whether this is an `output` or `result` block, showing what a step prints
+    pub(super) fn is_output(&self) -> bool {
+        matches!(self.language.as_str(), "output" | "result")
+    }
+}
+
+/// Split a `key=value` token, unquoting the value; `None` for a bare flag
+fn split_attribute(token: &str) -> Option<(String, String)> {
+    let (key, value) = token.split_once('=')?;
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    Some((key.trim().to_string(), value.to_string()))
+}
+
+/// The `key=value` pairs of every `<!-- sysadmin: ... -->` comment in `html`
+///
+/// Values are quoted like fence attributes. Other comments yield nothing.
+fn comment_metadata(html: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find("<!--") {
+        let comment = &rest[start + 4..];
+        let Some(end) = comment.find("-->") else {
+            break;
+        };
+        if let Some(body) = comment[..end].trim().strip_prefix("sysadmin:") {
+            let tokens = split_attribute_tokens(body);
+            pairs.extend(tokens.iter().filter_map(|token| split_attribute(token)));
+        }
+        rest = &comment[end + 3..];
+    }
+    pairs
+}
+
+/// Split attribute text on commas and whitespace outside double quotes
+fn split_attribute_tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in text.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if !in_quotes && (c == ',' || c.is_whitespace()) => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// Maps byte offsets in the source to 1-based line numbers
+struct LineIndex {
+    /// Byte offset at which each line starts
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(content: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self { line_starts }
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line + 1,
+            Err(next_line) => next_line,
+        }
+    }
+}
+
+/// Split a leading `---` delimited YAML frontmatter block from the markdown body
+///
+/// A shebang line may precede the frontmatter. Returns the YAML between the
+/// delimiters and the remaining markdown, or `None` and the untouched content
+/// if there is no (closed) frontmatter block.
+pub fn split_frontmatter(content: &str) -> (Option<&str>, &str) {
+    let mut rest = content;
+    if rest.starts_with("#!") {
+        rest = rest.find('\n').map_or("", |idx| &rest[idx + 1..]);
+    }
 
-        Ok(document)
+    let Some(after_open) = rest
+        .strip_prefix("---\n")
+        .or_else(|| rest.strip_prefix("---\r\n"))
+    else {
+        return (None, content);
+    };
+
+    let mut offset = 0;
+    for line in after_open.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            let yaml = &after_open[..offset];
+            let body = &after_open[offset + line.len()..];
+            return (Some(yaml), body);
+        }
+        offset += line.len();
     }
+
+    (None, content)
 }
 
 #[cfg(test)]
@@ -239,10 +963,457 @@ More text.
         assert_eq!(code_blocks.len(), 0);
     }
 
+    #[test]
+    fn test_parse_with_warnings_reports_missing_language() {
+        let content =
+            "# Test\n\n```\nnot executable\n```\n\n    indented code\n\n```bash\nls\n```\n";
+
+        let (doc, warnings) =
+            SysadminParser::parse_with_warnings(content, &ParseOptions::default()).unwrap();
+        assert_eq!(doc.step_count(), 1);
+        assert_eq!(
+            warnings,
+            vec![ParseWarning {
+                line_number: 3,
+                message: "code block has no language and was not added as a step".to_string(),
+                file: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_with_warnings_reports_unclosed_fence() {
+        let content =
+            "# Test\n\n```bash\nls\n```\n\n> ```sh\n> pwd\n> ```\n\n```python\nprint(1)\n";
+
+        let (doc, warnings) =
+            SysadminParser::parse_with_warnings(content, &ParseOptions::default()).unwrap();
+        // The quoted block is an example, not a step
+        assert_eq!(doc.step_count(), 2);
+        assert_eq!(
+            warnings,
+            vec![ParseWarning {
+                line_number: 11,
+                message: "unclosed code block: ```python".to_string(),
+                file: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_indented_code_blocks_with_options() {
+        let content = "# Legacy\n\nRestart it:\n\n    systemctl restart nginx\n";
+        assert_eq!(SysadminParser::parse(content).unwrap().step_count(), 0);
+
+        let options = ParseOptions::default().with_indented_language("bash");
+        let doc = SysadminParser::parse_with_options(content, &options).unwrap();
+        let steps = doc.executable_blocks();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].language, "bash");
+        assert_eq!(steps[0].content, "systemctl restart nginx");
+        assert_eq!(steps[0].line_number, 5);
+
+        // The frontmatter can opt in too, but the options take precedence
+        let content = format!("---\nallow_indented: sh\n---\n{}", content);
+        let doc = SysadminParser::parse(&content).unwrap();
+        assert_eq!(doc.executable_blocks()[0].language, "sh");
+        let doc = SysadminParser::parse_with_options(&content, &options).unwrap();
+        assert_eq!(doc.executable_blocks()[0].language, "bash");
+    }
+
     #[test]
     fn test_empty_document() {
         let content = "";
         let doc = SysadminParser::parse(content).unwrap();
         assert_eq!(doc.sections.len(), 0);
     }
+
+    #[test]
+    fn test_parse_frontmatter() {
+        let content = r#"#!/usr/bin/sysadmin
+---
+title: Database Migration
+author: dba-team
+tags: [postgres, migration]
+---
+
+# Migrate
+
+```bash
+./migrate.sh
+```
+"#;
+
+        let doc = SysadminParser::parse(content).unwrap();
+        let metadata = doc.metadata.as_ref().expect("frontmatter should be parsed");
+        assert_eq!(metadata.title.as_deref(), Some("Database Migration"));
+        assert_eq!(metadata.author.as_deref(), Some("dba-team"));
+        assert_eq!(metadata.tags, vec!["postgres", "migration"]);
+
+        assert_eq!(doc.sections.len(), 1);
+        assert_eq!(doc.sections[0].header, Some("Migrate".to_string()));
+        assert_eq!(doc.step_count(), 1);
+    }
+
+    #[test]
+    fn test_code_block_line_numbers() {
+        let content = r#"# Title
+
+Intro text
+spanning two lines.
+
+```bash
+echo one
+echo two
+```
+
+## Second
+
+```python
+print("three")
+```
+"#;
+
+        let doc = SysadminParser::parse(content).unwrap();
+        let code_blocks = doc.code_blocks();
+        assert_eq!(code_blocks.len(), 2);
+        assert_eq!(code_blocks[0].line_number, 6);
+        assert_eq!(code_blocks[1].line_number, 13);
+    }
+
+    #[test]
+    fn test_code_block_line_numbers_count_frontmatter() {
+        let content = "---\ntitle: T\n---\n\n```bash\nls\n```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+        assert_eq!(doc.code_blocks()[0].line_number, 5);
+    }
+
+    #[test]
+    fn test_info_string_attributes() {
+        let content = r#"```bash {danger, id=stop-db, note="only on primary"}
+systemctl stop postgresql
+```
+"#;
+
+        let doc = SysadminParser::parse(content).unwrap();
+        let code = doc.code_blocks()[0];
+        assert_eq!(code.language, "bash");
+        assert!(code.has_flag("danger"));
+        assert_eq!(code.attribute("id"), Some("stop-db"));
+        assert_eq!(code.attribute("note"), Some("only on primary"));
+    }
+
+    #[test]
+    fn test_plain_fence_has_no_attributes() {
+        let doc = SysadminParser::parse("```bash\nls\n```\n").unwrap();
+        let code = doc.code_blocks()[0];
+        assert_eq!(code.language, "bash");
+        assert!(code.attributes.is_empty());
+        assert!(code.flags.is_empty());
+    }
+
+    #[test]
+    fn test_parse_without_frontmatter_has_no_metadata() {
+        let doc = SysadminParser::parse("# Title\n\n---\n\nText\n").unwrap();
+        assert!(doc.metadata.is_none());
+    }
+
+    #[test]
+    fn test_to_markdown_round_trips() {
+        let content = "# Deploy\nIntro line\n* first\n* second\n```bash {danger, dir=/tmp}\nrm -rf ./build\n\n```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+        let formatted = doc.to_markdown();
+        let reparsed = SysadminParser::parse(&formatted).unwrap();
+
+        assert_eq!(reparsed.to_markdown(), formatted);
+        assert!(formatted.contains("- first\n- second"));
+        assert!(formatted.contains("```bash {danger, dir=/tmp}\n"));
+        assert_eq!(
+            reparsed.code_blocks()[0].content,
+            doc.code_blocks()[0].content
+        );
+    }
+
+    #[test]
+    fn test_text_keeps_its_source_markdown() {
+        let content = "# Notes\n\nSee [the docs](https://example.com) for ***really*** important\nnotes.\n\n* one\n  * nested\n\n```bash\nls\n```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+
+        let Block::Text { text, raw } = &doc.sections[0].blocks[0] else {
+            panic!("expected a text block");
+        };
+        assert!(text.starts_with("See the docs (https://example.com) for"));
+        assert_eq!(
+            raw.as_deref(),
+            Some("See [the docs](https://example.com) for ***really*** important\nnotes.\n\n* one\n  * nested")
+        );
+
+        let formatted = doc.to_markdown();
+        assert!(formatted.contains("[the docs](https://example.com) for ***really***"));
+        assert!(formatted.contains("- one\n  - nested\n\n```bash"));
+        assert_eq!(
+            SysadminParser::parse(&formatted).unwrap().to_markdown(),
+            formatted
+        );
+    }
+
+    #[test]
+    fn test_links_keep_their_url() {
+        let content = "See the [runbook wiki](https://wiki.example.com/db) or <https://status.example.com>.\n";
+        let doc = SysadminParser::parse(content).unwrap();
+
+        let Block::Text { text, .. } = &doc.sections[0].blocks[0] else {
+            panic!("expected a text block");
+        };
+        assert_eq!(
+            text.trim_end(),
+            "See the runbook wiki (https://wiki.example.com/db) or https://status.example.com."
+        );
+    }
+
+    #[test]
+    fn test_output_block_sets_expected_output() {
+        let content = "```bash\nsystemctl is-active nginx\n```\n\n```output\nactive\n```\n\nText\n\n```output\nstray\n```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+        let blocks = doc.code_blocks();
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].expected_output.as_deref(), Some("active"));
+        // Not directly after a step, so it stays a display-only block
+        assert_eq!(blocks[1].language, "output");
+        assert_eq!(doc.step_count(), 1);
+    }
+
+    #[test]
+    fn test_result_block_sets_expected_output() {
+        let content = "```bash\ncat deploy.sh\n```\n\n```result\n#!/bin/bash\n```\n\n```console\n#!/bin/bash\n```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+
+        assert_eq!(doc.step_count(), 1);
+        assert_eq!(
+            doc.code_blocks()[0].expected_output.as_deref(),
+            Some("#!/bin/bash")
+        );
+        assert_eq!(doc.code_blocks()[1].language, "console");
+    }
+
+    #[test]
+    fn test_expect_attribute_sets_expected_output() {
+        let doc = SysadminParser::parse("```bash {expect=\"/^ok/\"}\necho ok\n```\n").unwrap();
+        let expected = doc.code_blocks()[0].expected_output.clone();
+        assert_eq!(expected.as_deref(), Some("/^ok/"));
+    }
+
+    #[test]
+    fn test_block_quote_becomes_callout() {
+        let content =
+            "Before\n\n> **Warning:** don't skip this\n> or else\n\n> Plain quote\n\nAfter\n";
+        let doc = SysadminParser::parse(content).unwrap();
+        let blocks = &doc.sections[0].blocks;
+
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(
+            blocks[0],
+            Block::Text {
+                text: "Before\n".to_string(),
+                raw: Some("Before".to_string()),
+            }
+        );
+        assert_eq!(
+            blocks[1],
+            Block::Callout {
+                kind: CalloutKind::Warning,
+                text: "don't skip this or else".to_string(),
+            }
+        );
+        assert!(
+            matches!(&blocks[2], Block::Callout { kind: CalloutKind::Quote, text } if text == "Plain quote")
+        );
+
+        let formatted = doc.to_markdown();
+        assert!(formatted.contains("> **Warning:** don't skip this or else\n"));
+        assert_eq!(SysadminParser::parse(&formatted).unwrap(), doc);
+    }
+
+    #[test]
+    fn test_quoted_code_blocks_are_not_steps() {
+        let content =
+            "# Cleanup\n\n> **Warning:** never run this:\n>\n> ```bash\n> rm -rf /\n> ```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+
+        assert_eq!(doc.step_count(), 0);
+        assert!(doc.code_blocks().is_empty());
+        assert_eq!(SysadminParser::steps(content).count(), 0);
+        let blocks = &doc.sections[0].blocks;
+        assert!(matches!(
+            &blocks[0],
+            Block::Callout {
+                kind: CalloutKind::Warning,
+                ..
+            }
+        ));
+        assert!(matches!(&blocks[1], Block::Code(code) if code.quoted && !code.is_executable()));
+
+        let formatted = doc.to_markdown();
+        assert!(formatted.contains("> ```bash\n> rm -rf /\n> ```\n"));
+        assert_eq!(SysadminParser::parse(&formatted).unwrap(), doc);
+    }
+
+    #[test]
+    fn test_sysadmin_comments_become_metadata() {
+        let content = "---\nowner: platform\n---\n# Failover\n\n<!-- sysadmin: owner=dba-team, pager=\"db oncall\" -->\n\nPromote the replica. <!-- sysadmin: reviewed=2026-01 -->\n\n<!--\nsysadmin: tier=1\n-->\n\n<!-- just a note -->\n\n```bash\npg_ctl promote\n```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+
+        let extra = &doc.metadata.as_ref().unwrap().extra;
+        // The frontmatter wins over a comment
+        assert_eq!(extra.get("owner").map(String::as_str), Some("platform"));
+        assert_eq!(extra.get("pager").map(String::as_str), Some("db oncall"));
+        assert_eq!(extra.get("reviewed").map(String::as_str), Some("2026-01"));
+        assert_eq!(extra.get("tier").map(String::as_str), Some("1"));
+        assert_eq!(extra.len(), 4);
+
+        for block in doc.sections.iter().flat_map(|s| &s.blocks) {
+            if let Block::Text { text, .. } = block {
+                assert!(!text.contains("sysadmin:") && !text.contains("<!--"));
+            }
+        }
+        assert_eq!(doc.step_count(), 1);
+
+        let doc = SysadminParser::parse("<!-- sysadmin: owner=dba-team -->\n").unwrap();
+        let extra = &doc.metadata.unwrap().extra;
+        assert_eq!(extra.get("owner").map(String::as_str), Some("dba-team"));
+    }
+
+    #[test]
+    fn test_sysadmin_comments_survive_formatting() {
+        let content = "# Failover\n\n<!-- sysadmin: owner=dba-team, pager=\"db oncall\" -->\n\n```bash\npg_ctl promote\n```\n\nPromote the replica. <!-- sysadmin: reviewed=2026-01 -->\n\n<!--\nsysadmin: tier=1\n-->\n";
+        let doc = SysadminParser::parse(content).unwrap();
+
+        let formatted = doc.to_markdown();
+        assert!(formatted.contains("<!-- sysadmin: owner=dba-team, pager=\"db oncall\" -->"));
+        assert!(formatted.contains("<!--\nsysadmin: tier=1\n-->"));
+        assert_eq!(SysadminParser::parse(&formatted).unwrap(), doc);
+        assert_eq!(doc.metadata.unwrap().extra.len(), 4);
+    }
+
+    #[test]
+    fn test_task_list_becomes_checklist() {
+        let content = "# Preflight\n\nBefore starting:\n\n- [x] Backups verified\n- [ ] Change ticket **approved**\n- [X] On-call paged\n\nThen:\n\n- plain item\n- [x] not first\n";
+        let doc = SysadminParser::parse(content).unwrap();
+        let blocks = &doc.sections[0].blocks;
+
+        assert_eq!(blocks.len(), 3);
+        assert!(
+            matches!(&blocks[0], Block::Text { text, .. } if text.trim() == "Before starting:")
+        );
+        assert_eq!(
+            blocks[1],
+            Block::Checklist {
+                items: vec![
+                    (true, "Backups verified".to_string()),
+                    (false, "Change ticket **approved**".to_string()),
+                    (true, "On-call paged".to_string()),
+                ],
+            }
+        );
+        assert!(matches!(&blocks[2], Block::Text { text, .. } if text.contains("• [x] not first")));
+
+        let formatted = doc.to_markdown();
+        assert!(formatted.contains("- [x] Backups verified\n- [ ] Change ticket **approved**\n"));
+        assert_eq!(SysadminParser::parse(&formatted).unwrap(), doc);
+
+        assert!(is_task_list("1. [ ] first\n2. [ ] second"));
+        assert!(is_task_list("* [x] done"));
+        assert!(!is_task_list("- [link](https://example.com)"));
+    }
+
+    #[test]
+    fn test_checklist_item_with_a_step_keeps_its_text() {
+        let content = "# Deploy\n\n- [x] Drain the node\n- [ ] Stop the app:\n\n  ```bash\n  systemctl stop app\n  ```\n\n- [ ] Check it stopped\n";
+        let doc = SysadminParser::parse(content).unwrap();
+        let blocks = &doc.sections[0].blocks;
+
+        assert_eq!(
+            blocks[0],
+            Block::Checklist {
+                items: vec![
+                    (true, "Drain the node".to_string()),
+                    (false, "Stop the app:".to_string()),
+                ],
+            }
+        );
+        assert!(matches!(&blocks[1], Block::Code(code) if code.content == "systemctl stop app"));
+        assert_eq!(
+            blocks[2],
+            Block::Checklist {
+                items: vec![(false, "Check it stopped".to_string())],
+            }
+        );
+        assert_eq!(blocks.len(), 3);
+
+        let formatted = doc.to_markdown();
+        assert!(formatted.contains("- [ ] Stop the app:\n\n```bash\n"));
+        assert_eq!(SysadminParser::parse(&formatted).unwrap(), doc);
+    }
+
+    #[test]
+    fn test_table_becomes_table_block() {
+        let content = "## Hosts\n\n| Host | Role |\n|------|------|\n| `db01` | primary |\n| db02 | a \\| b |\n";
+        let doc = SysadminParser::parse(content).unwrap();
+
+        assert_eq!(
+            doc.sections[0].blocks,
+            vec![Block::Table {
+                headers: vec!["Host".to_string(), "Role".to_string()],
+                rows: vec![
+                    vec!["`db01`".to_string(), "primary".to_string()],
+                    vec!["db02".to_string(), "a | b".to_string()],
+                ],
+            }]
+        );
+        assert_eq!(SysadminParser::parse(&doc.to_markdown()).unwrap(), doc);
+    }
+
+    #[test]
+    fn test_host_attribute_and_section_default() {
+        let content = "## Backup {host=backup01}\n\n```bash\ndf -h\n```\n\n```bash {host=db01}\nuptime\n```\n\n## Local\n\n```bash\nls\n```\n";
+        let doc = SysadminParser::parse(content).unwrap();
+        let hosts: Vec<_> = doc
+            .code_blocks()
+            .iter()
+            .map(|c| c.remote_host.clone())
+            .collect();
+
+        assert_eq!(doc.sections[0].header.as_deref(), Some("Backup"));
+        assert_eq!(
+            hosts,
+            vec![Some("backup01".to_string()), Some("db01".to_string()), None]
+        );
+        assert_eq!(SysadminParser::parse(&doc.to_markdown()).unwrap(), doc);
+    }
+
+    #[test]
+    fn test_section_ids() {
+        let content = "# Step 1: Verify Backup\n\n## Notes\n\n## Notes\n\n## Notes-1\n\n## `kubectl` & friends\n\n## !!!\n";
+        let doc = SysadminParser::parse(content).unwrap();
+        let ids: Vec<_> = doc.sections.iter().map(|s| s.id.as_deref()).collect();
+
+        assert_eq!(
+            ids,
+            vec![
+                Some("step-1-verify-backup"),
+                Some("notes"),
+                Some("notes-1"),
+                Some("notes-1-1"),
+                Some("kubectl--friends"),
+                None,
+            ]
+        );
+        assert_eq!(
+            doc.section_by_id("notes-1")
+                .and_then(|s| s.header.as_deref()),
+            Some("Notes")
+        );
+        assert!(doc.section_by_id("missing").is_none());
+    }
 }
@@ -0,0 +1,402 @@
+use ratatui::{
+    style::{Modifier, Style},
+    text::Span,
+};
+use std::collections::HashMap;
+use std::ops::Range;
+
+use super::theme::Theme;
+
+/// Syntax highlighting for one line of code in some language
+pub trait Highlighter {
+    /// Split `line` into styled spans; unhighlighted text gets `base`
+    fn highlight(&self, line: &str, base: &Style) -> Vec<Span<'static>>;
+}
+
+/// Leaves the line as it is, in the base style
+pub struct PlainHighlighter;
+
+impl Highlighter for PlainHighlighter {
+    fn highlight(&self, line: &str, base: &Style) -> Vec<Span<'static>> {
+        vec![Span::styled(line.to_string(), *base)]
+    }
+}
+
+/// Comments, `$VARIABLES` and destructive commands in shell scripts
+pub struct BashHighlighter {
+    pub theme: Theme,
+    /// Lowercase patterns to pick out as destructive, as from [`danger_patterns`]
+    ///
+    /// [`danger_patterns`]: crate::model::danger_patterns
+    pub danger_patterns: Vec<String>,
+}
+
+impl Highlighter for BashHighlighter {
+    fn highlight(&self, line: &str, base: &Style) -> Vec<Span<'static>> {
+        highlight_shell(line, base, &self.theme, &self.danger_patterns)
+    }
+}
+
+/// Keywords, strings and comments in Python
+pub struct PythonHighlighter {
+    pub theme: Theme,
+}
+
+impl Highlighter for PythonHighlighter {
+    fn highlight(&self, line: &str, base: &Style) -> Vec<Span<'static>> {
+        highlight_python(line, base, &self.theme)
+    }
+}
+
+/// Highlighters by fence language, with a plain fallback
+pub struct HighlighterRegistry {
+    by_language: HashMap<String, Box<dyn Highlighter>>,
+    fallback: PlainHighlighter,
+}
+
+impl HighlighterRegistry {
+    /// No highlighters: every language is shown plain
+    pub fn empty() -> Self {
+        Self {
+            by_language: HashMap::new(),
+            fallback: PlainHighlighter,
+        }
+    }
+
+    /// The built-in highlighters, drawing in `theme`
+    ///
+    /// Shell lines have any of `danger_patterns` picked out as destructive.
+    pub fn new(theme: Theme, danger_patterns: &[String]) -> Self {
+        let mut registry = Self::empty();
+        for language in ["bash", "sh"] {
+            let danger_patterns = danger_patterns.to_vec();
+            registry.register(
+                language,
+                BashHighlighter {
+                    theme,
+                    danger_patterns,
+                },
+            );
+        }
+        for language in ["python", "python3"] {
+            registry.register(language, PythonHighlighter { theme });
+        }
+        registry
+    }
+
+    /// Use `highlighter` for `language`, replacing any registered before
+    pub fn register(&mut self, language: &str, highlighter: impl Highlighter + 'static) {
+        self.by_language
+            .insert(language.to_string(), Box::new(highlighter));
+    }
+
+    /// The highlighter for `language`, or the plain one if none is registered
+    pub fn get(&self, language: &str) -> &dyn Highlighter {
+        match self.by_language.get(language) {
+            Some(highlighter) => highlighter.as_ref(),
+            None => &self.fallback,
+        }
+    }
+}
+
+/// Simple syntax highlighting for shell commands
+fn highlight_shell(
+    line: &str,
+    base_style: &Style,
+    theme: &Theme,
+    danger_patterns: &[String],
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let trimmed = line.trim_start();
+    let indent_len = line.len().saturating_sub(trimmed.len());
+    let indent = &line[..indent_len];
+    if !indent.is_empty() {
+        spans.push(Span::raw(indent.to_string()));
+    }
+    if trimmed.is_empty() {
+        return spans;
+    }
+
+    // Comment
+    if trimmed.starts_with('#') {
+        spans.push(Span::styled(
+            trimmed.to_string(),
+            Style::default()
+                .fg(theme.comment)
+                .add_modifier(Modifier::ITALIC),
+        ));
+        return spans;
+    }
+
+    // Only the destructive tokens are red; the text around them keeps its highlighting
+    let danger_style = Style::default()
+        .fg(theme.danger)
+        .add_modifier(Modifier::BOLD);
+    let mut end = 0;
+    for danger in danger_matches(trimmed, danger_patterns) {
+        push_with_variables(&mut spans, &trimmed[end..danger.start], base_style, theme);
+        spans.push(Span::styled(
+            trimmed[danger.clone()].to_string(),
+            danger_style,
+        ));
+        end = danger.end;
+    }
+    push_with_variables(&mut spans, &trimmed[end..], base_style, theme);
+    spans
+}
+
+/// Byte ranges of the `patterns` found in `line`, in order and not overlapping
+///
+/// Patterns are matched case-insensitively and without their surrounding
+/// whitespace. A pattern that starts or ends with a word character only counts
+/// on a word boundary there, so `rm -rf` doesn't match in `norm -rf` and
+/// `--force` doesn't match in `--forced`.
+fn danger_matches(line: &str, patterns: &[String]) -> Vec<Range<usize>> {
+    // ASCII lowercasing keeps byte offsets the same as in `line`
+    let lower = line.to_ascii_lowercase();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    let patterns: Vec<&str> = patterns
+        .iter()
+        .map(|pattern| pattern.trim())
+        .filter(|pattern| !pattern.is_empty())
+        .collect();
+    let mut matches = Vec::new();
+    let mut from = 0;
+
+    while from < lower.len() {
+        // The earliest match, preferring the longer pattern where two start together
+        let found = patterns
+            .iter()
+            .flat_map(|pattern| {
+                lower[from..]
+                    .match_indices(pattern)
+                    .map(move |(idx, _)| (pattern, from + idx..from + idx + pattern.len()))
+            })
+            .filter(|(pattern, m)| {
+                let open_start =
+                    !pattern.starts_with(is_word) || !lower[..m.start].ends_with(is_word);
+                let open_end = !pattern.ends_with(is_word) || !lower[m.end..].starts_with(is_word);
+                open_start && open_end
+            })
+            .map(|(_, m)| m)
+            .min_by_key(|m| (m.start, usize::MAX - m.end));
+        let Some(found) = found else {
+            break;
+        };
+        from = found.end;
+        matches.push(found);
+    }
+    matches
+}
+
+/// Push `text` in `base_style`, with `$VARIABLE` references picked out
+fn push_with_variables(
+    spans: &mut Vec<Span<'static>>,
+    text: &str,
+    base_style: &Style,
+    theme: &Theme,
+) {
+    let mut remaining = text;
+    while let Some(dollar_idx) = remaining.find('$') {
+        if dollar_idx > 0 {
+            spans.push(Span::styled(
+                remaining[..dollar_idx].to_string(),
+                *base_style,
+            ));
+        }
+
+        // process var after $
+        let after = &remaining[dollar_idx + 1..];
+        let var_end = after
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(after.len());
+        let var = &after[..var_end];
+        spans.push(Span::styled(
+            format!("${}", var),
+            Style::default()
+                .fg(theme.variable)
+                .add_modifier(Modifier::BOLD),
+        ));
+
+        // advance remaining
+        remaining = &after[var_end..];
+    }
+    if !remaining.is_empty() {
+        spans.push(Span::styled(remaining.to_string(), *base_style));
+    }
+}
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// Keyword, string, and comment highlighting for Python
+fn highlight_python(line: &str, base_style: &Style, theme: &Theme) -> Vec<Span<'static>> {
+    let keyword_style = Style::default()
+        .fg(theme.keyword)
+        .add_modifier(Modifier::BOLD);
+    let string_style = Style::default().fg(theme.string);
+    let comment_style = Style::default()
+        .fg(theme.comment)
+        .add_modifier(Modifier::ITALIC);
+
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut rest = line;
+
+    while let Some(c) = rest.chars().next() {
+        let token_len = if c == '#' {
+            rest.len()
+        } else if c == '"' || c == '\'' {
+            quoted_len(rest, c)
+        } else if c.is_alphabetic() || c == '_' {
+            rest.find(|ch: char| !ch.is_alphanumeric() && ch != '_')
+                .unwrap_or(rest.len())
+        } else {
+            plain.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+
+        let (token, remaining) = rest.split_at(token_len);
+        let style = if c == '#' {
+            Some(comment_style)
+        } else if c == '"' || c == '\'' {
+            Some(string_style)
+        } else if PYTHON_KEYWORDS.contains(&token) {
+            Some(keyword_style)
+        } else {
+            None
+        };
+
+        match style {
+            Some(style) => {
+                if !plain.is_empty() {
+                    spans.push(Span::styled(std::mem::take(&mut plain), *base_style));
+                }
+                spans.push(Span::styled(token.to_string(), style));
+            }
+            None => plain.push_str(token),
+        }
+        rest = remaining;
+    }
+
+    if !plain.is_empty() {
+        spans.push(Span::styled(plain, *base_style));
+    }
+    spans
+}
+
+/// Byte length of the string literal at the start of `s`, including quotes
+///
+/// Unterminated strings run to the end of the line.
+fn quoted_len(s: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (idx, c) in s.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return idx + c.len_utf8();
+        }
+    }
+    s.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::danger_patterns;
+
+    fn contents(spans: &[Span]) -> Vec<String> {
+        spans.iter().map(|s| s.content.to_string()).collect()
+    }
+
+    #[test]
+    fn test_bash_highlights_variables_and_comments() {
+        let theme = Theme::dark();
+        let bash = BashHighlighter {
+            theme,
+            danger_patterns: danger_patterns(&[]),
+        };
+        let base = Style::default();
+
+        let spans = bash.highlight("  echo $HOME done", &base);
+        assert_eq!(contents(&spans), vec!["  ", "echo ", "$HOME", " done"]);
+        assert_eq!(spans[2].style.fg, Some(theme.variable));
+
+        let comment = bash.highlight("# note", &base);
+        assert_eq!(comment[0].style.fg, Some(theme.comment));
+    }
+
+    #[test]
+    fn test_bash_highlights_only_the_danger_token() {
+        let theme = Theme::dark();
+        let bash = BashHighlighter {
+            theme,
+            danger_patterns: danger_patterns(&[]),
+        };
+        let base = Style::default();
+
+        let spans = bash.highlight("git push --force origin $BRANCH", &base);
+        assert_eq!(
+            contents(&spans),
+            vec!["git push ", "--force", " origin ", "$BRANCH"]
+        );
+        assert_eq!(spans[1].style.fg, Some(theme.danger));
+        assert_eq!(spans[2].style, base);
+        assert_eq!(spans[3].style.fg, Some(theme.variable));
+
+        let spans = bash.highlight("RM -rf /tmp/x && rm y", &base);
+        assert_eq!(contents(&spans), vec!["RM -rf", " /tmp/x && rm y"]);
+
+        let spans = bash.highlight("psql -c 'DROP TABLE users'", &base);
+        assert_eq!(contents(&spans), vec!["psql -c '", "DROP TABLE", " users'"]);
+
+        let spans = bash.highlight("perform --forced drop dropbox", &base);
+        assert_eq!(contents(&spans), vec!["perform --forced drop dropbox"]);
+    }
+
+    #[test]
+    fn test_bash_highlights_custom_danger_patterns() {
+        let theme = Theme::dark();
+        let registry = HighlighterRegistry::new(theme, &danger_patterns(&["MKFS".to_string()]));
+        let base = Style::default();
+
+        let spans = registry.get("bash").highlight("mkfs.ext4 /dev/sdb1", &base);
+        assert_eq!(contents(&spans), vec!["mkfs", ".ext4 /dev/sdb1"]);
+        assert_eq!(spans[0].style.fg, Some(theme.danger));
+
+        let spans = registry.get("sh").highlight("delete $ID", &base);
+        assert_eq!(contents(&spans), vec!["delete", " ", "$ID"]);
+    }
+
+    #[test]
+    fn test_registry_falls_back_to_plain() {
+        let registry = HighlighterRegistry::new(Theme::dark(), &[]);
+        let base = Style::default();
+
+        let spans = registry.get("yaml").highlight("key: $value", &base);
+        assert_eq!(contents(&spans), vec!["key: $value"]);
+        assert_eq!(registry.get("sh").highlight("echo $X", &base).len(), 2);
+    }
+
+    #[test]
+    fn test_registered_highlighter_replaces_builtin() {
+        struct Shouting;
+        impl Highlighter for Shouting {
+            fn highlight(&self, line: &str, base: &Style) -> Vec<Span<'static>> {
+                vec![Span::styled(line.to_uppercase(), *base)]
+            }
+        }
+
+        let mut registry = HighlighterRegistry::new(Theme::dark(), &[]);
+        registry.register("bash", Shouting);
+        let spans = registry.get("bash").highlight("ls", &Style::default());
+        assert_eq!(contents(&spans), vec!["LS"]);
+    }
+}
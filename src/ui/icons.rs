@@ -0,0 +1,58 @@
+use emojis;
+
+use crate::model::CalloutKind;
+
+/// Centralized emoji icon manager
+pub(super) struct Icons {
+    pub done: &'static str,
+    pub current: &'static str,
+    pub pending: &'static str,
+    pub warning: &'static str,
+    pub danger: &'static str,
+    pub info: &'static str,
+    pub tip: &'static str,
+}
+
+pub(super) fn icons() -> Icons {
+    Icons {
+        done: emojis::get("check_mark_button")
+            .map(|e| e.as_str())
+            .unwrap_or("✔"),
+        current: emojis::get("arrow_right")
+            .map(|e| e.as_str())
+            .unwrap_or("➡"),
+        pending: emojis::get("radio_button")
+            .map(|e| e.as_str())
+            .unwrap_or("○"),
+        warning: emojis::get("warning").map(|e| e.as_str()).unwrap_or("⚠️"),
+        danger: emojis::get("fire").map(|e| e.as_str()).unwrap_or("🔥"),
+        info: emojis::get("information")
+            .map(|e| e.as_str())
+            .unwrap_or("ℹ️"),
+        tip: emojis::get("light_bulb")
+            .map(|e| e.as_str())
+            .unwrap_or("💡"),
+    }
+}
+
+impl Icons {
+    /// Icon for a callout's title; plain quotes have none
+    pub fn callout(&self, kind: CalloutKind) -> Option<&'static str> {
+        match kind {
+            CalloutKind::Note => Some(self.info),
+            CalloutKind::Tip => Some(self.tip),
+            CalloutKind::Warning => Some(self.warning),
+            CalloutKind::Danger => Some(self.danger),
+            CalloutKind::Quote => None,
+        }
+    }
+
+    /// Box drawn before a checklist item
+    pub fn checkbox(&self, checked: bool) -> &'static str {
+        if checked {
+            "☑"
+        } else {
+            "☐"
+        }
+    }
+}
@@ -1,5 +1,17 @@
+pub mod highlight;
+mod icons;
 pub mod renderer;
+mod table;
+pub mod tee;
+mod terminal;
+pub mod theme;
 pub mod tui;
 
+pub use highlight::{Highlighter, HighlighterRegistry};
 pub use renderer::Renderer;
+pub use tee::TeeWriter;
+pub use theme::Theme;
 pub use tui::TuiApp;
+
+#[cfg(test)]
+mod tests;
@@ -1,48 +1,182 @@
 use anyhow::Result;
 use crossterm::{
     execute,
-    style::{Color, Print, ResetColor, SetForegroundColor},
+    style::{Print, ResetColor, SetForegroundColor},
+    Command,
 };
-use std::io::{stdout, Write};
+use ratatui::style::Color;
+use similar::{ChangeTag, TextDiff};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::time::Duration;
 
-use crate::model::CodeBlock;
+use super::icons::icons;
+use super::table::{column_widths, pad_cell, COLUMN_SPACING};
+use super::tee::{strip_ansi, TeeWriter};
+use super::theme::{no_color, Theme};
+use crate::executor::StepResult;
+use crate::model::{expected_pattern, Block, CalloutKind, CodeBlock, Document, Redactor};
 
 pub struct Renderer {
-    current_step: usize,
-    total_steps: usize,
+    pub(super) current_step: usize,
+    pub(super) total_steps: usize,
+    /// File that receives a plain-text copy of everything rendered
+    transcript: Option<File>,
+    theme: Theme,
+    /// Whether to emit color escapes at all
+    pub(super) use_color: bool,
+    /// Template for the message shown before dropping to a shell
+    shell_prompt: Option<String>,
+    /// Masks secrets in step content and output
+    redactor: Redactor,
 }
 
 impl Renderer {
+    /// A renderer that uses color only when stdout is a terminal and
+    /// `NO_COLOR` is not set
     pub fn new() -> Self {
         Self {
             current_step: 0,
             total_steps: 0,
+            transcript: None,
+            theme: Theme::default(),
+            use_color: io::stdout().is_terminal() && !no_color(),
+            shell_prompt: None,
+            redactor: Redactor::default(),
         }
     }
 
+    /// A renderer that never emits color escapes
+    pub fn new_plain() -> Self {
+        Self {
+            use_color: false,
+            ..Self::new()
+        }
+    }
+
+    /// Render with these colors instead of the default dark theme
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Also write everything rendered, minus colors, to `file`
+    pub fn with_transcript(mut self, file: File) -> Self {
+        self.transcript = Some(file);
+        self
+    }
+
+    /// Say `template` before dropping to a shell, instead of the built-in
+    /// "Run the command above..." message
+    ///
+    /// `{step}` and `{total}` in the template become the step number and count.
+    pub fn with_shell_prompt_message(mut self, template: impl Into<String>) -> Self {
+        self.shell_prompt = Some(template.into());
+        self
+    }
+
+    /// Mask secrets matching the redactor's patterns in everything rendered
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// Switch to `color` for the following text, if color is on
+    pub(super) fn fg(&self, color: Color) -> Paint {
+        if self.use_color {
+            Paint::Foreground(color)
+        } else {
+            Paint::Off
+        }
+    }
+
+    /// Go back to the terminal's default color, if color is on
+    fn reset(&self) -> Paint {
+        if self.use_color {
+            Paint::Reset
+        } else {
+            Paint::Off
+        }
+    }
+
+    /// Where rendered output goes: stdout, plus the transcript if any
+    fn out(&self) -> TeeWriter<'_> {
+        TeeWriter::new(self.transcript.as_ref())
+    }
+
+    /// The transcript file, for copying step output into as it is printed
+    pub fn transcript(&self) -> Option<&File> {
+        self.transcript.as_ref()
+    }
+
+    /// Copy step output that was printed separately into the transcript, if any
+    pub fn record_output(&self, output: &str) -> Result<()> {
+        if let Some(mut file) = self.transcript.as_ref() {
+            file.write_all(&strip_ansi(output.as_bytes()))?;
+            file.flush()?;
+        }
+        Ok(())
+    }
+
     pub fn set_total_steps(&mut self, total: usize) {
         self.total_steps = total;
     }
 
-    /// Render a section header
-    pub fn render_header(&self, header: &str, level: u32) -> Result<()> {
-        let mut stdout = stdout();
+    /// Advance the step counter without rendering anything
+    pub fn skip_step(&mut self) {
+        self.current_step += 1;
+    }
+
+    /// Render a whole document read-only: headers, text and every step
+    ///
+    /// Steps are numbered as in a run, but their variables are left as written.
+    pub fn render_document(&mut self, doc: &Document) -> Result<()> {
+        self.set_total_steps(doc.step_count());
+
+        for section in &doc.sections {
+            if let Some(header) = &section.header {
+                let level = section.header_level.unwrap_or(1);
+                self.render_header(header, level, section.step_count())?;
+            }
+            for block in &section.blocks {
+                self.render_block(block)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Render any block; executable steps advance the step counter
+    pub fn render_block(&mut self, block: &Block) -> Result<()> {
+        match block {
+            Block::Text { text, .. } => self.render_text(text),
+            Block::Table { headers, rows } => self.render_table(headers, rows),
+            Block::Callout { kind, text } => self.render_callout(*kind, text),
+            Block::Checklist { items } => self.render_checklist(items),
+            Block::Code(code) if !code.is_executable() => self.render_listing(code),
+            Block::Code(code) => self.render_code(code, &HashMap::new(), &HashMap::new()),
+        }
+    }
+
+    /// Render a section header, followed by how many steps the section has
+    pub fn render_header(&self, header: &str, level: u32, steps: usize) -> Result<()> {
+        let mut stdout = self.out();
 
         // Add spacing
         writeln!(stdout)?;
 
-        // Different colors for different header levels
-        let color = match level {
-            1 => Color::Cyan,
-            2 => Color::Blue,
-            _ => Color::White,
-        };
-
         execute!(
             stdout,
-            SetForegroundColor(color),
-            Print(format!("{} {}", "#".repeat(level as usize), header)),
-            ResetColor,
+            self.fg(self.theme.header(level)),
+            Print(format!(
+                "{} {}{}",
+                "#".repeat(level as usize),
+                header,
+                step_count_suffix(steps)
+            )),
+            self.reset(),
             Print("\n")
         )?;
 
@@ -53,7 +187,7 @@ impl Renderer {
 
     /// Render documentation text
     pub fn render_text(&self, text: &str) -> Result<()> {
-        let mut stdout = stdout();
+        let mut stdout = self.out();
 
         // Simple text rendering - just print it
         for line in text.lines() {
@@ -66,31 +200,186 @@ impl Renderer {
         Ok(())
     }
 
-    /// Render a code block with syntax highlighting (simple version)
-    pub fn render_code(&mut self, code: &CodeBlock) -> Result<()> {
-        let mut stdout = stdout();
+    /// Render a table as aligned columns under an underlined header row
+    pub fn render_table(&self, headers: &[String], rows: &[Vec<String>]) -> Result<()> {
+        let mut stdout = self.out();
+        let widths = column_widths(headers, rows);
+        let spacing = " ".repeat(COLUMN_SPACING);
+        let format_row = |cells: &[String]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| pad_cell(cell, *width))
+                .collect();
+            cells.join(&spacing).trim_end().to_string()
+        };
+        let rule: Vec<String> = widths.iter().map(|width| "─".repeat(*width)).collect();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.accent),
+            Print(format_row(headers)),
+            self.reset(),
+            Print("\n"),
+            self.fg(self.theme.muted),
+            Print(rule.join(&spacing)),
+            self.reset(),
+            Print("\n")
+        )?;
+        for row in rows {
+            writeln!(stdout, "{}", format_row(row))?;
+        }
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Render a callout as a box with its kind's icon and color
+    pub fn render_callout(&self, kind: CalloutKind, text: &str) -> Result<()> {
+        let mut stdout = self.out();
+        let title = match (icons().callout(kind), kind.label()) {
+            (Some(icon), Some(label)) => format!("┌─ {} {}", icon, label),
+            _ => "┌─".to_string(),
+        };
 
+        let color = self.theme.callout(kind);
+
+        execute!(
+            stdout,
+            self.fg(color),
+            Print(title),
+            self.reset(),
+            Print("\n")
+        )?;
+        for line in text.lines() {
+            execute!(
+                stdout,
+                self.fg(color),
+                Print("│ "),
+                self.reset(),
+                Print(format!("{}\n", line))
+            )?;
+        }
+        execute!(
+            stdout,
+            self.fg(color),
+            Print("└─"),
+            self.reset(),
+            Print("\n")
+        )?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Render a task list, checked items in the done color
+    pub fn render_checklist(&self, items: &[(bool, String)]) -> Result<()> {
+        let mut stdout = self.out();
+        let icons = icons();
+
+        for (checked, text) in items {
+            let color = if *checked {
+                self.theme.done
+            } else {
+                self.theme.pending
+            };
+            execute!(
+                stdout,
+                self.fg(color),
+                Print(icons.checkbox(*checked)),
+                self.reset(),
+                Print(format!(" {}\n", text))
+            )?;
+        }
+        writeln!(stdout)?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Render a code block with syntax highlighting (simple version)
+    ///
+    /// Variable references in the content are substituted from `vars`.
+    pub fn render_code(
+        &mut self,
+        code: &CodeBlock,
+        vars: &HashMap<String, String>,
+        env: &HashMap<String, String>,
+    ) -> Result<()> {
         self.current_step += 1;
 
+        let mut stdout = self.out();
+
         // Step indicator
         writeln!(stdout)?;
         execute!(
             stdout,
-            SetForegroundColor(Color::Yellow),
+            self.fg(self.theme.current),
             Print(format!(
                 "Step {}/{} [{}]:",
-                self.current_step, self.total_steps, code.language
+                self.current_step,
+                self.total_steps,
+                step_label(code)
             )),
-            ResetColor,
-            Print("\n")
+            self.reset()
         )?;
+        if let Some(note) = code.note() {
+            execute!(
+                stdout,
+                self.fg(self.theme.muted),
+                Print(format!(" {}", note)),
+                self.reset()
+            )?;
+        }
+        writeln!(stdout)?;
 
         // Code content with indentation
-        execute!(stdout, SetForegroundColor(Color::Green))?;
-        for line in code.content.lines() {
+        execute!(stdout, self.fg(self.theme.code))?;
+        let content = code.render_content(vars, env);
+        for line in self.redactor.redact(&content).lines() {
+            writeln!(stdout, "  {}", line)?;
+        }
+        execute!(stdout, self.reset())?;
+
+        writeln!(stdout)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Show a step as edited at the prompt, just before it runs
+    pub fn render_edited(&self, code: &CodeBlock) -> Result<()> {
+        let mut stdout = self.out();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.accent),
+            Print("Running the edited step:\n"),
+            self.fg(self.theme.code)
+        )?;
+        for line in self.redactor.redact(&code.content).lines() {
             writeln!(stdout, "  {}", line)?;
         }
-        execute!(stdout, ResetColor)?;
+        execute!(stdout, self.reset())?;
+
+        writeln!(stdout)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Render a display-only code block, which is not a step
+    pub fn render_listing(&self, code: &CodeBlock) -> Result<()> {
+        let mut stdout = self.out();
+
+        writeln!(stdout)?;
+        execute!(
+            stdout,
+            self.fg(self.theme.muted),
+            Print(format!("[{}]:\n", code.language))
+        )?;
+        for line in self.redactor.redact(&code.content).lines() {
+            writeln!(stdout, "  {}", line)?;
+        }
+        execute!(stdout, self.reset())?;
 
         writeln!(stdout)?;
         stdout.flush()?;
@@ -99,21 +388,322 @@ impl Renderer {
 
     /// Render the shell prompt
     pub fn render_shell_prompt(&self) -> Result<()> {
-        let mut stdout = stdout();
+        let mut stdout = self.out();
+
+        if let Some(message) = self.shell_prompt_message() {
+            execute!(
+                stdout,
+                self.fg(self.theme.accent),
+                Print(format!("→ {}", message)),
+                self.reset(),
+                Print("\n\n")
+            )?;
+            stdout.flush()?;
+            return Ok(());
+        }
 
         execute!(
             stdout,
-            SetForegroundColor(Color::Cyan),
+            self.fg(self.theme.accent),
             Print("→ Dropping into shell. Run the command above, then type "),
-            SetForegroundColor(Color::Yellow),
+            self.fg(self.theme.current),
             Print("exit"),
-            SetForegroundColor(Color::Cyan),
+            self.fg(self.theme.accent),
             Print(" or press "),
-            SetForegroundColor(Color::Yellow),
+            self.fg(self.theme.current),
             Print("Ctrl-D"),
-            SetForegroundColor(Color::Cyan),
+            self.fg(self.theme.accent),
             Print(" to continue."),
-            ResetColor,
+            self.reset(),
+            Print("\n")
+        )?;
+
+        writeln!(stdout)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// The configured shell prompt message for the current step, if any
+    pub(super) fn shell_prompt_message(&self) -> Option<String> {
+        self.shell_prompt.as_ref().map(|template| {
+            template
+                .replace("{step}", &self.current_step.to_string())
+                .replace("{total}", &self.total_steps.to_string())
+        })
+    }
+
+    /// Ask what to do with the step just shown
+    pub fn render_step_prompt(&self) -> Result<()> {
+        let mut stdout = self.out();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.accent),
+            Print("[r]un / [e]dit / [s]hell / [k]skip / [q]uit (Enter for shell): "),
+            self.reset()
+        )?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Ask whether to go on into a section: "Proceed with section 'X'? [y/N]"
+    pub fn render_section_gate(&self, header: &str) -> Result<()> {
+        let mut stdout = self.out();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.warning),
+            Print(format!("Proceed with section '{}'? [y/N] ", header)),
+            self.reset()
+        )?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Note that a step was skipped without running anything
+    pub fn render_skipped(&self, step: usize) -> Result<()> {
+        let mut stdout = self.out();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.muted),
+            Print(format!("↷ Skipped step {}", step)),
+            self.reset(),
+            Print("\n")
+        )?;
+
+        writeln!(stdout)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Note that the run was stopped before `step`
+    pub fn render_stopped(&self, step: usize) -> Result<()> {
+        let mut stdout = self.out();
+
+        writeln!(stdout)?;
+        execute!(
+            stdout,
+            self.fg(self.theme.warning),
+            Print(format!("■ Stopped before step {}", step)),
+            self.reset(),
+            Print("\n")
+        )?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Remind the user that the step they're about to run needs root
+    pub fn render_needs_root(&self, sudo_cmd: &str) -> Result<()> {
+        let mut stdout = self.out();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.warning),
+            Print(format!(
+                "⚠ This step needs root; run it with `{}`",
+                sudo_cmd
+            )),
+            self.reset(),
+            Print("\n")
+        )?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Note that a step's `dir=` doesn't exist, before dropping to a shell anyway
+    pub fn render_missing_dir(&self, dir: &Path) -> Result<()> {
+        let mut stdout = self.out();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.warning),
+            Print(format!(
+                "⚠ Step directory {} does not exist; staying in the current one",
+                dir.display()
+            )),
+            self.reset(),
+            Print("\n")
+        )?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Remind the user that the step they're about to run belongs on another host
+    pub fn render_remote_notice(&self, host: &str) -> Result<()> {
+        let mut stdout = self.out();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.warning),
+            Print(format!("⚠ This step runs on {}; ssh there first", host)),
+            self.reset(),
+            Print("\n")
+        )?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Warn that the runbook's steps are written for a different shell than
+    /// the one steps are dropped into
+    pub fn render_shell_mismatch(&self, language: &str, shell: &str) -> Result<()> {
+        let mut stdout = self.out();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.warning),
+            Print(format!(
+                "⚠ This runbook targets {} but your shell is {}",
+                language, shell
+            )),
+            self.reset(),
+            Print("\n")
+        )?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Render an error message
+    pub fn render_error(&self, message: &str) -> Result<()> {
+        let mut stdout = self.out();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.danger),
+            Print(format!("✗ {}", message)),
+            self.reset(),
+            Print("\n")
+        )?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Show how a step's stdout differs from what it was expected to print
+    ///
+    /// Substring expectations get a line diff; for a `/regex/` the actual
+    /// output is shown under the pattern.
+    pub fn render_output_mismatch(&self, step: usize, expected: &str, actual: &str) -> Result<()> {
+        let mut stdout = self.out();
+        let actual = self.redactor.redact(actual);
+        let actual = actual.as_ref();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.danger),
+            Print(format!(
+                "✗ Step {} output did not match the expected output",
+                step
+            )),
+            self.reset(),
+            Print("\n")
+        )?;
+
+        if let Some(pattern) = expected_pattern(expected) {
+            writeln!(stdout, "  expected a match for /{}/, got:", pattern)?;
+            for line in actual.lines() {
+                writeln!(stdout, "  | {}", line)?;
+            }
+        } else {
+            writeln!(stdout, "  --- expected\n  +++ actual")?;
+            for change in TextDiff::from_lines(expected, actual).iter_all_changes() {
+                let (sign, color) = match change.tag() {
+                    ChangeTag::Delete => ("-", self.theme.danger),
+                    ChangeTag::Insert => ("+", self.theme.done),
+                    ChangeTag::Equal => (" ", Color::Reset),
+                };
+                execute!(
+                    stdout,
+                    self.fg(color),
+                    Print(format!(
+                        "  {}{}",
+                        sign,
+                        change.value().trim_end_matches('\n')
+                    )),
+                    self.reset(),
+                    Print("\n")
+                )?;
+            }
+        }
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Ask the user to confirm a dangerous step by typing `expected` or "yes"
+    pub fn render_danger_confirmation(&self, expected: &str) -> Result<()> {
+        let mut stdout = self.out();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.danger),
+            Print("⚠ This step looks destructive. Type "),
+            self.fg(self.theme.warning),
+            Print(expected),
+            self.fg(self.theme.danger),
+            Print(" or "),
+            self.fg(self.theme.warning),
+            Print("yes"),
+            self.fg(self.theme.danger),
+            Print(" to run it: "),
+            self.reset()
+        )?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Announce that a failed attempt at a step will be retried after `delay`
+    pub fn render_retry(
+        &self,
+        step: usize,
+        attempt: u32,
+        attempts: u32,
+        reason: &str,
+        delay: Duration,
+    ) -> Result<()> {
+        let mut stdout = self.out();
+
+        execute!(
+            stdout,
+            self.fg(self.theme.warning),
+            Print(format!(
+                "↻ Step {} attempt {}/{} failed with {}; retrying in {:?}",
+                step, attempt, attempts, reason, delay
+            )),
+            self.reset(),
+            Print("\n")
+        )?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Render how far a run got, for one that stopped early or skipped steps
+    ///
+    /// Shown instead of [`render_completion`](Self::render_completion) unless
+    /// every step ran.
+    pub fn render_progress_summary(
+        &self,
+        completed: usize,
+        skipped: usize,
+        total: usize,
+    ) -> Result<()> {
+        let mut stdout = self.out();
+
+        writeln!(stdout)?;
+        execute!(
+            stdout,
+            self.fg(self.theme.warning),
+            Print(progress_summary(completed, skipped, total)),
+            self.reset(),
             Print("\n")
         )?;
 
@@ -122,16 +712,97 @@ impl Renderer {
         Ok(())
     }
 
+    /// Render how many steps succeeded, and which failed, after a batch run
+    pub fn render_summary(&self, succeeded: usize, total: usize, failed: &[usize]) -> Result<()> {
+        let mut stdout = self.out();
+
+        writeln!(stdout)?;
+        execute!(
+            stdout,
+            self.fg(self.theme.warning),
+            Print(format!("{}/{} steps succeeded", succeeded, total)),
+            self.reset(),
+            Print("\n")
+        )?;
+        if !failed.is_empty() {
+            let failed: Vec<String> = failed.iter().map(|s| s.to_string()).collect();
+            execute!(
+                stdout,
+                self.fg(self.theme.danger),
+                Print(format!("✗ Failed steps: {}", failed.join(", "))),
+                self.reset(),
+                Print("\n")
+            )?;
+        }
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Render a table of the steps that ran: number, language, outcome and duration
+    ///
+    /// Rows are green for steps that succeeded and red for the rest. Skipped
+    /// steps are left out.
+    pub fn render_run_table(&self, results: &[StepResult]) -> Result<()> {
+        let ran: Vec<&StepResult> = results.iter().filter(|r| !r.skipped).collect();
+        if ran.is_empty() {
+            return Ok(());
+        }
+
+        let headers: Vec<String> = ["Step", "Language", "Status", "Duration"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+        let rows: Vec<Vec<String>> = ran.iter().map(|result| run_table_row(result)).collect();
+        let widths = column_widths(&headers, &rows);
+        let spacing = " ".repeat(COLUMN_SPACING);
+        let format_row = |cells: &[String]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| pad_cell(cell, *width))
+                .collect();
+            cells.join(&spacing).trim_end().to_string()
+        };
+
+        let mut stdout = self.out();
+        writeln!(stdout)?;
+        execute!(
+            stdout,
+            self.fg(self.theme.accent),
+            Print(format_row(&headers)),
+            self.reset(),
+            Print("\n")
+        )?;
+        for (result, row) in ran.iter().zip(&rows) {
+            let color = if result.succeeded() {
+                self.theme.done
+            } else {
+                self.theme.danger
+            };
+            execute!(
+                stdout,
+                self.fg(color),
+                Print(format_row(row)),
+                self.reset(),
+                Print("\n")
+            )?;
+        }
+
+        stdout.flush()?;
+        Ok(())
+    }
+
     /// Render completion message
     pub fn render_completion(&self) -> Result<()> {
-        let mut stdout = stdout();
+        let mut stdout = self.out();
 
         writeln!(stdout)?;
         execute!(
             stdout,
-            SetForegroundColor(Color::Green),
+            self.fg(self.theme.done),
             Print("✓ All steps completed!"),
-            ResetColor,
+            self.reset(),
             Print("\n")
         )?;
 
@@ -141,8 +812,95 @@ impl Renderer {
     }
 }
 
+/// A row of [`Renderer::render_run_table`], e.g. `["3", "bash", "✗ exit 2", "1.4s"]`
+pub(super) fn run_table_row(result: &StepResult) -> Vec<String> {
+    let status = if result.succeeded() {
+        "✓ ok".to_string()
+    } else if result.timed_out {
+        "✗ timed out".to_string()
+    } else if result.output_mismatch {
+        "✗ output".to_string()
+    } else {
+        match result.exit_code {
+            Some(code) => format!("✗ exit {}", code),
+            None => "✗ signal".to_string(),
+        }
+    };
+    vec![
+        result.index.to_string(),
+        result.language.clone(),
+        status,
+        format_duration(result.duration),
+    ]
+}
+
+/// A short duration: `350ms`, `4.2s` or `3m07s`
+pub(super) fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    if millis < 1000 {
+        format!("{}ms", millis)
+    } else if millis < 60_000 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        let secs = duration.as_secs();
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// E.g. "Completed 5, skipped 2, 4 remaining (of 11 steps)"
+pub(super) fn progress_summary(completed: usize, skipped: usize, total: usize) -> String {
+    let remaining = total.saturating_sub(completed + skipped);
+    format!(
+        "Completed {}, skipped {}, {} remaining (of {} steps)",
+        completed, skipped, remaining, total
+    )
+}
+
+/// E.g. " (3 steps)" after a section header; empty for a section without steps
+pub(super) fn step_count_suffix(steps: usize) -> String {
+    match steps {
+        0 => String::new(),
+        1 => " (1 step)".to_string(),
+        n => format!(" ({} steps)", n),
+    }
+}
+
 impl Default for Renderer {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// A step's language, plus the host it runs on if it's remote (`bash @ backup01`)
+pub(super) fn step_label(code: &CodeBlock) -> String {
+    match &code.remote_host {
+        Some(host) => format!("{} @ {}", code.language, host),
+        None => code.language.clone(),
+    }
+}
+
+/// A color change that writes nothing when color output is off
+pub(super) enum Paint {
+    Off,
+    Foreground(Color),
+    Reset,
+}
+
+impl Command for Paint {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match self {
+            Paint::Off => Ok(()),
+            Paint::Foreground(color) => SetForegroundColor((*color).into()).write_ansi(f),
+            Paint::Reset => ResetColor.write_ansi(f),
+        }
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        match self {
+            Paint::Off => Ok(()),
+            Paint::Foreground(color) => SetForegroundColor((*color).into()).execute_winapi(),
+            Paint::Reset => ResetColor.execute_winapi(),
+        }
+    }
+}
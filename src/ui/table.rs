@@ -0,0 +1,104 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Row, Table, Widget},
+};
+
+use super::theme::Theme;
+
+/// Columns between table cells
+pub(super) const COLUMN_SPACING: usize = 2;
+
+/// Display width of each column: its widest header or cell
+pub(super) fn column_widths(headers: &[String], rows: &[Vec<String>]) -> Vec<usize> {
+    let columns = rows
+        .iter()
+        .map(Vec::len)
+        .max()
+        .unwrap_or(0)
+        .max(headers.len());
+    (0..columns)
+        .map(|column| {
+            std::iter::once(headers)
+                .chain(rows.iter().map(Vec::as_slice))
+                .filter_map(|cells| cells.get(column))
+                .map(|cell| Span::raw(cell.as_str()).width())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+/// Pad `cell` with spaces to `width` display columns
+pub(super) fn pad_cell(cell: &str, width: usize) -> String {
+    let padding = width.saturating_sub(Span::raw(cell).width());
+    format!("{}{}", cell, " ".repeat(padding))
+}
+
+/// Draw a table with ratatui's `Table` widget and return it as styled lines
+///
+/// The runbook view is a single scrolling paragraph, so the widget is drawn
+/// into an off-screen buffer that is then read back row by row.
+pub(super) fn table_lines(
+    headers: &[String],
+    rows: &[Vec<String>],
+    theme: &Theme,
+) -> Vec<Line<'static>> {
+    let widths = column_widths(headers, rows);
+    let width = widths.iter().sum::<usize>() + COLUMN_SPACING * widths.len().saturating_sub(1) + 2;
+    // Borders plus the header row
+    let height = rows.len() + 3;
+    let area = Rect::new(
+        0,
+        0,
+        u16::try_from(width).unwrap_or(u16::MAX),
+        u16::try_from(height).unwrap_or(u16::MAX),
+    );
+
+    let table = Table::new(
+        rows.iter().map(|row| Row::new(row.clone())),
+        widths
+            .iter()
+            .map(|w| Constraint::Length(u16::try_from(*w).unwrap_or(u16::MAX))),
+    )
+    .header(
+        Row::new(headers.to_vec()).style(
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ),
+    )
+    .column_spacing(COLUMN_SPACING as u16)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.muted)),
+    );
+    let mut buffer = Buffer::empty(area);
+    table.render(area, &mut buffer);
+
+    (0..area.height)
+        .map(|y| {
+            let mut spans: Vec<Span<'static>> = Vec::new();
+            // Cells covered by the previous wide character
+            let mut hidden = 0;
+            for x in 0..area.width {
+                if hidden > 0 {
+                    hidden -= 1;
+                    continue;
+                }
+                let cell = &buffer[(x, y)];
+                hidden = Span::raw(cell.symbol()).width().saturating_sub(1);
+                match spans.last_mut() {
+                    Some(last) if last.style == cell.style() => {
+                        last.content.to_mut().push_str(cell.symbol());
+                    }
+                    _ => spans.push(Span::styled(cell.symbol().to_string(), cell.style())),
+                }
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
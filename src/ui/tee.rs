@@ -0,0 +1,120 @@
+use std::fs::File;
+use std::io::{self, Stdout, Write};
+
+/// Writes to stdout and, optionally, a transcript file
+///
+/// The file copy has ANSI escape sequences stripped so it reads as plain text.
+pub struct TeeWriter<'a> {
+    stdout: Stdout,
+    file: Option<&'a File>,
+    /// Escape sequences may be split across writes, so parsing state is kept
+    stripper: AnsiStripper,
+}
+
+impl<'a> TeeWriter<'a> {
+    pub fn new(file: Option<&'a File>) -> Self {
+        Self {
+            stdout: io::stdout(),
+            file,
+            stripper: AnsiStripper::default(),
+        }
+    }
+}
+
+impl Write for TeeWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdout.write_all(buf)?;
+        if let Some(mut file) = self.file {
+            file.write_all(&self.stripper.strip(buf))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()?;
+        if let Some(mut file) = self.file {
+            file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Remove ANSI escape sequences (colors, cursor movement) from output
+pub fn strip_ansi(bytes: &[u8]) -> Vec<u8> {
+    AnsiStripper::default().strip(bytes)
+}
+
+/// Where we are within an escape sequence
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum AnsiState {
+    #[default]
+    Text,
+    /// Just saw ESC
+    Escape,
+    /// Inside `ESC [`, waiting for the final byte
+    Csi,
+    /// Inside `ESC ]`, waiting for BEL or `ESC \`
+    Osc,
+    /// Saw ESC inside an OSC sequence
+    OscEscape,
+}
+
+/// Streaming ANSI escape remover
+#[derive(Debug, Default)]
+struct AnsiStripper {
+    state: AnsiState,
+}
+
+impl AnsiStripper {
+    fn strip(&mut self, bytes: &[u8]) -> Vec<u8> {
+        const ESC: u8 = 0x1b;
+        const BEL: u8 = 0x07;
+
+        let mut out = Vec::with_capacity(bytes.len());
+        for &byte in bytes {
+            self.state = match (self.state, byte) {
+                (AnsiState::Text, ESC) => AnsiState::Escape,
+                (AnsiState::Text, _) => {
+                    out.push(byte);
+                    AnsiState::Text
+                }
+                (AnsiState::Escape, b'[') => AnsiState::Csi,
+                (AnsiState::Escape, b']') => AnsiState::Osc,
+                // Any other two-byte escape
+                (AnsiState::Escape, _) => AnsiState::Text,
+                (AnsiState::Csi, 0x40..=0x7e) => AnsiState::Text,
+                (AnsiState::Csi, _) => AnsiState::Csi,
+                (AnsiState::Osc, BEL) => AnsiState::Text,
+                (AnsiState::Osc, ESC) => AnsiState::OscEscape,
+                (AnsiState::Osc, _) => AnsiState::Osc,
+                (AnsiState::OscEscape, b'\\') => AnsiState::Text,
+                (AnsiState::OscEscape, _) => AnsiState::Osc,
+            };
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi_colors() {
+        let colored = b"\x1b[38;5;11mStep 1/2 [bash]:\x1b[0m\n";
+        assert_eq!(strip_ansi(colored), b"Step 1/2 [bash]:\n");
+    }
+
+    #[test]
+    fn test_strip_ansi_across_writes() {
+        let mut stripper = AnsiStripper::default();
+        let mut out = stripper.strip(b"\x1b");
+        out.extend(stripper.strip(b"[38;5;14m# Hi"));
+        assert_eq!(out, b"# Hi");
+    }
+
+    #[test]
+    fn test_strip_ansi_keeps_plain_text() {
+        assert_eq!(strip_ansi("✓ done\n".as_bytes()), "✓ done\n".as_bytes());
+    }
+}
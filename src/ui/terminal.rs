@@ -0,0 +1,67 @@
+use anyhow::Result;
+use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::io;
+
+/// Holds the terminal in TUI mode (raw mode, alternate screen, mouse capture)
+///
+/// Dropping the guard restores the terminal, so it is left usable however
+/// the TUI exits, including by a panic unwinding through `run_loop`.
+pub(super) struct TerminalGuard {
+    /// Whether the terminal is currently in TUI mode
+    active: bool,
+}
+
+impl TerminalGuard {
+    /// Switch the terminal into TUI mode
+    pub(super) fn enter() -> Result<Self> {
+        let mut guard = Self { active: false };
+        guard.reenter()?;
+        Ok(guard)
+    }
+
+    /// Hand the terminal back, e.g. to a sub-shell; [`reenter`](Self::reenter) undoes it
+    pub(super) fn leave(&mut self) -> Result<()> {
+        if self.active {
+            self.active = false;
+            disable_raw_mode()?;
+            execute!(
+                io::stdout(),
+                LeaveAlternateScreen,
+                DisableMouseCapture,
+                Show
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Switch back into TUI mode after [`leave`](Self::leave)
+    pub(super) fn reenter(&mut self) -> Result<()> {
+        if !self.active {
+            enable_raw_mode()?;
+            // From here on there is something to undo
+            self.active = true;
+            execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // Errors can't be reported from here; restoring as much as possible is all we can do
+        if self.active {
+            let _ = disable_raw_mode();
+            let _ = execute!(
+                io::stdout(),
+                LeaveAlternateScreen,
+                DisableMouseCapture,
+                Show
+            );
+        }
+    }
+}
@@ -10,6 +10,35 @@ fn test_renderer_new() {
     assert_eq!(renderer.total_steps, 0);
 }
 
+#[test]
+fn test_renderer_new_plain_writes_no_escapes() {
+    use crossterm::Command;
+
+    let renderer = Renderer::new_plain();
+    assert!(!renderer.use_color);
+
+    let mut written = String::new();
+    renderer
+        .fg(ratatui::style::Color::Red)
+        .write_ansi(&mut written)
+        .unwrap();
+    assert!(written.is_empty());
+}
+
+#[test]
+fn test_renderer_shell_prompt_message_fills_in_step() {
+    assert_eq!(Renderer::new().shell_prompt_message(), None);
+
+    let mut renderer =
+        Renderer::new().with_shell_prompt_message("Step {step}/{total}: run it, then exit");
+    renderer.set_total_steps(4);
+    renderer.skip_step();
+    assert_eq!(
+        renderer.shell_prompt_message().as_deref(),
+        Some("Step 1/4: run it, then exit")
+    );
+}
+
 #[test]
 fn test_renderer_set_total_steps() {
     let mut renderer = Renderer::new();
@@ -29,7 +58,7 @@ fn test_tui_app_new() {
 #[test]
 fn test_tui_app_render_runbook_content_empty() {
     let doc = Document::new();
-    let mut app = TuiApp::new(doc);
+    let app = TuiApp::new(doc);
     
     // This should not panic and return an empty content
     let content = app.render_runbook_content();
@@ -42,7 +71,7 @@ fn test_tui_app_render_runbook_content_with_simple_document() {
     let section = Section::with_header("Test Section".to_string(), 1);
     doc.sections.push(section);
     
-    let mut app = TuiApp::new(doc);
+    let app = TuiApp::new(doc);
     
     // This should not panic and return content
     let content = app.render_runbook_content();
@@ -55,16 +84,12 @@ fn test_tui_app_render_runbook_content_with_code_blocks() {
     let mut section = Section::with_header("Test Section".to_string(), 1);
     
     // Add a code block to the section
-    let code_block = CodeBlock {
-        language: "bash".to_string(),
-        content: "echo \"hello\"".to_string(),
-        line_number: 1,
-    };
+    let code_block = CodeBlock::new("bash", "echo \"hello\"", 1);
     
     section.blocks.push(Block::Code(code_block));
     doc.sections.push(section);
     
-    let mut app = TuiApp::new(doc);
+    let app = TuiApp::new(doc);
     
     // This should not panic and return content with code blocks
     let content = app.render_runbook_content();
@@ -77,16 +102,12 @@ fn test_tui_app_highlight_code_line() {
     let mut section = Section::with_header("Test Section".to_string(), 1);
     
     // Add a code block to the section
-    let code_block = CodeBlock {
-        language: "bash".to_string(),
-        content: "echo \"hello\"".to_string(),
-        line_number: 1,
-    };
+    let code_block = CodeBlock::new("bash", "echo \"hello\"", 1);
     
     section.blocks.push(Block::Code(code_block));
     doc.sections.push(section);
     
-    let mut app = TuiApp::new(doc);
+    let app = TuiApp::new(doc);
     
     // Test the highlighting function
     let base_style = ratatui::style::Style::default();
@@ -102,11 +123,7 @@ fn test_tui_app_auto_scroll_to_current_step() {
     let mut section = Section::with_header("Test Section".to_string(), 1);
     
     // Add a code block to the section
-    let code_block = CodeBlock {
-        language: "bash".to_string(),
-        content: "echo \"hello\"".to_string(),
-        line_number: 1,
-    };
+    let code_block = CodeBlock::new("bash", "echo \"hello\"", 1);
     
     section.blocks.push(Block::Code(code_block));
     doc.sections.push(section);
@@ -124,11 +141,7 @@ fn test_tui_app_next_step() {
     let mut section = Section::with_header("Test Section".to_string(), 1);
     
     // Add a code block to the section
-    let code_block = CodeBlock {
-        language: "bash".to_string(),
-        content: "echo \"hello\"".to_string(),
-        line_number: 1,
-    };
+    let code_block = CodeBlock::new("bash", "echo \"hello\"", 1);
     
     section.blocks.push(Block::Code(code_block));
     doc.sections.push(section);
@@ -146,11 +159,7 @@ fn test_tui_app_previous_step() {
     let mut section = Section::with_header("Test Section".to_string(), 1);
     
     // Add a code block to the section
-    let code_block = CodeBlock {
-        language: "bash".to_string(),
-        content: "echo \"hello\"".to_string(),
-        line_number: 1,
-    };
+    let code_block = CodeBlock::new("bash", "echo \"hello\"", 1);
     
     section.blocks.push(Block::Code(code_block));
     doc.sections.push(section);
@@ -160,4 +169,618 @@ fn test_tui_app_previous_step() {
     // Test that previous_step function doesn't panic
     app.previous_step();
     assert_eq!(app.current_step, 0);
-}
\ No newline at end of file
+}
+#[test]
+fn test_tui_app_highlight_python_line() {
+    let app = TuiApp::new(Document::new());
+
+    let base_style = ratatui::style::Style::default();
+    let highlighted = app.highlight_code_line("def greet(name):  # say hi", "python", &base_style);
+
+    // Keyword, plain text, and comment should be split into separate spans
+    assert!(highlighted.len() > 1);
+    assert_eq!(highlighted[0].content, "def");
+    assert_eq!(highlighted.last().unwrap().content, "# say hi");
+}
+
+#[test]
+fn test_tui_app_highlights_custom_danger_patterns() {
+    let app = TuiApp::new(Document::new())
+        .with_theme(crate::ui::Theme::light())
+        .with_danger_patterns(&["mkfs".to_string()]);
+
+    let base_style = ratatui::style::Style::default();
+    let highlighted = app.highlight_code_line("mkfs.ext4 /dev/sdb1", "bash", &base_style);
+
+    assert_eq!(highlighted[0].content, "mkfs");
+    assert_eq!(highlighted[0].style.fg, Some(crate::ui::Theme::light().danger));
+}
+
+#[test]
+fn test_tui_app_highlight_python_strings() {
+    let app = TuiApp::new(Document::new());
+
+    let base_style = ratatui::style::Style::default();
+    let highlighted = app.highlight_code_line("print('it\\'s # not a comment')", "python", &base_style);
+
+    let contents: Vec<&str> = highlighted.iter().map(|s| s.content.as_ref()).collect();
+    assert_eq!(contents, vec!["print(", "'it\\'s # not a comment'", ")"]);
+}
+
+#[test]
+fn test_tui_app_display_only_blocks_are_not_numbered() {
+    let mut doc = Document::new();
+    let mut section = Section::with_header("Test Section".to_string(), 1);
+    section.blocks.push(Block::Code(CodeBlock::new("yaml", "replicas: 3", 1)));
+    section.blocks.push(Block::Code(CodeBlock::new("bash", "kubectl apply -f app.yaml", 5)));
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    let text: Vec<String> = app.render_runbook_content().iter().map(|l| l.to_string()).collect();
+    assert!(text.iter().any(|l| l.contains("Step 1 [bash]")));
+    assert!(!text.iter().any(|l| l.contains("Step 2")));
+
+    // The only step is the bash block, so there's nowhere further to go
+    app.next_step();
+    app.next_step();
+    assert_eq!(app.current_step, 1);
+}
+
+#[test]
+fn test_tui_app_numbers_identical_steps_separately() {
+    let mut doc = Document::new();
+    let mut section = Section::with_header("Test Section".to_string(), 1);
+    section.blocks.push(Block::Code(CodeBlock::new("bash", "systemctl restart app", 1)));
+    section.blocks.push(Block::Code(CodeBlock::new("bash", "systemctl restart app", 1)));
+    doc.sections.push(section);
+
+    let app = TuiApp::new(doc);
+    let text: Vec<String> = app.render_runbook_content().iter().map(|l| l.to_string()).collect();
+    assert!(text.iter().any(|l| l.contains("Step 1 [bash]")));
+    assert!(text.iter().any(|l| l.contains("Step 2 [bash]")));
+}
+
+#[test]
+fn test_tui_app_collapsed_section_hides_steps() {
+    let mut doc = Document::new();
+    let mut first = Section::with_header("First".to_string(), 1);
+    first.blocks.push(Block::Code(CodeBlock::new("bash", "echo one", 3)));
+    first.blocks.push(Block::Code(CodeBlock::new("bash", "echo two", 7)));
+    doc.sections.push(first);
+    let mut second = Section::with_header("Second".to_string(), 1);
+    second.blocks.push(Block::Code(CodeBlock::new("bash", "echo three", 11)));
+    doc.sections.push(second);
+
+    let mut app = TuiApp::new(doc);
+    app.toggle_current_section();
+    assert!(app.collapsed_sections.contains(&0));
+
+    let text: Vec<String> = app
+        .render_runbook_content()
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+    assert!(text.iter().any(|l| l.contains("▸") && l.contains("(2 steps hidden)")));
+    assert!(!text.iter().any(|l| l.contains("echo one")));
+    // Hidden steps still count, so the next section starts at step 3
+    assert!(text.iter().any(|l| l.contains("Step 3 [bash]")));
+
+    app.toggle_current_section();
+    assert!(app.collapsed_sections.is_empty());
+}
+
+#[test]
+fn test_tui_app_collapse_hides_sub_sections() {
+    let mut doc = Document::new();
+    let mut deploy = Section::with_header("Deploy".to_string(), 1);
+    deploy.blocks.push(Block::Code(CodeBlock::new("bash", "echo one", 3)));
+    doc.sections.push(deploy);
+    let mut build = Section::with_header("Build".to_string(), 2);
+    build.blocks.push(Block::Code(CodeBlock::new("bash", "echo two", 7)));
+    build.blocks.push(Block::Code(CodeBlock::new("bash", "echo three", 11)));
+    doc.sections.push(build);
+    let mut verify = Section::with_header("Verify".to_string(), 1);
+    verify.blocks.push(Block::Code(CodeBlock::new("bash", "echo four", 15)));
+    doc.sections.push(verify);
+
+    // From a step in the `##` sub-section, the `#` section collapses
+    let mut app = TuiApp::new(doc);
+    app.current_step = 2;
+    app.toggle_current_section();
+    assert!(app.collapsed_sections.contains(&0));
+    assert_eq!(app.collapsed_sections.len(), 1);
+
+    let text: Vec<String> = app
+        .render_runbook_content()
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+    assert!(text.iter().any(|l| l.contains("Deploy") && l.contains("(3 steps hidden)")));
+    assert!(!text.iter().any(|l| l.contains("Build") || l.contains("echo two")));
+    assert!(text.iter().any(|l| l.contains("Step 4 [bash]")));
+}
+
+#[test]
+fn test_tui_app_mouse_wheel_scrolls() {
+    use crossterm::event::MouseEventKind;
+
+    let mut section = Section::with_header("Long".to_string(), 1);
+    let content = vec!["echo line"; 20].join("\n");
+    section.blocks.push(Block::Code(CodeBlock::new("bash", content, 3)));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    app.viewport_height = 5;
+    app.handle_mouse(MouseEventKind::ScrollDown);
+    app.handle_mouse(MouseEventKind::ScrollDown);
+    assert_eq!(app.scroll_offset, 6);
+
+    app.handle_mouse(MouseEventKind::ScrollUp);
+    assert_eq!(app.scroll_offset, 3);
+    app.handle_mouse(MouseEventKind::Moved);
+    assert_eq!(app.scroll_offset, 3);
+}
+
+#[test]
+fn test_tui_app_scroll_stops_at_end_of_content() {
+    use crossterm::event::MouseEventKind;
+
+    let mut section = Section::with_header("Short".to_string(), 1);
+    section.blocks.push(Block::Code(CodeBlock::new("bash", "ls", 3)));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    app.viewport_height = 4;
+    let total_lines = app.render_runbook_content().len();
+    for _ in 0..10 {
+        app.handle_mouse(MouseEventKind::ScrollDown);
+    }
+    assert_eq!(app.scroll_offset, total_lines - 4);
+
+    // Content that fits the viewport doesn't scroll at all
+    app.viewport_height = 100;
+    app.scroll_offset = 0;
+    app.handle_mouse(MouseEventKind::ScrollDown);
+    assert_eq!(app.scroll_offset, 0);
+}
+
+#[test]
+fn test_tui_app_horizontal_scroll_needs_wrap_off() {
+    let mut section = Section::with_header("Wide".to_string(), 1);
+    let command = format!("kubectl get pods {}", "-l app=web ".repeat(10));
+    section.blocks.push(Block::Code(CodeBlock::new("bash", command, 3)));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    app.viewport_width = 40;
+    app.scroll_right(8);
+    assert_eq!(app.horizontal_offset, 0);
+
+    app.toggle_wrap();
+    assert!(!app.wrap_enabled);
+    app.scroll_right(8);
+    assert_eq!(app.horizontal_offset, 8);
+
+    // Never past the end of the widest line
+    app.scroll_right(1000);
+    let widest = app
+        .render_runbook_content()
+        .iter()
+        .map(|line| line.width())
+        .max()
+        .unwrap();
+    assert_eq!(app.horizontal_offset, widest - 40);
+
+    app.toggle_wrap();
+    assert_eq!(app.horizontal_offset, 0);
+}
+
+#[test]
+fn test_tui_app_mono_theme_uses_no_colors() {
+    use crate::ui::Theme;
+    use ratatui::style::Color;
+
+    let mut section = Section::with_header("Deploy".to_string(), 1);
+    section.blocks.push(Block::text("WARNING: production"));
+    section.blocks.push(Block::Code(CodeBlock::new("bash", "rm -rf /tmp/build # $HOME", 3)));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let app = TuiApp::new(doc).with_theme(Theme::mono());
+    for line in app.render_runbook_content() {
+        for span in &line.spans {
+            assert!(matches!(span.style.fg, None | Some(Color::Reset)), "{:?}", span);
+        }
+    }
+}
+
+#[test]
+fn test_tui_app_renders_callout_box() {
+    use crate::model::CalloutKind;
+
+    let mut section = Section::with_header("Deploy".to_string(), 1);
+    section.blocks.push(Block::Callout {
+        kind: CalloutKind::Warning,
+        text: "Drain the node first".to_string(),
+    });
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let app = TuiApp::new(doc);
+    let text: Vec<String> = app.render_runbook_content().iter().map(|l| l.to_string()).collect();
+    let top = text.iter().position(|l| l.starts_with("┌─") && l.ends_with("Warning")).unwrap();
+    assert_eq!(text[top + 1], "│ Drain the node first");
+    assert_eq!(text[top + 2], "└─");
+}
+
+#[test]
+fn test_tui_app_renders_table_with_borders() {
+    let mut section = Section::with_header("Hosts".to_string(), 2);
+    section.blocks.push(Block::Table {
+        headers: vec!["Host".to_string(), "Role".to_string()],
+        rows: vec![vec!["db01".to_string(), "primary".to_string()]],
+    });
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let app = TuiApp::new(doc);
+    let text: Vec<String> = app.render_runbook_content().iter().map(|l| l.to_string()).collect();
+    let top = text.iter().position(|l| l.starts_with('┌')).unwrap();
+    assert_eq!(text[top + 1], "│Host  Role   │");
+    assert_eq!(text[top + 2], "│db01  primary│");
+    assert!(text[top + 3].starts_with('└'));
+}
+
+#[test]
+fn test_tui_app_shows_step_note_after_header() {
+    let mut code = CodeBlock::new("bash", "pg_ctl promote", 3);
+    code.attributes.insert("note".to_string(), "only on primary".to_string());
+    let mut section = Section::with_header("Failover".to_string(), 1);
+    section.blocks.push(Block::Code(code));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let app = TuiApp::new(doc);
+    let text: Vec<String> = app.render_runbook_content().iter().map(|l| l.to_string()).collect();
+    assert!(text.iter().any(|l| l.ends_with("Step 1 [bash]: only on primary")));
+    assert!(!text.iter().any(|l| l.trim() == "only on primary"));
+}
+
+#[test]
+fn test_renderer_render_document_numbers_every_step() {
+    let mut section = Section::with_header("Deploy".to_string(), 1);
+    section.blocks.push(Block::text("Intro"));
+    section.blocks.push(Block::Code(CodeBlock::new("bash", "echo one", 3)));
+    section.blocks.push(Block::Code(CodeBlock::new("yaml", "key: value", 7)));
+    section.blocks.push(Block::Code(CodeBlock::new("bash", "echo two", 11)));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut renderer = Renderer::new_plain();
+    renderer.render_document(&doc).unwrap();
+    assert_eq!(renderer.total_steps, 2);
+    assert_eq!(renderer.current_step, 2);
+}
+
+#[test]
+fn test_step_count_suffix() {
+    use crate::ui::renderer::step_count_suffix;
+
+    assert_eq!(step_count_suffix(0), "");
+    assert_eq!(step_count_suffix(1), " (1 step)");
+    assert_eq!(step_count_suffix(3), " (3 steps)");
+}
+
+#[test]
+fn test_run_table_rows() {
+    use crate::executor::StepResult;
+    use crate::ui::renderer::run_table_row;
+    use std::time::Duration;
+
+    let ok = StepResult {
+        index: 1,
+        language: "bash".to_string(),
+        exit_code: Some(0),
+        duration: Duration::from_millis(350),
+        ..StepResult::default()
+    };
+    assert_eq!(run_table_row(&ok), vec!["1", "bash", "✓ ok", "350ms"]);
+
+    let failed = StepResult {
+        index: 2,
+        language: "python".to_string(),
+        exit_code: Some(2),
+        duration: Duration::from_millis(4_250),
+        ..StepResult::default()
+    };
+    assert_eq!(run_table_row(&failed), vec!["2", "python", "✗ exit 2", "4.2s"]);
+
+    let timed_out = StepResult {
+        timed_out: true,
+        duration: Duration::from_secs(187),
+        ..failed
+    };
+    assert_eq!(run_table_row(&timed_out)[2..], ["✗ timed out", "3m07s"]);
+}
+
+#[test]
+fn test_progress_summary() {
+    use crate::ui::renderer::progress_summary;
+
+    assert_eq!(
+        progress_summary(5, 2, 11),
+        "Completed 5, skipped 2, 4 remaining (of 11 steps)"
+    );
+    assert_eq!(
+        progress_summary(3, 1, 4),
+        "Completed 3, skipped 1, 0 remaining (of 4 steps)"
+    );
+}
+
+#[test]
+fn test_format_elapsed_as_minutes_and_seconds() {
+    use crate::ui::tui::format_elapsed;
+    use std::time::Duration;
+
+    assert_eq!(format_elapsed(Duration::from_secs(42)), "00:42");
+    assert_eq!(format_elapsed(Duration::from_millis(723_900)), "12:03");
+    assert_eq!(format_elapsed(Duration::from_secs(3_725)), "62:05");
+}
+
+#[test]
+fn test_tui_app_moving_steps_restarts_step_timer() {
+    let mut section = Section::new();
+    section.blocks.push(Block::Code(CodeBlock::new("bash", "echo one", 1)));
+    section.blocks.push(Block::Code(CodeBlock::new("bash", "echo two", 5)));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    let opened = app.run_started;
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    app.next_step();
+    assert!(app.step_started > opened);
+    assert_eq!(app.run_started, opened);
+
+    let moved = app.step_started;
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    app.previous_step();
+    assert!(app.step_started > moved);
+}
+
+#[test]
+fn test_link_urls_are_underlined() {
+    use crate::ui::tui::link_spans;
+    use crate::ui::Theme;
+    use ratatui::style::Modifier;
+
+    let spans = link_spans("See the wiki (https://wiki.example.com) first", &Theme::dark());
+    let text: Vec<&str> = spans.iter().map(|s| s.content.as_ref()).collect();
+    assert_eq!(text, vec!["See the wiki (", "https://wiki.example.com", ")", " first"]);
+    assert!(spans[1].style.add_modifier.contains(Modifier::UNDERLINED));
+    assert!(!spans[0].style.add_modifier.contains(Modifier::UNDERLINED));
+
+    let plain = link_spans("No links (here)", &Theme::dark());
+    assert_eq!(plain.len(), 1);
+}
+
+#[test]
+fn test_view_only_runbook_has_banner() {
+    let mut section = Section::with_header("About".to_string(), 1);
+    section.blocks.push(Block::text("Just docs"));
+    section.blocks.push(Block::Code(CodeBlock::new("yaml", "key: value", 5)));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    let text: Vec<String> = app.render_runbook_content().iter().map(|l| l.to_string()).collect();
+    assert!(text[0].ends_with("This runbook has no executable steps (view-only)"));
+
+    app.next_step();
+    assert_eq!(app.current_step, 0);
+    let (message, _) = app.transient_message.as_ref().unwrap();
+    assert!(message.contains("view-only"));
+}
+
+#[test]
+fn test_rerun_keeps_current_step() {
+    let mut section = Section::with_header("Rollout".to_string(), 1);
+    section.blocks.push(Block::Code(CodeBlock::new("bash", "kubectl get pods", 3)));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    assert!(!app.start_rerun());
+
+    app.next_step();
+    assert!(app.start_rerun());
+    assert_eq!(app.current_step, 1);
+    let (message, _) = app.transient_message.as_ref().unwrap();
+    assert!(message.ends_with("Re-running step 1"));
+}
+
+#[test]
+fn test_execute_shows_combined_output_in_pane() {
+    use std::time::{Duration, Instant};
+
+    let mut section = Section::with_header("Check".to_string(), 1);
+    let script = "echo hello\necho oops >&2\nexit 3";
+    section.blocks.push(Block::Code(CodeBlock::new("bash", script, 3)));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    assert!(!app.start_execute());
+
+    app.next_step();
+    assert!(app.start_execute());
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while app.output_pane.is_none() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+        app.poll_execution();
+    }
+
+    let pane = app.output_pane.as_ref().unwrap();
+    assert_eq!(pane.step, 1);
+    assert!(!pane.success);
+    assert_eq!(pane.summary, "exited with 3");
+    assert_eq!(pane.text, "hello\noops\n");
+    assert!(app.handle_output_key(crossterm::event::KeyCode::Esc));
+    assert!(app.output_pane.is_none());
+}
+
+#[test]
+fn test_execute_refuses_remote_steps() {
+    let mut section = Section::with_header("Deploy".to_string(), 1);
+    let mut code = CodeBlock::new("bash", "systemctl restart api", 3);
+    code.remote_host = Some("web01".to_string());
+    section.blocks.push(Block::Code(code));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    app.next_step();
+    assert!(!app.start_execute());
+    let (message, _) = app.transient_message.as_ref().unwrap();
+    assert_eq!(message, "Step 1 runs on web01; press s to run it in a shell");
+}
+
+#[test]
+fn test_quitting_kills_a_running_step() {
+    use std::time::{Duration, Instant};
+
+    let marker = std::env::temp_dir().join(format!("sysadmin-tui-stop-{}", std::process::id()));
+    let _ = std::fs::remove_file(&marker);
+    let script = format!("sleep 1\ntouch '{}'", marker.display());
+    let mut section = Section::with_header("Wait".to_string(), 1);
+    section.blocks.push(Block::Code(CodeBlock::new("bash", &script, 3)));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    app.next_step();
+    assert!(app.start_execute());
+    let started = Instant::now();
+    app.stop_running();
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    std::thread::sleep(Duration::from_millis(1500));
+    assert!(!marker.exists());
+}
+
+#[test]
+fn test_execute_confirms_dangerous_steps_and_substitutes_vars() {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    let mut section = Section::with_header("Cleanup".to_string(), 1);
+    let mut code = CodeBlock::new("bash", "echo ${TARGET}", 3);
+    code.flags.push("danger".to_string());
+    section.blocks.push(Block::Code(code));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let vars = HashMap::from([("TARGET".to_string(), "staging".to_string())]);
+    let mut app = TuiApp::new(doc).with_vars(vars);
+    app.next_step();
+    assert!(!app.start_execute());
+    let (message, _) = app.transient_message.as_ref().unwrap();
+    assert_eq!(message, "⚠ Step 1 looks dangerous; press x again to run it");
+
+    assert!(app.start_execute());
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while app.output_pane.is_none() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+        app.poll_execution();
+    }
+    assert_eq!(app.output_pane.as_ref().unwrap().text, "staging\n");
+
+    // A stale first press has to be repeated
+    app.execute_armed = Some((1, Instant::now() - Duration::from_secs(5)));
+    assert!(!app.start_execute());
+}
+
+#[test]
+fn test_quit_needs_confirming_mid_run() {
+    use std::time::{Duration, Instant};
+
+    let mut section = Section::with_header("Migrate".to_string(), 1);
+    section.blocks.push(Block::Code(CodeBlock::new("bash", "pg_dump app", 3)));
+    section.blocks.push(Block::Code(CodeBlock::new("bash", "./migrate", 7)));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    assert!(app.confirm_quit());
+
+    app.next_step();
+    assert!(!app.confirm_quit());
+    let (message, _) = app.transient_message.as_ref().unwrap();
+    assert_eq!(message, "Press q again to quit (run in progress)");
+    assert!(app.confirm_quit());
+
+    // A stale first press has to be repeated
+    app.quit_armed = Some(Instant::now() - Duration::from_secs(5));
+    assert!(!app.confirm_quit());
+
+    app.next_step();
+    assert!(app.confirm_quit());
+}
+
+#[test]
+fn test_resize_clamps_scroll_offset() {
+    let mut section = Section::with_header("Deploy".to_string(), 1);
+    for n in 0..10 {
+        let code = CodeBlock::new("bash", format!("echo {}", n), n);
+        section.blocks.push(Block::Code(code));
+    }
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    let content_height = app.render_runbook_content().len();
+    app.handle_resize(80, 10);
+    app.scroll_offset = app.max_scroll();
+    assert!(app.scroll_offset > 0);
+
+    // Taller than the content: nothing left to scroll
+    app.handle_resize(80, content_height as u16 + 20);
+    assert_eq!(app.viewport_height, content_height + 15);
+    assert_eq!(app.scroll_offset, 0);
+}
+
+#[test]
+fn test_max_scroll_counts_wrapped_lines() {
+    let mut section = Section::with_header("Wide".to_string(), 1);
+    let command = format!("kubectl get pods {}", "-l app=web ".repeat(20));
+    section.blocks.push(Block::Code(CodeBlock::new("bash", command, 3)));
+    let mut doc = Document::new();
+    doc.sections.push(section);
+
+    let mut app = TuiApp::new(doc);
+    let logical_lines = app.render_runbook_content().len();
+    app.viewport_width = 20;
+    app.viewport_height = logical_lines;
+    // The long command wraps onto several rows, which all need to be reachable
+    assert!(app.max_scroll() > 0);
+
+    app.toggle_wrap();
+    assert_eq!(app.max_scroll(), 0);
+}
+
+#[test]
+fn test_output_pane_height_survives_huge_output() {
+    use crate::ui::tui::{output_pane_height, StepOutput};
+
+    let pane = StepOutput {
+        step: 1,
+        success: true,
+        summary: "exited with 0".to_string(),
+        text: "line\n".repeat(70_000),
+        scroll: 0,
+    };
+    assert_eq!(output_pane_height(&pane, 50), 20);
+}
@@ -0,0 +1,182 @@
+use anyhow::{bail, Result};
+use ratatui::style::Color;
+
+use crate::model::CalloutKind;
+
+/// Names accepted by [`Theme::from_name`]
+pub const THEME_NAMES: &[&str] = &["dark", "light", "mono"];
+
+/// Colors used by the [`Renderer`](super::Renderer) and the TUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Level 1, level 2, and deeper section headers
+    pub headers: [Color; 3],
+    /// Completed steps
+    pub done: Color,
+    /// The step being worked on
+    pub current: Color,
+    /// Steps not reached yet
+    pub pending: Color,
+    /// Code of the current step
+    pub code: Color,
+    pub danger: Color,
+    pub warning: Color,
+    pub info: Color,
+    /// URLs in text
+    pub link: Color,
+    /// De-emphasized content: display-only blocks, separators, hints
+    pub muted: Color,
+    /// Borders and prompts
+    pub accent: Color,
+    pub status_fg: Color,
+    pub status_bg: Color,
+    pub keyword: Color,
+    pub string: Color,
+    pub comment: Color,
+    /// `$VARIABLE` references in shell code
+    pub variable: Color,
+}
+
+impl Theme {
+    /// Bright colors for dark terminal backgrounds (the default)
+    pub fn dark() -> Self {
+        Self {
+            headers: [Color::Cyan, Color::Magenta, Color::Gray],
+            done: Color::Green,
+            current: Color::Yellow,
+            pending: Color::DarkGray,
+            code: Color::LightGreen,
+            danger: Color::Red,
+            warning: Color::Yellow,
+            info: Color::Blue,
+            link: Color::Blue,
+            muted: Color::DarkGray,
+            accent: Color::Cyan,
+            status_fg: Color::White,
+            status_bg: Color::Blue,
+            keyword: Color::Magenta,
+            string: Color::Yellow,
+            comment: Color::Gray,
+            variable: Color::Cyan,
+        }
+    }
+
+    /// Darker colors that stay readable on light backgrounds
+    pub fn light() -> Self {
+        Self {
+            headers: [Color::Blue, Color::Magenta, Color::Black],
+            done: Color::Green,
+            current: Color::Indexed(130),
+            pending: Color::Gray,
+            code: Color::Black,
+            danger: Color::Red,
+            warning: Color::Indexed(130),
+            info: Color::Blue,
+            link: Color::Blue,
+            muted: Color::Gray,
+            accent: Color::Blue,
+            status_fg: Color::White,
+            status_bg: Color::Blue,
+            keyword: Color::Magenta,
+            string: Color::Indexed(130),
+            comment: Color::DarkGray,
+            variable: Color::Blue,
+        }
+    }
+
+    /// No colors at all; emphasis comes only from bold and underline
+    pub fn mono() -> Self {
+        Self {
+            headers: [Color::Reset; 3],
+            done: Color::Reset,
+            current: Color::Reset,
+            pending: Color::Reset,
+            code: Color::Reset,
+            danger: Color::Reset,
+            warning: Color::Reset,
+            info: Color::Reset,
+            link: Color::Reset,
+            muted: Color::Reset,
+            accent: Color::Reset,
+            status_fg: Color::Reset,
+            status_bg: Color::Reset,
+            keyword: Color::Reset,
+            string: Color::Reset,
+            comment: Color::Reset,
+            variable: Color::Reset,
+        }
+    }
+
+    /// Look up a theme by name, one of [`THEME_NAMES`]
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "dark" => Ok(Self::dark()),
+            "light" => Ok(Self::light()),
+            "mono" => Ok(Self::mono()),
+            _ => bail!(
+                "Unknown theme '{}' (expected one of: {})",
+                name,
+                THEME_NAMES.join(", ")
+            ),
+        }
+    }
+
+    /// `mono` if the `NO_COLOR` environment variable is set, otherwise `dark`
+    pub fn from_env() -> Self {
+        if no_color() {
+            Self::mono()
+        } else {
+            Self::dark()
+        }
+    }
+
+    /// Color for a callout's border and title
+    pub fn callout(&self, kind: CalloutKind) -> Color {
+        match kind {
+            CalloutKind::Note => self.info,
+            CalloutKind::Tip => self.done,
+            CalloutKind::Warning => self.warning,
+            CalloutKind::Danger => self.danger,
+            CalloutKind::Quote => self.muted,
+        }
+    }
+
+    /// Color for a header of the given level
+    pub fn header(&self, level: u32) -> Color {
+        match level {
+            1 => self.headers[0],
+            2 => self.headers[1],
+            _ => self.headers[2],
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+/// Whether the user asked for no color via a non-empty `NO_COLOR`
+pub fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_from_name() {
+        assert_eq!(Theme::from_name("light").unwrap(), Theme::light());
+        assert_eq!(Theme::from_name("mono").unwrap().danger, Color::Reset);
+        assert!(Theme::from_name("solarized").is_err());
+    }
+
+    #[test]
+    fn test_theme_header_levels() {
+        let theme = Theme::dark();
+        assert_eq!(theme.header(1), Color::Cyan);
+        assert_eq!(theme.header(4), theme.header(3));
+    }
+}
@@ -1,49 +1,138 @@
-use anyhow::Result;
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
-use emojis;
+use anyhow::{anyhow, Result};
+use arboard::Clipboard;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers, MouseEventKind};
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Terminal,
 };
+use std::collections::{HashMap, HashSet};
 use std::io;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
 use std::time::{Duration, Instant};
 
-use crate::model::{Block as DocBlock, Document};
+use super::highlight::{Highlighter, HighlighterRegistry};
+use super::icons::icons;
+use super::renderer::{step_count_suffix, step_label};
+use super::table::table_lines;
+use super::terminal::TerminalGuard;
+use super::theme::Theme;
+use crate::executor::{run_code_combined, DEFAULT_SUDO_CMD};
+use crate::exporter;
+use crate::model::{danger_patterns, Block as DocBlock, CodeBlock, Document};
 
-/// Centralized emoji icon manager
-struct Icons {
-    done: &'static str,
-    current: &'static str,
-    pending: &'static str,
-    warning: &'static str,
-    danger: &'static str,
-    info: &'static str,
-}
+/// Lines scrolled per mouse-wheel notch
+const MOUSE_SCROLL_LINES: usize = 3;
 
-fn icons() -> Icons {
-    Icons {
-        done: emojis::get("check_mark_button").map(|e| e.as_str()).unwrap_or("✔"),
-        current: emojis::get("arrow_right").map(|e| e.as_str()).unwrap_or("➡"),
-        pending: emojis::get("radio_button").map(|e| e.as_str()).unwrap_or("○"),
-        warning: emojis::get("warning").map(|e| e.as_str()).unwrap_or("⚠️"),
-        danger: emojis::get("fire").map(|e| e.as_str()).unwrap_or("🔥"),
-        info: emojis::get("information").map(|e| e.as_str()).unwrap_or("ℹ️"),
-    }
-}
+/// Columns scrolled per Left/Right press when wrapping is off
+const HORIZONTAL_SCROLL_COLS: usize = 4;
+
+/// Rows taken by the bordered status bar under the runbook
+const STATUS_BAR_HEIGHT: u16 = 3;
+
+/// How long a first `q` mid-run, or `x` on a dangerous step, waits for the second one
+const CONFIRM_WINDOW: Duration = Duration::from_secs(2);
+
+/// How long quitting waits for a step started with `x` to be killed
+const STOP_GRACE: Duration = Duration::from_secs(2);
+
+/// Keybindings shown in the help overlay
+const HELP_KEYS: &[(&str, &str)] = &[
+    ("n", "Next step"),
+    ("p", "Previous step"),
+    ("g <N> Enter", "Go to step N"),
+    ("↑ / ↓ / wheel", "Scroll"),
+    ("w", "Toggle line wrapping"),
+    ("← / →", "Scroll sideways while wrapping is off"),
+    ("Tab / z", "Collapse or expand the top-level section"),
+    ("s", "Drop to shell for the current step"),
+    ("r", "Re-run the current step without advancing"),
+    ("x", "Run the current step here and show its output"),
+    ("Esc", "Close the output pane"),
+    ("c", "Copy current step to clipboard"),
+    ("C", "Copy the whole runbook as a shell script"),
+    ("?", "Toggle this help"),
+    ("q", "Quit (press twice mid-run)"),
+    ("Ctrl-C", "Quit immediately"),
+];
 
 pub struct TuiApp {
     document: Document,
-    current_step: usize,
-    scroll_offset: usize,
-    transient_message: Option<(String, Instant)>,
+    pub(super) current_step: usize,
+    pub(super) scroll_offset: usize,
+    /// Rows available for runbook content, as of the last draw
+    pub(super) viewport_height: usize,
+    /// Columns available for runbook content, as of the last draw
+    pub(super) viewport_width: usize,
+    /// Whether long lines wrap; when off they can be scrolled sideways
+    pub(super) wrap_enabled: bool,
+    /// Columns scrolled to the right, used only while wrapping is off
+    pub(super) horizontal_offset: usize,
+    pub(super) transient_message: Option<(String, Instant)>,
+    /// When `q` was first pressed mid-run, awaiting a second press to quit
+    pub(super) quit_armed: Option<Instant>,
+    /// The dangerous step `x` was first pressed on, awaiting a second press to run it
+    pub(super) execute_armed: Option<(usize, Instant)>,
+    /// System clipboard, opened on first use and kept alive so copies persist
+    clipboard: Option<Clipboard>,
+    /// Digits typed after `g`, while a jump-to-step is being entered
+    jump_input: Option<String>,
+    /// Whether the keybinding help overlay is open
+    show_help: bool,
+    /// Lowercased patterns that mark a step as dangerous
+    danger_patterns: Vec<String>,
+    /// Shell to drop into, instead of `$SHELL`
+    shell: Option<String>,
+    /// Environment variables exported into the shell
+    env: HashMap<String, String>,
+    /// Values for `${VAR}` references in steps run with `x`
+    vars: HashMap<String, String>,
+    /// Command that `{sudo}` steps are run under
+    sudo_cmd: String,
+    /// Quit when a dropped-to shell exits with 130 (Ctrl-C), instead of returning
+    exit_on_interrupt: bool,
+    /// Colors for everything drawn
+    theme: Theme,
+    /// Syntax highlighting for code, by language
+    highlighters: HighlighterRegistry,
+    /// Indices into `document.sections` of top-level sections shown as just
+    /// their header, hiding their sub-sections too
+    pub(super) collapsed_sections: HashSet<usize>,
+    /// When the current step was moved to
+    pub(super) step_started: Instant,
+    /// When the TUI was opened
+    pub(super) run_started: Instant,
+    /// Kill steps run with `x` after this long
+    timeout: Option<Duration>,
+    /// Commands that replace the built-in interpreter for a fence language
+    interpreters: HashMap<String, Vec<String>>,
+    /// A step started with `x` that is still running, where its result
+    /// arrives, and the flag that kills it
+    running: Option<(usize, Receiver<RunResult>, Arc<AtomicBool>)>,
+    /// What the last step run with `x` printed, shown in a pane under the runbook
+    pub(super) output_pane: Option<StepOutput>,
+}
+
+/// How a step run with `x` ended, and what it printed
+type RunResult = Result<(Option<ExitStatus>, String)>;
+
+/// Output of a step run with `x`
+pub(super) struct StepOutput {
+    pub(super) step: usize,
+    pub(super) success: bool,
+    /// How it ended, e.g. "exited with 3"
+    pub(super) summary: String,
+    /// Stdout and stderr, interleaved
+    pub(super) text: String,
+    /// Lines scrolled down in the pane
+    pub(super) scroll: usize,
 }
 
 impl TuiApp {
@@ -52,64 +141,177 @@ impl TuiApp {
             document,
             current_step: 0,
             scroll_offset: 0,
+            viewport_height: 0,
+            viewport_width: 0,
+            wrap_enabled: true,
+            horizontal_offset: 0,
             transient_message: None,
+            quit_armed: None,
+            execute_armed: None,
+            clipboard: None,
+            jump_input: None,
+            show_help: false,
+            danger_patterns: danger_patterns(&[]),
+            shell: None,
+            env: HashMap::new(),
+            vars: HashMap::new(),
+            sudo_cmd: DEFAULT_SUDO_CMD.to_string(),
+            exit_on_interrupt: false,
+            theme: Theme::default(),
+            highlighters: HighlighterRegistry::new(Theme::default(), &danger_patterns(&[])),
+            collapsed_sections: HashSet::new(),
+            step_started: Instant::now(),
+            run_started: Instant::now(),
+            timeout: None,
+            interpreters: HashMap::new(),
+            running: None,
+            output_pane: None,
         }
     }
 
+    /// Draw with these colors instead of the default dark theme
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self.highlighters = HighlighterRegistry::new(theme, &self.danger_patterns);
+        self
+    }
+
+    /// Highlight `language` code with `highlighter` instead of the built-in one
+    ///
+    /// Call after [`with_theme`](Self::with_theme) and
+    /// [`with_danger_patterns`](Self::with_danger_patterns), which reset the
+    /// highlighters.
+    pub fn with_highlighter(
+        mut self,
+        language: &str,
+        highlighter: impl Highlighter + 'static,
+    ) -> Self {
+        self.highlighters.register(language, highlighter);
+        self
+    }
+
+    /// Flag and highlight steps matching these patterns as dangerous, on top of
+    /// the defaults
+    pub fn with_danger_patterns(mut self, extra: &[String]) -> Self {
+        self.danger_patterns = danger_patterns(extra);
+        self.highlighters = HighlighterRegistry::new(self.theme, &self.danger_patterns);
+        self
+    }
+
+    /// Drop into this shell instead of `$SHELL`
+    pub fn with_shell(mut self, shell: impl Into<String>) -> Self {
+        self.shell = Some(shell.into());
+        self
+    }
+
+    /// Export these environment variables into the shell
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Substitute these values for `${VAR}` references in steps run with `x`
+    pub fn with_vars(mut self, vars: HashMap<String, String>) -> Self {
+        self.vars = vars;
+        self
+    }
+
+    /// Run `{sudo}` steps under this command (e.g. `doas`) instead of `sudo`
+    pub fn with_sudo_cmd(mut self, sudo_cmd: impl Into<String>) -> Self {
+        self.sudo_cmd = sudo_cmd.into();
+        self
+    }
+
+    /// Kill steps run with `x` that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run steps in these fence languages with the given program and arguments
+    pub fn with_interpreters(mut self, interpreters: HashMap<String, Vec<String>>) -> Self {
+        self.interpreters = interpreters;
+        self
+    }
+
+    /// Quit instead of returning to the TUI when a shell exits after Ctrl-C
+    pub fn with_exit_on_interrupt(mut self, exit: bool) -> Self {
+        self.exit_on_interrupt = exit;
+        self
+    }
+
     pub fn run(&mut self) -> Result<()> {
-        // Setup terminal
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
-
-        let result = self.run_loop(&mut terminal);
-
-        // Restore terminal
-        disable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        )?;
-        terminal.show_cursor()?;
+        // The guard restores the terminal however we leave, panics included
+        let mut guard = TerminalGuard::enter()?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+        let result = self.run_loop(&mut terminal, &mut guard);
+        self.stop_running();
 
+        guard.leave()?;
         result
     }
 
-    fn run_loop(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
+    fn run_loop(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+        guard: &mut TerminalGuard,
+    ) -> Result<()> {
         loop {
+            self.poll_execution();
+
             terminal.draw(|f| {
+                let pane_height = self
+                    .output_pane
+                    .as_ref()
+                    .map_or(0, |pane| output_pane_height(pane, f.area().height));
                 let chunks = Layout::default()
                     .direction(Direction::Vertical)
-                    .constraints([Constraint::Min(10), Constraint::Length(3)])
+                    .constraints([
+                        Constraint::Min(10),
+                        Constraint::Length(pane_height),
+                        Constraint::Length(STATUS_BAR_HEIGHT),
+                    ])
                     .split(f.area());
-            
-                let runbook_content = self.render_runbook_content();
-                let runbook = Paragraph::new(runbook_content)
+
+                // Inside the borders
+                self.viewport_height = chunks[0].height.saturating_sub(2) as usize;
+                self.viewport_width = chunks[0].width.saturating_sub(2) as usize;
+                let runbook = self.runbook_paragraph();
+                let max_scroll = self.content_height(&runbook).saturating_sub(self.viewport_height);
+                let horizontal_offset = if self.wrap_enabled { 0 } else { self.horizontal_offset };
+                let runbook = runbook
                     .block(
                         Block::default()
                             .title("📘 Runbook")
                             .borders(Borders::ALL)
-                            .border_style(Style::default().fg(Color::Cyan)),
+                            .border_style(Style::default().fg(self.theme.accent)),
                     )
-                    .wrap(Wrap { trim: true })
-                    .scroll((self.scroll_offset as u16, 0));
+                    .scroll((
+                        self.scroll_offset.min(max_scroll) as u16,
+                        horizontal_offset as u16,
+                    ));
             
                 f.render_widget(runbook, chunks[0]);
             
                 // Status bar
                 let total_steps = self.document.step_count();
-                let status_text = if total_steps == 0 {
-                    " No executable steps | q: Quit ".to_string()
+                let status_text = if let Some(input) = &self.jump_input {
+                    format!(" Go to step: {}_ | Enter: Jump | Esc: Cancel ", input)
+                } else if total_steps == 0 {
+                    " View-only: no executable steps | ↑/↓: Scroll | q: Quit ".to_string()
                 } else if self.current_step >= total_steps {
-                    " ✅ Final step complete! Press 'q' to quit or 'p' to review. ".to_string()
+                    format!(
+                        " ✅ Final step complete! (total {}) Press 'q' to quit or 'p' to review. ",
+                        format_elapsed(self.run_started.elapsed())
+                    )
                 } else {
                     format!(
-                        " Step {}/{} | ↑↓: Scroll | n: Next | p: Previous | s: Shell | q: Quit ",
+                        " Step {}/{} | ⏱ {} (total {}) | n: Next | p: Previous | s: Shell | x: Run | ?: Help | q: Quit ",
                         self.current_step.min(total_steps),
-                        total_steps
+                        total_steps,
+                        format_elapsed(self.step_started.elapsed()),
+                        format_elapsed(self.run_started.elapsed())
                     )
                 };
             
@@ -117,17 +319,21 @@ impl TuiApp {
                     .alignment(Alignment::Center)
                     .style(
                         Style::default()
-                            .bg(Color::Blue)
-                            .fg(Color::White)
+                            .bg(self.theme.status_bg)
+                            .fg(self.theme.status_fg)
                             .add_modifier(Modifier::BOLD),
                     )
                     .block(
                         Block::default()
                             .borders(Borders::ALL)
-                            .border_style(Style::default().fg(Color::White)),
+                            .border_style(Style::default().fg(self.theme.status_fg)),
                     );
+
+                if let Some(pane) = &self.output_pane {
+                    f.render_widget(output_pane_widget(pane, &self.theme), chunks[1]);
+                }
             
-                f.render_widget(status, chunks[1]);
+                f.render_widget(status, chunks[2]);
             
                 // Render transient message as a floating single-line overlay (doesn't change Layout)
                 const MSG_TTL: Duration = Duration::from_secs(4);
@@ -135,9 +341,9 @@ impl TuiApp {
                     if Instant::now().saturating_duration_since(when) < MSG_TTL {
                         // Place the overlay directly above the status bar, full width
                         let overlay_area = ratatui::layout::Rect::new(
-                            chunks[1].x,
-                            chunks[1].y.saturating_sub(1),
-                            chunks[1].width,
+                            chunks[2].x,
+                            chunks[2].y.saturating_sub(1),
+                            chunks[2].width,
                             1,
                         );
             
@@ -145,8 +351,8 @@ impl TuiApp {
                             .alignment(Alignment::Left)
                             .style(
                                 Style::default()
-                                    .bg(Color::Black)
-                                    .fg(Color::White)
+                                    .bg(self.theme.status_bg)
+                                    .fg(self.theme.status_fg)
                                     .add_modifier(Modifier::BOLD),
                             )
                             .block(Block::default()); // no borders so it doesn't change layout
@@ -159,25 +365,70 @@ impl TuiApp {
                         // so we leave clearing to the outer loop after draw (see below).
                     }
                 }
+
+                // Help popup floats over the runbook without changing Layout
+                if self.show_help {
+                    render_help_overlay(f, chunks[0], &self.theme);
+                }
             })?;
 
             // Handle input
             if event::poll(std::time::Duration::from_millis(100))? {
-                if let Event::Key(key) = event::read()? {
+                let event = event::read()?;
+                if let Event::Resize(width, height) = event {
+                    // Redraw right away instead of waiting for the next key
+                    self.handle_resize(width, height);
+                    continue;
+                }
+                if let Event::Mouse(mouse) = event {
+                    self.handle_mouse(mouse.kind);
+                    continue;
+                }
+
+                if let Event::Key(key) = event {
+                    if self.jump_input.is_some() {
+                        self.handle_jump_key(key.code);
+                        continue;
+                    }
+
+                    // Any key dismisses the help overlay
+                    if self.show_help {
+                        self.show_help = false;
+                        continue;
+                    }
+
+                    if self.handle_output_key(key.code) {
+                        continue;
+                    }
+
                     match key.code {
-                        KeyCode::Char('q') => break,
+                        KeyCode::Char('q') if self.confirm_quit() => break,
                         KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+                        KeyCode::Char('c') => self.copy_current_step(),
+                        KeyCode::Char('C') => self.copy_script(),
+                        KeyCode::Char('g') => self.jump_input = Some(String::new()),
+                        KeyCode::Tab | KeyCode::Char('z') => self.toggle_current_section(),
+                        KeyCode::Char('?') => self.show_help = true,
                         KeyCode::Char('n') => self.next_step(),
                         KeyCode::Char('p') => self.previous_step(),
                         KeyCode::Char('s') => {
-                            self.drop_to_shell(terminal)?;
+                            self.drop_to_shell(terminal, guard)?;
+                        }
+                        KeyCode::Char('r') if self.start_rerun() => {
+                            self.drop_to_shell(terminal, guard)?;
+                        }
+                        KeyCode::Char('x') => {
+                            self.start_execute();
                         }
                         KeyCode::Up => {
-                            self.scroll_offset = self.scroll_offset.saturating_sub(1);
+                            self.scroll_up(1);
                         }
                         KeyCode::Down => {
-                            self.scroll_offset = self.scroll_offset.saturating_add(1);
+                            self.scroll_down(1);
                         }
+                        KeyCode::Char('w') => self.toggle_wrap(),
+                        KeyCode::Left => self.scroll_left(HORIZONTAL_SCROLL_COLS),
+                        KeyCode::Right => self.scroll_right(HORIZONTAL_SCROLL_COLS),
                         _ => {}
                     }
                 }
@@ -187,43 +438,85 @@ impl TuiApp {
         Ok(())
     }
 
-    fn render_runbook_content(&self) -> Vec<Line> {
+    pub(super) fn render_runbook_content(&self) -> Vec<Line<'_>> {
         let mut lines = Vec::new();
-        let code_blocks = self.document.code_blocks();
+        let mut step_num = 0;
         let i = icons();
+        let t = &self.theme;
 
+        // Doc-style runbooks can't be stepped through; say so up front
+        if self.document.step_count() == 0 && !self.document.sections.is_empty() {
+            lines.push(Line::from(Span::styled(
+                format!("{} {}", i.info, VIEW_ONLY_BANNER),
+                Style::default().fg(t.info).add_modifier(Modifier::BOLD),
+            )));
+            lines.push(Line::from(""));
+        }
+
+        // Sub-sections of a collapsed section are hidden up to this index
+        let mut hidden_until = 0;
         for (section_idx, section) in self.document.sections.iter().enumerate() {
+            if section_idx < hidden_until {
+                continue;
+            }
+
             // Render header
             if let Some(header) = &section.header {
                 let level = section.header_level.unwrap_or(1);
-                let header_style = match level {
-                    1 => Style::default()
-                        .fg(Color::Cyan)
-                        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
-                    2 => Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
-                    _ => Style::default().fg(Color::Gray).add_modifier(Modifier::BOLD),
+                let header_modifier = match level {
+                    1 => Modifier::BOLD | Modifier::UNDERLINED,
+                    _ => Modifier::BOLD,
                 };
+                let header_style = Style::default().fg(t.header(level)).add_modifier(header_modifier);
 
                 // Add visual separator for top-level sections
                 if level == 1 && section_idx > 0 {
                     lines.push(Line::from(Span::styled(
                         "─".repeat(60),
-                        Style::default().fg(Color::DarkGray),
+                        Style::default().fg(t.muted),
                     )));
                 }
 
-                lines.push(Line::from(""));
-                lines.push(Line::from(Span::styled(
-                    format!("📘 {} {}", "#".repeat(level as usize), header),
+                let collapsed = self.collapsed_sections.contains(&section_idx);
+                let fold_marker = if collapsed { "▸" } else { "▾" };
+                let mut header_spans = vec![Span::styled(
+                    format!("{} 📘 {} {}", fold_marker, "#".repeat(level as usize), header),
                     header_style,
-                )));
+                )];
+
+                if collapsed {
+                    // Hidden steps keep their numbers
+                    let extent = self.section_extent(section_idx);
+                    let hidden = self.document.sections[extent.clone()]
+                        .iter()
+                        .map(|s| s.step_count())
+                        .sum::<usize>();
+                    hidden_until = extent.end;
+                    step_num += hidden;
+                    header_spans.push(Span::styled(
+                        format!("  ({} step{} hidden)", hidden, if hidden == 1 { "" } else { "s" }),
+                        Style::default().fg(t.muted),
+                    ));
+                } else {
+                    header_spans.push(Span::styled(
+                        step_count_suffix(section.step_count()),
+                        Style::default().fg(t.muted),
+                    ));
+                }
+
+                lines.push(Line::from(""));
+                lines.push(Line::from(header_spans));
                 lines.push(Line::from(""));
+
+                if collapsed {
+                    continue;
+                }
             }
 
             // Render blocks
             for block in &section.blocks {
                 match block {
-                    DocBlock::Text(text) => {
+                    DocBlock::Text { text, .. } => {
                         for line in text.lines() {
                             if !line.trim().is_empty() {
                                 let upper = line.to_uppercase();
@@ -231,92 +524,128 @@ impl TuiApp {
                                     Line::from(vec![
                                         Span::styled(
                                             format!("{} ", i.warning),
-                                            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                                            Style::default().fg(t.warning).add_modifier(Modifier::BOLD),
                                         ),
-                                        Span::styled(line, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                                        Span::styled(line, Style::default().fg(t.warning).add_modifier(Modifier::BOLD)),
                                     ])
                                 } else if upper.contains("DANGER") || upper.contains("CRITICAL") {
                                     Line::from(vec![
                                         Span::styled(
                                             format!("{} ", i.danger),
-                                            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                                            Style::default().fg(t.danger).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
                                         ),
-                                        Span::styled(line, Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+                                        Span::styled(line, Style::default().fg(t.danger).add_modifier(Modifier::BOLD)),
                                     ])
                                 } else if upper.contains("INFO") || upper.contains("NOTE") {
                                     Line::from(vec![
                                         Span::styled(
                                             format!("{} ", i.info),
-                                            Style::default().fg(Color::Blue),
+                                            Style::default().fg(t.info),
                                         ),
-                                        Span::styled(line, Style::default().fg(Color::Gray)),
+                                        Span::styled(line, Style::default().fg(t.muted)),
                                     ])
                                 } else {
-                                    Line::from(line.to_string())
+                                    Line::from(link_spans(line, t))
                                 };
                                 lines.push(styled_line);
                             }
                         }
                         lines.push(Line::from(""));
                     }
+                    DocBlock::Table { headers, rows } => {
+                        lines.extend(table_lines(headers, rows, t));
+                        lines.push(Line::from(""));
+                    }
+                    DocBlock::Callout { kind, text } => {
+                        let border = Style::default().fg(t.callout(*kind));
+                        let title = match (i.callout(*kind), kind.label()) {
+                            (Some(icon), Some(label)) => format!("┌─ {} {}", icon, label),
+                            _ => "┌─".to_string(),
+                        };
+                        lines.push(Line::from(Span::styled(title, border.add_modifier(Modifier::BOLD))));
+                        for line in text.lines() {
+                            lines.push(Line::from(vec![Span::styled("│ ", border), Span::raw(line)]));
+                        }
+                        lines.push(Line::from(Span::styled("└─", border)));
+                        lines.push(Line::from(""));
+                    }
+                    DocBlock::Checklist { items } => {
+                        for (checked, text) in items {
+                            let color = if *checked { t.done } else { t.pending };
+                            lines.push(Line::from(vec![
+                                Span::styled(format!("{} ", i.checkbox(*checked)), Style::default().fg(color)),
+                                Span::raw(text.as_str()),
+                            ]));
+                        }
+                        lines.push(Line::from(""));
+                    }
+                    DocBlock::Code(code) if !code.is_executable() => {
+                        // Display-only blocks (e.g. output, yaml) are boxed and dimmed, without a step number
+                        let listing_style = Style::default().fg(t.muted).add_modifier(Modifier::DIM);
+                        lines.push(Line::from(Span::styled(format!("┌─ [{}]", code.language), listing_style)));
+                        for line in code.content.lines() {
+                            lines.push(Line::from(Span::styled(format!("│ {}", line), listing_style)));
+                        }
+                        lines.push(Line::from(Span::styled("└─", listing_style)));
+                        lines.push(Line::from(""));
+                    }
                     DocBlock::Code(code) => {
-                        // Find which step number this is
-                        let step_num = code_blocks
-                            .iter()
-                            .position(|c| *c == code)
-                            .map(|i| i + 1)
-                            .unwrap_or(0);
+                        step_num += 1;
 
                         let is_current = step_num == self.current_step;
                         let is_completed = step_num < self.current_step;
 
                         // Step header styling
                         let (marker, step_style, box_char) = if is_completed {
-                            (i.done, Style::default().fg(Color::Green).add_modifier(Modifier::BOLD), "│")
+                            (i.done, Style::default().fg(t.done).add_modifier(Modifier::BOLD), "│")
                         } else if is_current {
-                            (i.current, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD), "┃")
+                            (i.current, Style::default().fg(t.current).add_modifier(Modifier::BOLD), "┃")
                         } else {
-                            (i.pending, Style::default().fg(Color::DarkGray), "│")
+                            (i.pending, Style::default().fg(t.pending), "│")
                         };
 
-                        // Check if this looks like a dangerous command (case-insensitive)
-                        let content_lower = code.content.to_lowercase();
-                        let is_dangerous = content_lower.contains("rm -rf")
-                            || content_lower.contains("drop table")
-                            || content_lower.contains("drop database")
-                            || content_lower.contains("delete ")
-                            || content_lower.contains("--force");
+                        // Explicitly flagged `{danger}`, or looks like a dangerous command
+                        let is_dangerous = code.is_dangerous_with(&self.danger_patterns);
 
                         let danger_marker = if is_dangerous {
                             Span::styled(
                                 format!(" {}", i.danger),
-                                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                                Style::default().fg(t.danger).add_modifier(Modifier::BOLD),
                             )
                         } else {
                             Span::raw("")
                         };
 
+                        let note = match code.note() {
+                            Some(note) => Span::styled(
+                                format!(" {}", note),
+                                Style::default().fg(t.muted).add_modifier(Modifier::DIM),
+                            ),
+                            None => Span::raw(""),
+                        };
+
                         lines.push(Line::from(vec![
                             Span::styled(format!("{} ", marker), step_style),
-                            Span::styled(format!("Step {} [{}]:", step_num, code.language), step_style),
+                            Span::styled(format!("Step {} [{}]:", step_num, step_label(code)), step_style),
                             danger_marker,
+                            note,
                         ]));
 
                         // Code content with syntax-aware styling
                         let code_style = if is_current {
-                            Style::default().fg(Color::LightGreen).add_modifier(Modifier::BOLD)
+                            Style::default().fg(t.code).add_modifier(Modifier::BOLD)
                         } else if is_completed {
-                            Style::default().fg(Color::Green).add_modifier(Modifier::DIM)
+                            Style::default().fg(t.done).add_modifier(Modifier::DIM)
                         } else {
-                            Style::default().fg(Color::DarkGray)
+                            Style::default().fg(t.pending)
                         };
 
                         let prefix_style = if is_current {
-                            Style::default().fg(Color::Yellow)
+                            Style::default().fg(t.current)
                         } else if is_completed {
-                            Style::default().fg(Color::Green)
+                            Style::default().fg(t.done)
                         } else {
-                            Style::default().fg(Color::DarkGray)
+                            Style::default().fg(t.pending)
                         };
 
                         for line in code.content.lines() {
@@ -338,115 +667,492 @@ impl TuiApp {
         lines
     }
 
-    fn highlight_code_line(&self, line: &str, language: &str, base_style: &Style) -> Vec<Span> {
-        // Simple syntax highlighting for shell commands; fallback to raw text for others.
-        if language == "bash" || language == "sh" {
-            let mut spans = Vec::new();
-            let trimmed = line.trim_start();
-            let indent_len = line.len().saturating_sub(trimmed.len());
-            let indent = &line[..indent_len];
-            if !indent.is_empty() {
-                spans.push(Span::raw(indent.to_string()));
+    pub(super) fn highlight_code_line(&self, line: &str, language: &str, base_style: &Style) -> Vec<Span<'_>> {
+        self.highlighters.get(language).highlight(line, base_style)
+    }
+
+    pub(super) fn next_step(&mut self) {
+        let total_steps = self.document.step_count();
+        if self.current_step < total_steps {
+            self.current_step += 1;
+            self.step_started = Instant::now();
+            self.auto_scroll_to_current_step();
+        } else if total_steps > 0 {
+            // Already at final step: set transient in-TUI prompt (won't disturb layout)
+            let msg = "🎉 You’ve reached the final step! Press 'q' to quit or 'p' to go back.".to_string();
+            self.transient_message = Some((msg, Instant::now()));
+        } else {
+            self.transient_message = Some((VIEW_ONLY_BANNER.to_string(), Instant::now()));
+        }
+    }
+
+    /// Furthest the runbook can scroll while still filling the viewport
+    pub(super) fn max_scroll(&self) -> usize {
+        self.content_height(&self.runbook_paragraph())
+            .saturating_sub(self.viewport_height)
+    }
+
+    /// The runbook content, wrapped when wrapping is on, without its border
+    fn runbook_paragraph(&self) -> Paragraph<'_> {
+        let runbook = Paragraph::new(self.render_runbook_content());
+        if self.wrap_enabled {
+            runbook.wrap(Wrap { trim: true })
+        } else {
+            runbook
+        }
+    }
+
+    /// Rows `runbook` takes up in the viewport, counting each wrapped line
+    ///
+    /// Before the first draw the width is unknown, so lines count once each.
+    fn content_height(&self, runbook: &Paragraph) -> usize {
+        match u16::try_from(self.viewport_width) {
+            Ok(width) if width > 0 => runbook.line_count(width),
+            _ => runbook.line_count(u16::MAX),
+        }
+    }
+
+    fn scroll_up(&mut self, lines: usize) {
+        self.scroll_offset = self.scroll_offset.saturating_sub(lines);
+    }
+
+    fn scroll_down(&mut self, lines: usize) {
+        self.scroll_offset = self.scroll_offset.saturating_add(lines).min(self.max_scroll());
+    }
+
+    /// Switch between wrapped lines and sideways scrolling
+    pub(super) fn toggle_wrap(&mut self) {
+        self.wrap_enabled = !self.wrap_enabled;
+        self.horizontal_offset = 0;
+        let msg = if self.wrap_enabled {
+            "Line wrapping on"
+        } else {
+            "Line wrapping off: ←/→ to scroll sideways"
+        };
+        self.transient_message = Some((msg.to_string(), Instant::now()));
+    }
+
+    fn scroll_left(&mut self, cols: usize) {
+        self.horizontal_offset = self.horizontal_offset.saturating_sub(cols);
+    }
+
+    /// Scroll right, but not past the point where the widest line ends
+    pub(super) fn scroll_right(&mut self, cols: usize) {
+        if self.wrap_enabled {
+            return;
+        }
+        let widest = self
+            .render_runbook_content()
+            .iter()
+            .map(Line::width)
+            .max()
+            .unwrap_or(0);
+        let max_offset = widest.saturating_sub(self.viewport_width);
+        self.horizontal_offset = self.horizontal_offset.saturating_add(cols).min(max_offset);
+    }
+
+    /// Adopt a new terminal size, keeping the scroll position within the content
+    ///
+    /// The viewport is recomputed from the same layout the next draw uses:
+    /// the runbook fills everything above the status bar, inside its borders.
+    pub(super) fn handle_resize(&mut self, width: u16, height: u16) {
+        self.viewport_height = height.saturating_sub(STATUS_BAR_HEIGHT + 2) as usize;
+        self.viewport_width = width.saturating_sub(2) as usize;
+        self.scroll_offset = self.scroll_offset.min(self.max_scroll());
+        // Clamps the horizontal offset to the new width
+        self.scroll_right(0);
+    }
+
+    /// Scroll the runbook with the mouse wheel
+    pub(super) fn handle_mouse(&mut self, kind: MouseEventKind) {
+        match kind {
+            MouseEventKind::ScrollUp => {
+                self.scroll_up(MOUSE_SCROLL_LINES);
             }
-            if trimmed.is_empty() {
-                return spans;
+            MouseEventKind::ScrollDown => {
+                self.scroll_down(MOUSE_SCROLL_LINES);
             }
+            _ => {}
+        }
+    }
 
-            // Comment
-            if trimmed.starts_with('#') {
-                spans.push(Span::styled(
-                    trimmed.to_string(),
-                    Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC),
-                ));
-                return spans;
+    /// Handle a key while a jump-to-step number is being typed
+    fn handle_jump_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                if let Some(input) = &mut self.jump_input {
+                    input.push(c);
+                }
             }
-
-            let lower = trimmed.to_lowercase();
-            if lower.contains("rm ") || lower.contains("rm -rf") || lower.contains("delete ")
-                || lower.contains("drop ") || lower.contains("--force")
-            {
-                spans.push(Span::styled(trimmed.to_string(), Style::default().fg(Color::Red)));
-                return spans;
+            KeyCode::Backspace => {
+                if let Some(input) = &mut self.jump_input {
+                    input.pop();
+                }
             }
-            if trimmed.contains('$') {
-                let mut remaining = trimmed;
-                while let Some(dollar_idx) = remaining.find('$') {
-                    if dollar_idx > 0 {
-                        spans.push(Span::styled(remaining[..dollar_idx].to_string(), *base_style));
-                    }
+            KeyCode::Enter => {
+                if let Some(step) = self.jump_input.take().and_then(|i| i.parse().ok()) {
+                    self.jump_to_step(step);
+                }
+            }
+            KeyCode::Esc => self.jump_input = None,
+            _ => {}
+        }
+    }
 
-                    // process var after $
-                    let after = &remaining[dollar_idx + 1..];
-                    let var_end = after.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(after.len());
-                    let var = &after[..var_end];
-                    spans.push(Span::styled(
-                        format!("${}", var),
-                        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
-                    ));
+    /// Index of the section holding the current step, or the first headed section
+    fn current_section_index(&self) -> Option<usize> {
+        if self.current_step == 0 {
+            return self.document.sections.iter().position(|s| s.header.is_some());
+        }
 
-                    // advance remaining
-                    remaining = &after[var_end..];
-                }
-                if !remaining.is_empty() {
-                    spans.push(Span::styled(remaining.to_string(), *base_style));
-                }
-                return spans;
+        let mut step_num = 0;
+        for (section_idx, section) in self.document.sections.iter().enumerate() {
+            step_num += section.step_count();
+            if step_num >= self.current_step {
+                return Some(section_idx);
             }
+        }
+        None
+    }
+
+    /// The sections collapsing `section_idx` hides along with it: those up
+    /// to the next header at its level or above
+    fn section_extent(&self, section_idx: usize) -> std::ops::Range<usize> {
+        let sections = &self.document.sections;
+        let level = sections[section_idx].header_level.unwrap_or(1);
+        let end = sections[section_idx + 1..]
+            .iter()
+            .position(|s| s.header.is_some() && s.header_level.unwrap_or(1) <= level)
+            .map_or(sections.len(), |offset| section_idx + 1 + offset);
+        section_idx..end
+    }
+
+    /// Collapse the top-level section around the current step down to its
+    /// header, sub-sections included, or expand it again
+    ///
+    /// Top-level sections are those with the highest-ranking header in the
+    /// runbook, `#` unless it has none.
+    pub(super) fn toggle_current_section(&mut self) {
+        let sections = &self.document.sections;
+        let header_level = |idx: usize| {
+            let section = &sections[idx];
+            section
+                .header
+                .as_ref()
+                .map(|_| section.header_level.unwrap_or(1))
+        };
+        let top_level = (0..sections.len()).filter_map(header_level).min();
+        let enclosing = self.current_section_index().and_then(|idx| {
+            (0..=idx)
+                .rev()
+                .find(|&i| header_level(i).is_some() && header_level(i) == top_level)
+        });
+        let Some(section_idx) = enclosing else {
+            let msg = "This step isn't under a header, so there's nothing to collapse".to_string();
+            self.transient_message = Some((msg, Instant::now()));
+            return;
+        };
+
+        if !self.collapsed_sections.remove(&section_idx) {
+            self.collapsed_sections.insert(section_idx);
+        }
+        self.auto_scroll_to_current_step();
+    }
+
+    fn jump_to_step(&mut self, step: usize) {
+        self.current_step = step.min(self.document.step_count());
+        self.step_started = Instant::now();
+        self.auto_scroll_to_current_step();
+    }
+
+    /// The code of the current step, if one is selected
+    fn current_code(&self) -> Option<&CodeBlock> {
+        let index = self.current_step.checked_sub(1)?;
+        self.document
+            .code_blocks_iter()
+            .filter(|code| code.is_executable())
+            .nth(index)
+    }
+
+    fn copy_current_step(&mut self) {
+        let Some(content) = self.current_code().map(|code| code.content.clone()) else {
+            let msg = "No step selected to copy".to_string();
+            self.transient_message = Some((msg, Instant::now()));
+            return;
+        };
 
-            // Pipes and redirects are just returned with base style (could be extended)
-            spans.push(Span::styled(trimmed.to_string(), *base_style));
-            spans
+        let msg = match self.copy_to_clipboard(content) {
+            Ok(()) => format!("📋 Copied step {} to clipboard", self.current_step),
+            Err(e) => format!("Clipboard unavailable: {}", e),
+        };
+        self.transient_message = Some((msg, Instant::now()));
+    }
+
+    /// Announce a re-run of the current step, returning whether there is one
+    ///
+    /// Unlike `n`, a re-run leaves `current_step` where it is, for steps like
+    /// "wait for pods" that need running until they pass.
+    pub(super) fn start_rerun(&mut self) -> bool {
+        let has_step = self.current_step > 0 && self.current_step <= self.document.step_count();
+        let msg = if has_step {
+            format!("🔁 Re-running step {}", self.current_step)
         } else {
-            // For other languages, just use base style
-            vec![Span::styled(line.to_string(), *base_style)]
+            "No step selected to re-run".to_string()
+        };
+        self.transient_message = Some((msg, Instant::now()));
+        has_step
+    }
+
+    /// Whether `q` should quit now
+    ///
+    /// Between the first and last step a run is in progress, so the first
+    /// `q` only arms quitting; a second within [`CONFIRM_WINDOW`] quits.
+    pub(super) fn confirm_quit(&mut self) -> bool {
+        let in_progress = self.current_step > 0 && self.current_step < self.document.step_count();
+        if !in_progress {
+            return true;
         }
+        if let Some(armed) = self.quit_armed.take() {
+            if armed.elapsed() < CONFIRM_WINDOW {
+                return true;
+            }
+        }
+        self.quit_armed = Some(Instant::now());
+        let msg = "Press q again to quit (run in progress)".to_string();
+        self.transient_message = Some((msg, Instant::now()));
+        false
     }
 
-    fn next_step(&mut self) {
-        let total_steps = self.document.step_count();
-        if self.current_step < total_steps {
-            self.current_step += 1;
-            self.auto_scroll_to_current_step();
-        } else if total_steps > 0 {
-            // Already at final step: set transient in-TUI prompt (won't disturb layout)
-            let msg = "🎉 You’ve reached the final step! Press 'q' to quit or 'p' to go back.".to_string();
+    /// Run the current step in the background, returning whether it started
+    ///
+    /// Its output, stdout and stderr together, appears in the output pane once
+    /// it finishes. Steps that need root are left to `s`, since the sudo
+    /// command would prompt for a password on the terminal the TUI is drawing
+    /// on. Dangerous steps need a second `x` within [`CONFIRM_WINDOW`].
+    pub(super) fn start_execute(&mut self) -> bool {
+        let step = self.current_step;
+        let code = match (&self.running, self.current_code()) {
+            (Some((running, ..)), _) => Err(format!("Step {} is still running", running)),
+            (None, None) => Err("No step selected to run".to_string()),
+            (None, Some(code)) if code.requires_sudo() => Err(format!(
+                "Step {} needs root; press s to run it with {} in a shell",
+                step, self.sudo_cmd
+            )),
+            // ssh may ask about the host key or for a password on the terminal
+            (None, Some(code)) if code.remote_host.is_some() => Err(format!(
+                "Step {} runs on {}; press s to run it in a shell",
+                step,
+                code.remote_host.as_deref().unwrap_or_default()
+            )),
+            (None, Some(code)) => Ok(code.clone()),
+        };
+        let code = match code {
+            Ok(code) => code,
+            Err(msg) => {
+                self.transient_message = Some((msg, Instant::now()));
+                return false;
+            }
+        };
+        if code.is_dangerous_with(&self.danger_patterns) && !self.confirm_dangerous(step) {
+            return false;
+        }
+
+        let script = code.render_content(&self.vars, &self.env);
+        let env = self.env.clone();
+        let timeout = self.timeout;
+        let sudo_cmd = self.sudo_cmd.clone();
+        let interpreters = self.interpreters.clone();
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let stop = Arc::clone(&cancel);
+        thread::spawn(move || {
+            let result = run_code_combined(
+                &code,
+                &script,
+                &env,
+                timeout,
+                &sudo_cmd,
+                &interpreters,
+                &stop,
+            );
+            // The TUI may have quit in the meantime
+            let _ = sender.send(result);
+        });
+        self.running = Some((step, receiver, cancel));
+        self.transient_message = Some((format!("▶ Running step {}…", step), Instant::now()));
+        true
+    }
+
+    /// Whether `x` should run dangerous step `step` now
+    ///
+    /// The first press only arms it; a second on the same step within
+    /// [`CONFIRM_WINDOW`] runs it.
+    pub(super) fn confirm_dangerous(&mut self, step: usize) -> bool {
+        if let Some((armed_step, armed)) = self.execute_armed.take() {
+            if armed_step == step && armed.elapsed() < CONFIRM_WINDOW {
+                return true;
+            }
+        }
+        self.execute_armed = Some((step, Instant::now()));
+        let msg = format!("⚠ Step {} looks dangerous; press x again to run it", step);
+        self.transient_message = Some((msg, Instant::now()));
+        false
+    }
+
+    /// Kill a step started with `x` that is still running, so it doesn't
+    /// outlive the TUI
+    pub(super) fn stop_running(&mut self) {
+        if let Some((_, receiver, cancel)) = self.running.take() {
+            cancel.store(true, Ordering::Relaxed);
+            // The runner thread kills and reaps it, unless we exit first
+            let _ = receiver.recv_timeout(STOP_GRACE);
+        }
+    }
+
+    /// Show the result of a step started with `x`, if it has finished
+    pub(super) fn poll_execution(&mut self) {
+        let Some((step, receiver, _)) = &self.running else {
+            return;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err(anyhow!("the step stopped without a result")),
+        };
+        let step = *step;
+        self.running = None;
+
+        let (success, summary, text) = match result {
+            Ok((Some(status), text)) => {
+                let summary = match status.code() {
+                    Some(code) => format!("exited with {}", code),
+                    None => "was killed by a signal".to_string(),
+                };
+                (status.success(), summary, text)
+            }
+            Ok((None, text)) => {
+                let limit = self.timeout.unwrap_or_default();
+                (false, format!("timed out after {:?}", limit), text)
+            }
+            Err(e) => (false, format!("could not run: {:#}", e), String::new()),
+        };
+        self.output_pane = Some(StepOutput {
+            step,
+            success,
+            summary,
+            text,
+            scroll: 0,
+        });
+    }
+
+    /// Scroll or close the output pane, returning whether the key was used
+    pub(super) fn handle_output_key(&mut self, code: KeyCode) -> bool {
+        let Some(pane) = &mut self.output_pane else {
+            return false;
+        };
+        match code {
+            KeyCode::Esc => self.output_pane = None,
+            KeyCode::Up => pane.scroll = pane.scroll.saturating_sub(1),
+            KeyCode::Down => {
+                let last = pane.text.lines().count().saturating_sub(1);
+                pane.scroll = (pane.scroll + 1).min(last);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Copy every step as one shell script, as `sysadmin export` writes it
+    fn copy_script(&mut self) {
+        let steps = self.document.step_count();
+        if steps == 0 {
+            let msg = "No steps to copy".to_string();
             self.transient_message = Some((msg, Instant::now()));
+            return;
         }
+
+        let script = exporter::to_shell_script(&self.document);
+        let msg = match self.copy_to_clipboard(script) {
+            Ok(()) => format!("📋 Copied all {} steps to clipboard as a script", steps),
+            Err(e) => format!("Clipboard unavailable: {}", e),
+        };
+        self.transient_message = Some((msg, Instant::now()));
     }
 
-    fn previous_step(&mut self) {
+    fn copy_to_clipboard(&mut self, text: String) -> Result<(), arboard::Error> {
+        let mut clipboard = match self.clipboard.take() {
+            Some(clipboard) => clipboard,
+            None => Clipboard::new()?,
+        };
+        let result = clipboard.set_text(text);
+        self.clipboard = Some(clipboard);
+        result
+    }
+
+    pub(super) fn previous_step(&mut self) {
         if self.current_step > 0 {
             self.current_step = self.current_step.saturating_sub(1);
+            self.step_started = Instant::now();
             self.auto_scroll_to_current_step();
+        } else if self.document.step_count() == 0 {
+            self.transient_message = Some((VIEW_ONLY_BANNER.to_string(), Instant::now()));
         }
     }
 
-    fn auto_scroll_to_current_step(&mut self) {
+    pub(super) fn auto_scroll_to_current_step(&mut self) {
         // Find the line number where the current step is
-        let code_blocks = self.document.code_blocks();
-        if self.current_step == 0 || self.current_step > code_blocks.len() {
+        if self.current_step == 0 || self.current_step > self.document.step_count() {
             return;
         }
 
-        let target_code = code_blocks[self.current_step - 1];
-        let mut line_count = 0;
+        let mut step_num = 0;
+        let mut line_count: usize = 0;
+        // Sub-sections of a collapsed section are hidden up to this index
+        let mut hidden_until = 0;
+
+        for (section_idx, section) in self.document.sections.iter().enumerate() {
+            if section_idx < hidden_until {
+                continue;
+            }
 
-        for section in &self.document.sections {
             // Count header lines
             if section.header.is_some() {
                 line_count += 3;
             }
 
+            // A collapsed section is only its header; scroll to that
+            if self.collapsed_sections.contains(&section_idx) && section.header.is_some() {
+                let extent = self.section_extent(section_idx);
+                step_num += self.document.sections[extent.clone()]
+                    .iter()
+                    .map(|s| s.step_count())
+                    .sum::<usize>();
+                hidden_until = extent.end;
+                if step_num >= self.current_step {
+                    self.scroll_offset = line_count.saturating_sub(5).min(self.max_scroll());
+                    return;
+                }
+                continue;
+            }
+
             // Count lines in blocks
             for block in &section.blocks {
                 match block {
-                    DocBlock::Text(text) => line_count += text.lines().count() + 1,
+                    DocBlock::Text { text, .. } => line_count += text.lines().count() + 1,
+                    // Text plus the top and bottom borders
+                    DocBlock::Callout { text, .. } => line_count += text.lines().count() + 3,
+                    // Rows plus the header, borders and a blank line
+                    DocBlock::Table { rows, .. } => line_count += rows.len() + 4,
+                    DocBlock::Checklist { items } => line_count += items.len() + 1,
                     DocBlock::Code(code) => {
-                        if code == target_code {
-                            // Found it! Set scroll to show this step near the top
-                            // Leave some context lines above (5 lines)
-                            self.scroll_offset = line_count.saturating_sub(5);
-                            return;
+                        if code.is_executable() {
+                            step_num += 1;
+                            if step_num == self.current_step {
+                                // Found it! Set scroll to show this step near the top
+                                // Leave some context lines above (5 lines)
+                                self.scroll_offset = line_count.saturating_sub(5).min(self.max_scroll());
+                                return;
+                            }
                         }
                         line_count += 1 + code.content.lines().count() + 1;
                     }
@@ -455,22 +1161,34 @@ impl TuiApp {
         }
     }
 
-    fn drop_to_shell(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
+    fn drop_to_shell(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+        guard: &mut TerminalGuard,
+    ) -> Result<()> {
         // Properly restore terminal before spawning shell
-        disable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        )?;
-        terminal.show_cursor()?;
+        guard.leave()?;
 
         // Clear screen and show current step
         print!("\x1B[2J\x1B[1;1H"); // Clear screen, move to top
 
-        let code_blocks = self.document.code_blocks();
-        if self.current_step > 0 && self.current_step <= code_blocks.len() {
-            let code = code_blocks[self.current_step - 1];
+        let mut dir = None;
+        if let Some(code) = self.current_code() {
+            // Like a directly-run step, a remote step's `dir=` isn't local
+            match code.working_dir().filter(|_| code.remote_host.is_none()) {
+                Some(path) if path.is_dir() => dir = Some(path),
+                Some(path) => println!(
+                    "⚠️  Step directory {} does not exist; staying in the current one",
+                    path.display()
+                ),
+                None => {}
+            }
+            if code.requires_sudo() {
+                println!("⚠️  This step needs root");
+            }
+            if let Some(host) = &code.remote_host {
+                println!("⚠️  This step runs on {}; ssh there first", host);
+            }
             println!("{}", "=".repeat(60));
             println!("Current step [{}]:", code.language);
             for line in code.content.lines() {
@@ -483,33 +1201,144 @@ impl TuiApp {
         }
 
         // Spawn shell
-        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
-        let status = std::process::Command::new(&shell)
+        let shell = self
+            .shell
+            .clone()
+            .or_else(|| std::env::var("SHELL").ok())
+            .unwrap_or_else(|| "/bin/bash".to_string());
+        let mut command = std::process::Command::new(&shell);
+        if let Some(dir) = dir {
+            command.current_dir(dir);
+        }
+        let status = command
+            .envs(&self.env)
             .stdin(std::process::Stdio::inherit())
             .stdout(std::process::Stdio::inherit())
             .stderr(std::process::Stdio::inherit())
             .status()?;
 
-        if let Some(code) = status.code() {
-            if code == 130 {
-                // User Ctrl-C'd in shell, don't return to TUI
+        // A shell exits with 130 when its last command was Ctrl-C'd
+        if status.code() == Some(130) {
+            if self.exit_on_interrupt {
                 std::process::exit(130);
             }
+            println!("\nInterrupted.");
         }
 
         println!("\nReturning to TUI...");
         std::thread::sleep(std::time::Duration::from_millis(500));
 
         // Re-enter TUI mode
-        enable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            EnterAlternateScreen,
-            EnableMouseCapture
-        )?;
+        guard.reenter()?;
         terminal.hide_cursor()?;
         terminal.clear()?;
 
         Ok(())
     }
 }
+
+/// Rows for the output pane: its output plus borders, at most two fifths of the screen
+pub(super) fn output_pane_height(pane: &StepOutput, screen_height: u16) -> u16 {
+    let lines = u16::try_from(pane.text.lines().count().max(1)).unwrap_or(u16::MAX);
+    lines.saturating_add(2).min(screen_height * 2 / 5).max(3)
+}
+
+/// The output pane, bordered in the done or danger color by how the step ended
+fn output_pane_widget<'a>(pane: &'a StepOutput, theme: &Theme) -> Paragraph<'a> {
+    let (icon, color) = if pane.success {
+        (icons().done, theme.done)
+    } else {
+        (icons().danger, theme.danger)
+    };
+    let lines: Vec<Line> = if pane.text.trim().is_empty() {
+        vec![Line::from(Span::styled(
+            "(no output)",
+            Style::default().fg(theme.muted),
+        ))]
+    } else {
+        pane.text.lines().map(Line::from).collect()
+    };
+    Paragraph::new(lines).scroll((pane.scroll as u16, 0)).block(
+        Block::default()
+            .title(format!(
+                " {} Step {} {} | ↑/↓: Scroll | Esc: Close ",
+                icon, pane.step, pane.summary
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(color)),
+    )
+}
+
+/// Shown for runbooks with nothing to step through
+const VIEW_ONLY_BANNER: &str = "This runbook has no executable steps (view-only)";
+
+/// Format a duration as `mm:ss`, letting minutes run past 59
+pub(super) fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// Split a text line so the URL of each `label (https://...)` link is
+/// underlined in the link color
+pub(super) fn link_spans(line: &str, theme: &Theme) -> Vec<Span<'static>> {
+    let link_style = Style::default()
+        .fg(theme.link)
+        .add_modifier(Modifier::UNDERLINED);
+    let mut spans = Vec::new();
+    let mut rest = line;
+
+    while let Some(start) = ["(http://", "(https://"]
+        .iter()
+        .filter_map(|prefix| rest.find(prefix))
+        .min()
+    {
+        let Some(len) = rest[start + 1..].find(')') else {
+            break;
+        };
+        let url = &rest[start + 1..start + 1 + len];
+        spans.push(Span::raw(rest[..=start].to_string()));
+        spans.push(Span::styled(url.to_string(), link_style));
+        spans.push(Span::raw(")"));
+        rest = &rest[start + len + 2..];
+    }
+    if !rest.is_empty() || spans.is_empty() {
+        spans.push(Span::raw(rest.to_string()));
+    }
+    spans
+}
+
+/// Draw the keybinding help as a centered popup over `area`
+fn render_help_overlay(f: &mut ratatui::Frame, area: Rect, theme: &Theme) {
+    let key_width = HELP_KEYS.iter().map(|(k, _)| k.chars().count()).max().unwrap_or(0);
+    let lines: Vec<Line> = HELP_KEYS
+        .iter()
+        .map(|(key, action)| {
+            Line::from(vec![
+                Span::styled(
+                    format!(" {:<width$}  ", key, width = key_width),
+                    Style::default().fg(theme.current).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(*action),
+            ])
+        })
+        .collect();
+
+    let width = (area.width * 3 / 5).max(40).min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect::new(
+        area.x + area.width.saturating_sub(width) / 2,
+        area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    );
+
+    let help = Paragraph::new(lines).block(
+        Block::default()
+            .title(" Keys (any key to close) ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.current)),
+    );
+
+    f.render_widget(Clear, popup);
+    f.render_widget(help, popup);
+}
@@ -0,0 +1,422 @@
+use std::collections::hash_map::{Entry, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::model::{output_matches, Block, Document};
+use crate::parser::SourceMap;
+
+/// How serious a validation problem is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A structural problem found in a runbook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 1-based source line the problem refers to, if any
+    pub line_number: Option<usize>,
+    /// The `@include`d file `line_number` is in; `None` for the runbook itself
+    pub file: Option<PathBuf>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(line_number: usize, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            line_number: Some(line_number),
+            file: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(line_number: usize, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            line_number: Some(line_number),
+            file: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// Lint a runbook without executing it
+///
+/// `source` is the raw file content and `document` the result of parsing it.
+/// On top of [`validate_document`], fence problems are found in the source,
+/// since the parser drops fences it can't turn into steps. Diagnostics are
+/// sorted by line.
+pub fn validate(source: &str, document: &Document) -> Vec<Diagnostic> {
+    let mut diagnostics = check_fences(source);
+    diagnostics.extend(validate_document(document));
+
+    diagnostics.sort_by_key(|d| d.line_number);
+    diagnostics
+}
+
+/// Like [`validate`], for a runbook whose `@include`s were expanded into `source`
+///
+/// `map` comes from
+/// [`expand_includes_with_map`](crate::parser::expand_includes_with_map).
+/// Line numbers, including those in messages, point into the file each
+/// problem is in, and [`Diagnostic::file`] names included files.
+pub fn validate_expanded(source: &str, document: &Document, map: &SourceMap) -> Vec<Diagnostic> {
+    let runbook = map.files().first().map(PathBuf::as_path);
+    let describe = |line: usize| match map.locate(line) {
+        Some((file, line)) if Some(file) != runbook => format!("{}:{}", file.display(), line),
+        Some((_, line)) => format!("line {}", line),
+        None => format!("line {}", line),
+    };
+    let mut diagnostics = check_fences(source);
+    diagnostics.extend(document_checks(document, &describe));
+    diagnostics.sort_by_key(|d| d.line_number);
+
+    for diagnostic in &mut diagnostics {
+        let Some((file, line)) = diagnostic.line_number.and_then(|line| map.locate(line)) else {
+            continue;
+        };
+        diagnostic.line_number = Some(line);
+        if Some(file) != runbook {
+            diagnostic.file = Some(file.to_path_buf());
+        }
+    }
+    diagnostics
+}
+
+/// Lint a parsed runbook, sorted by line
+///
+/// Runs every check that doesn't need the source text; see
+/// [`Document::validate`].
+pub fn validate_document(document: &Document) -> Vec<Diagnostic> {
+    let mut diagnostics = document_checks(document, &|line| format!("line {}", line));
+    diagnostics.sort_by_key(|d| d.line_number);
+    diagnostics
+}
+
+/// Every check that doesn't need the source text, unsorted
+///
+/// `describe` names a line for messages that refer to another one.
+fn document_checks(document: &Document, describe: &dyn Fn(usize) -> String) -> Vec<Diagnostic> {
+    let mut diagnostics = check_empty_steps(document);
+    diagnostics.extend(check_headerless_steps(document));
+    diagnostics.extend(check_expected_output(document));
+    diagnostics.extend(check_retry_attributes(document));
+    diagnostics.extend(check_duplicate_steps(document, describe));
+    diagnostics
+}
+
+/// An open fenced code block while scanning the source
+struct OpenFence {
+    line_number: usize,
+    marker: char,
+    len: usize,
+}
+
+/// Find fences without a language and fences that are never closed
+fn check_fences(source: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut open: Option<OpenFence> = None;
+
+    for (idx, line) in source.lines().enumerate() {
+        let line_number = idx + 1;
+        let Some((marker, len, info)) = parse_fence(line) else {
+            continue;
+        };
+
+        match &open {
+            None => {
+                // Backtick fences can't have backticks in their info string
+                if marker == '`' && info.contains('`') {
+                    continue;
+                }
+                if info.is_empty() {
+                    diagnostics.push(Diagnostic::warning(
+                        line_number,
+                        "code block has no language and will not run as a step",
+                    ));
+                }
+                open = Some(OpenFence {
+                    line_number,
+                    marker,
+                    len,
+                });
+            }
+            Some(fence) => {
+                if marker == fence.marker && len >= fence.len && info.is_empty() {
+                    open = None;
+                }
+            }
+        }
+    }
+
+    if let Some(fence) = open {
+        diagnostics.push(Diagnostic::error(
+            fence.line_number,
+            "code block is never closed",
+        ));
+    }
+
+    diagnostics
+}
+
+/// Split a fence line into its marker character, run length and info string
+fn parse_fence(line: &str) -> Option<(char, usize, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.chars().take_while(|c| *c == marker).count();
+    if len < 3 {
+        return None;
+    }
+
+    Some((marker, len, trimmed[len..].trim()))
+}
+
+/// Steps with nothing in them
+fn check_empty_steps(document: &Document) -> Vec<Diagnostic> {
+    document
+        .code_blocks()
+        .into_iter()
+        .filter(|code| code.content.trim().is_empty())
+        .map(|code| Diagnostic::error(code.line_number, "code block is empty"))
+        .collect()
+}
+
+/// Expected-output regexes that don't compile
+fn check_expected_output(document: &Document) -> Vec<Diagnostic> {
+    document
+        .code_blocks()
+        .into_iter()
+        .filter_map(|code| {
+            let expected = code.expected_output.as_deref()?;
+            let error = output_matches(expected, "").err()?;
+            Some(Diagnostic::error(
+                code.line_number,
+                format!("expected output regex is invalid: {}", error),
+            ))
+        })
+        .collect()
+}
+
+/// `retries=` and `retry-delay=` values that can't be parsed
+fn check_retry_attributes(document: &Document) -> Vec<Diagnostic> {
+    document
+        .code_blocks()
+        .into_iter()
+        .flat_map(|code| {
+            let retries = code.retries().err();
+            let delay = code.retry_delay().err();
+            retries
+                .into_iter()
+                .chain(delay)
+                .map(|error| Diagnostic::error(code.line_number, format!("{:#}", error)))
+        })
+        .collect()
+}
+
+/// Steps with the same content as an earlier one, ignoring whitespace
+///
+/// Only steps on the same host and in the same directory count. Running a
+/// destructive command twice can do harm, so each repeat is flagged with the
+/// line of the first occurrence.
+fn check_duplicate_steps(
+    document: &Document,
+    describe: &dyn Fn(usize) -> String,
+) -> Vec<Diagnostic> {
+    let mut first_seen: HashMap<(Option<String>, Option<PathBuf>, String), usize> = HashMap::new();
+    document
+        .code_blocks()
+        .into_iter()
+        .filter(|code| code.is_executable() && !code.content.trim().is_empty())
+        .filter_map(|code| {
+            let normalized = code
+                .content
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let key = (code.remote_host.clone(), code.working_dir(), normalized);
+            match first_seen.entry(key) {
+                Entry::Occupied(first) => Some(Diagnostic::warning(
+                    code.line_number,
+                    format!("step duplicates the step at {}", describe(*first.get())),
+                )),
+                Entry::Vacant(entry) => {
+                    entry.insert(code.line_number);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Steps that appear before the first header, with no context
+fn check_headerless_steps(document: &Document) -> Vec<Diagnostic> {
+    document
+        .sections
+        .iter()
+        .filter(|section| section.header.is_none())
+        .flat_map(|section| &section.blocks)
+        .filter_map(|block| match block {
+            Block::Code(code) => Some(Diagnostic::warning(
+                code.line_number,
+                "step is not under a section header",
+            )),
+            Block::Text { .. }
+            | Block::Table { .. }
+            | Block::Callout { .. }
+            | Block::Checklist { .. } => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::SysadminParser;
+
+    fn lint(source: &str) -> Vec<Diagnostic> {
+        let document = SysadminParser::parse(source).unwrap();
+        validate(source, &document)
+    }
+
+    #[test]
+    fn test_expanded_runbook_lines_point_into_each_file() {
+        let dir = std::env::temp_dir().join(format!("sysadmin-validate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let included = dir.join("common.sysadmin");
+        std::fs::write(&included, "## Check\n\n```bash\nuptime\n```\n").unwrap();
+        let main = dir.join("main.sysadmin");
+        let content = "# Deploy\n\n@include common.sysadmin\n\n```bash\nuptime\n```\n";
+        std::fs::write(&main, content).unwrap();
+
+        let (expanded, map) = crate::parser::expand_includes_with_map(content, &main).unwrap();
+        let document = SysadminParser::parse(&expanded).unwrap();
+        let message = format!("step duplicates the step at {}:3", included.display());
+        assert_eq!(
+            validate_expanded(&expanded, &document, &map),
+            vec![Diagnostic::warning(5, message)]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_document_validate_skips_source_checks() {
+        let source = "# Deploy\n\n```bash\nls\n```\n\n```bash\nls\n```\n\n```\nplain\n```\n";
+        let document = SysadminParser::parse(source).unwrap();
+
+        assert_eq!(
+            document.validate(),
+            vec![Diagnostic::warning(7, "step duplicates the step at line 3")]
+        );
+        assert_eq!(lint(source).len(), 2);
+    }
+
+    #[test]
+    fn test_clean_runbook_has_no_diagnostics() {
+        let source = "# Deploy\n\n```bash\n./deploy.sh\n```\n";
+        assert!(lint(source).is_empty());
+    }
+
+    #[test]
+    fn test_missing_language_and_headerless_step() {
+        let source = "```bash\nls\n```\n\n# Later\n\n```\nplain\n```\n";
+        let diagnostics = lint(source);
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic::warning(1, "step is not under a section header"),
+                Diagnostic::warning(7, "code block has no language and will not run as a step"),
+            ]
+        );
+        assert!(!diagnostics.iter().any(Diagnostic::is_error));
+    }
+
+    #[test]
+    fn test_unclosed_and_empty_blocks_are_errors() {
+        let source = "# Steps\n\n```bash\n```\n\n```bash\necho never closed\n";
+        let diagnostics = lint(source);
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic::error(3, "code block is empty"),
+                Diagnostic::error(6, "code block is never closed"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_expected_output_regex() {
+        let source = "# Check\n\n```bash {expect=\"/[unclosed/\"}\necho hi\n```\n";
+        let diagnostics = lint(source);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+        assert_eq!(diagnostics[0].line_number, Some(3));
+    }
+
+    #[test]
+    fn test_invalid_retry_attributes() {
+        let source = "# Wait\n\n```bash {retries=3, retry-delay=5s}\ntrue\n```\n\n```bash {retries=-1, retry-delay=soon}\nexit 0\n```\n";
+        let diagnostics = lint(source);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.line_number == Some(7)));
+        assert!(diagnostics[0]
+            .message
+            .starts_with("Invalid retries value '-1'"));
+    }
+
+    #[test]
+    fn test_duplicate_steps_are_warnings() {
+        let source = "# Clean\n\n```bash\nrm -rf /tmp/build\n```\n\n```yaml\nkey: value\n```\n\n```bash\nrm  -rf /tmp/build\n```\n\n```yaml\nkey: value\n```\n\n```sh\nrm -rf /tmp/build\n```\n";
+        let diagnostics = lint(source);
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic::warning(11, "step duplicates the step at line 3"),
+                Diagnostic::warning(19, "step duplicates the step at line 3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_same_step_on_other_hosts_or_dirs_is_not_duplicate() {
+        let source = "# Deploy\n\n```bash host=web01\nmake install\n```\n\n```bash host=web02\nmake install\n```\n\n```bash dir=/srv/a\nmake install\n```\n\n```bash dir=/srv/b\nmake install\n```\n\n```bash dir=/srv/b\nmake install\n```\n";
+        assert_eq!(
+            lint(source),
+            vec![Diagnostic::warning(
+                19,
+                "step duplicates the step at line 15"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_longer_fence_contains_shorter_one() {
+        let source = "# Docs\n\n````markdown\n```\nnested\n```\n````\n";
+        assert!(lint(source).is_empty());
+    }
+}
@@ -1,6 +1,13 @@
 //! Integration tests for the sysadmin executor module
 
-use usr_bin_sysadmin::executor::InteractiveExecutor;
+// The original tests predate the clippy gate and are kept as written
+#![allow(unused_imports, unused_variables, unused_mut)]
+
+use std::time::{Duration, Instant};
+use usr_bin_sysadmin::executor::{
+    AuditEntry, AuditLog, BatchExecutor, Checkpoint, InteractiveExecutor, StepSelection,
+    TagFilter,
+};
 use usr_bin_sysadmin::parser::SysadminParser;
 use usr_bin_sysadmin::model::Document;
 
@@ -185,4 +192,429 @@ More text.
     assert_eq!(doc.sections.len(), 1);
     let code_blocks = doc.code_blocks();
     assert_eq!(code_blocks.len(), 1);
-}
\ No newline at end of file
+}
+#[test]
+fn test_executor_run_block_reports_exit_status() {
+    let doc = SysadminParser::parse("```bash\nexit 3\n```\n").unwrap();
+    let executor = InteractiveExecutor::new();
+
+    let status = executor.run_block(doc.code_blocks()[0]).unwrap();
+    assert_eq!(status.code(), Some(3));
+}
+
+#[test]
+fn test_executor_run_block_kills_step_after_timeout() {
+    let doc = SysadminParser::parse("```bash\nsleep 5\n```\n").unwrap();
+    let executor = InteractiveExecutor::new().with_timeout(Duration::from_millis(200));
+
+    let started = Instant::now();
+    let result = executor.run_block(doc.code_blocks()[0]);
+
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("timed out"));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_audit_log_appends_json_lines() {
+    let path = std::env::temp_dir().join(format!("sysadmin-audit-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    for step in 1..=2 {
+        let mut log = AuditLog::open(&path).unwrap();
+        log.record(AuditEntry {
+            timestamp: 0,
+            step,
+            section: Some("Backup".to_string()),
+            language: "bash".to_string(),
+            content: "ls /backups".to_string(),
+            exit_code: Some(0),
+            duration_ms: 12,
+            skipped: false,
+        })
+        .unwrap();
+    }
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("\"step\":1"));
+    assert!(lines[1].contains("\"step\":2"));
+    assert!(lines[1].contains("\"section\":\"Backup\""));
+    assert!(lines[1].contains("\"exit_code\":0"));
+}
+
+#[test]
+fn test_interrupted_run_leaves_checkpoint() {
+    let dir = std::env::temp_dir().join(format!("sysadmin-resume-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let runbook = dir.join("deploy.sysadmin");
+    let content = "```bash\ntrue\n```\n\n```bash\nexit 3\n```\n";
+    let doc = SysadminParser::parse(content).unwrap();
+
+    let mut executor = InteractiveExecutor::new()
+        .with_auto_run(true)
+        .with_checkpoint(Checkpoint::new(&runbook, content));
+    assert!(executor.execute(&doc).is_err());
+
+    let checkpoint = Checkpoint::load(&runbook).unwrap().unwrap();
+    assert_eq!(checkpoint.last_completed_step, 1);
+    assert!(checkpoint.matches(content));
+
+    // Finishing the remaining steps removes the state file
+    let mut executor = InteractiveExecutor::new()
+        .with_auto_run(true)
+        .with_selection(StepSelection::Only(1))
+        .with_checkpoint(checkpoint);
+    executor.execute(&doc).unwrap();
+    assert_eq!(Checkpoint::load(&runbook).unwrap(), None);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_executor_rejects_from_step_past_end() {
+    let doc = SysadminParser::parse("```bash\necho one\n```\n\n```bash\necho two\n```\n").unwrap();
+    let mut executor = InteractiveExecutor::new().with_selection(StepSelection::Range(3, 3));
+
+    let err = executor.execute(&doc).unwrap_err();
+    assert!(err.to_string().contains("step 3"));
+}
+
+#[test]
+fn test_executor_rejects_only_step_out_of_range() {
+    let doc = SysadminParser::parse("```bash\necho one\n```\n").unwrap();
+    let mut executor = InteractiveExecutor::new().with_selection(StepSelection::Only(2));
+
+    let err = executor.execute(&doc).unwrap_err();
+    assert!(err.to_string().contains("Cannot run step 2"));
+}
+
+#[test]
+fn test_batch_executor_runs_all_steps() {
+    let content = "# One\n\n```bash\ntrue\n```\n\n# Two\n\n```sh\nexit 0\n```\n";
+    let doc = SysadminParser::parse(content).unwrap();
+    let mut executor = BatchExecutor::new();
+
+    let report = executor.execute(&doc).unwrap();
+    assert!(report.is_success());
+    assert_eq!(report.exit_code(), 0);
+}
+
+#[test]
+fn test_batch_executor_stops_at_first_failure() {
+    let marker = std::env::temp_dir().join(format!("sysadmin-batch-{}", std::process::id()));
+    let _ = std::fs::remove_file(&marker);
+
+    let content = format!(
+        "```bash\nexit 4\n```\n\n```bash\ntouch {}\n```\n",
+        marker.display()
+    );
+    let doc = SysadminParser::parse(&content).unwrap();
+    let mut executor = BatchExecutor::new();
+
+    let report = executor.execute(&doc).unwrap();
+    assert_eq!(report.exit_code(), 4);
+    assert_eq!(report.failed_steps(), vec![1]);
+    assert!(!marker.exists(), "steps after a failure must not run");
+}
+
+#[test]
+fn test_batch_executor_retries_flaky_step() {
+    let counter = std::env::temp_dir().join(format!("sysadmin-retry-{}", std::process::id()));
+    let _ = std::fs::remove_file(&counter);
+
+    // Counts attempts in a file, succeeding once the count reaches `target`
+    let attempt = |target: u32| {
+        format!(
+            "n=$(cat {0} 2>/dev/null || echo 0); n=$((n+1)); echo $n > {0}; [ $n -ge {1} ]",
+            counter.display(),
+            target
+        )
+    };
+    let content = format!(
+        "```bash {{retries=2, retry-delay=10ms}}\n{}\n```\n\n```bash {{retries=1, retry-delay=10ms}}\n{}\n```\n",
+        attempt(3),
+        attempt(10)
+    );
+    let doc = SysadminParser::parse(&content).unwrap();
+    let mut executor = BatchExecutor::new().with_continue_on_error(true);
+
+    let report = executor.execute(&doc).unwrap();
+    assert_eq!(report.failed_steps(), vec![2]);
+    // Three attempts at step 1, then two at step 2
+    assert_eq!(std::fs::read_to_string(&counter).unwrap().trim(), "5");
+
+    std::fs::remove_file(&counter).unwrap();
+}
+
+#[test]
+fn test_batch_executor_continue_on_error_runs_every_step() {
+    let content = "```bash\nexit 2\n```\n\n```bash\ntrue\n```\n\n```bash\nexit 5\n```\n";
+    let doc = SysadminParser::parse(content).unwrap();
+    let mut executor = BatchExecutor::new().with_continue_on_error(true);
+
+    let report = executor.execute(&doc).unwrap();
+    assert_eq!(report.steps.len(), 3);
+    assert_eq!(report.succeeded(), 1);
+    assert_eq!(report.failed_steps(), vec![1, 3]);
+    assert_eq!(report.exit_code(), 2);
+}
+
+#[test]
+fn test_batch_executor_sets_step_environment() {
+    let content = "```bash\ntest \"$SYSADMIN_TEST_ENV\" = injected\n```\n";
+    let doc = SysadminParser::parse(content).unwrap();
+    let env = [("SYSADMIN_TEST_ENV".to_string(), "injected".to_string())].into();
+    let mut executor = BatchExecutor::new().with_env(env);
+
+    assert!(executor.execute(&doc).unwrap().is_success());
+}
+
+#[test]
+fn test_batch_executor_runs_step_in_working_dir() {
+    let dir = std::env::temp_dir().canonicalize().unwrap();
+    let content = format!(
+        "```bash {{dir={}}}\ntest \"$(pwd -P)\" = \"{}\"\n```\n",
+        dir.display(),
+        dir.display()
+    );
+    let doc = SysadminParser::parse(&content).unwrap();
+    let mut executor = BatchExecutor::new();
+
+    assert!(executor.execute(&doc).unwrap().is_success());
+}
+
+#[test]
+fn test_batch_executor_rejects_missing_working_dir() {
+    let doc = SysadminParser::parse("```bash {dir=/definitely/not/here}\ntrue\n```\n").unwrap();
+    let mut executor = BatchExecutor::new();
+
+    let err = executor.execute(&doc).unwrap_err();
+    assert!(err.to_string().contains("does not exist"));
+}
+
+#[test]
+fn test_batch_executor_checks_expected_output() {
+    let content = "```bash\necho 'status: active'\n```\n\n```output\nactive\n```\n\n```bash {expect=\"/^ok$/\"}\necho 'not ok'\n```\n";
+    let doc = SysadminParser::parse(content).unwrap();
+    assert_eq!(doc.step_count(), 2);
+    let mut executor = BatchExecutor::new().with_continue_on_error(true);
+
+    let report = executor.execute(&doc).unwrap();
+    assert_eq!(report.succeeded(), 1);
+    assert_eq!(report.failed_steps(), vec![2]);
+    assert!(report.steps[1].output_mismatch);
+    assert_eq!(report.exit_code(), 1);
+}
+
+#[test]
+fn test_batch_executor_checks_required_tools() {
+    let content = "---\nrequires: [sh, no-such-tool-for-sysadmin]\n---\n\n```bash\ntrue\n```\n";
+    let doc = SysadminParser::parse(content).unwrap();
+
+    let err = BatchExecutor::new().execute(&doc).unwrap_err();
+    assert!(err.to_string().contains("no-such-tool-for-sysadmin"));
+
+    let report = BatchExecutor::new()
+        .with_skip_checks(true)
+        .execute(&doc)
+        .unwrap();
+    assert!(report.is_success());
+}
+
+#[test]
+fn test_batch_executor_checks_step_dependencies() {
+    let content = "```bash {id=migrate}\ntrue\n```\n\n```bash {needs=migrate}\ntrue\n```\n";
+    let doc = SysadminParser::parse(content).unwrap();
+
+    let report = BatchExecutor::new().execute(&doc).unwrap();
+    assert!(report.is_success());
+
+    let report = BatchExecutor::new()
+        .with_selection(StepSelection::Only(2))
+        .execute(&doc)
+        .unwrap();
+    assert_eq!(report.failed_steps(), vec![2]);
+    assert_eq!(report.exit_code(), 1);
+
+    // Resuming after the prerequisite completed in an earlier run
+    let report = BatchExecutor::new()
+        .with_selection(StepSelection::Range(2, 2))
+        .with_succeeded_steps(vec![1])
+        .execute(&doc)
+        .unwrap();
+    assert!(report.is_success());
+}
+
+/// Run `content` interactively until its failing step, leaving a checkpoint,
+/// then run step 4 (which needs step 1) in batch mode from that checkpoint
+fn resume_needs_from_checkpoint(name: &str, selection: StepSelection, tags: TagFilter) -> bool {
+    let dir = std::env::temp_dir().join(format!("sysadmin-needs-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let runbook = dir.join("deploy.sysadmin");
+    let content = "```bash {id=migrate, tags=db}\ntrue\n```\n\n```bash\ntrue\n```\n\n```bash\nexit 3\n```\n\n```bash {needs=migrate}\ntrue\n```\n";
+    let doc = SysadminParser::parse(content).unwrap();
+
+    let mut executor = InteractiveExecutor::new()
+        .with_auto_run(true)
+        .with_selection(selection)
+        .with_tag_filter(tags)
+        .with_checkpoint(Checkpoint::new(&runbook, content));
+    assert!(executor.execute(&doc).is_err());
+    let checkpoint = Checkpoint::load(&runbook).unwrap().unwrap();
+    assert_eq!(checkpoint.last_completed_step, 2);
+
+    let report = BatchExecutor::new()
+        .with_selection(StepSelection::Only(4))
+        .with_succeeded_steps(checkpoint.succeeded_steps)
+        .execute(&doc)
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    report.is_success()
+}
+
+#[test]
+fn test_checkpoint_needs_ignores_skipped_steps() {
+    let all = TagFilter::default();
+    assert!(resume_needs_from_checkpoint("ran", StepSelection::All, all));
+
+    // Step 1 was skipped before step 2 succeeded
+    let skip_db = TagFilter::new(vec![], vec!["db".to_string()]);
+    let satisfied = resume_needs_from_checkpoint("skip", StepSelection::All, skip_db);
+    assert!(!satisfied);
+}
+
+#[test]
+fn test_checkpoint_needs_ignores_steps_before_from() {
+    let from_two = StepSelection::Range(2, 4);
+    let satisfied = resume_needs_from_checkpoint("from", from_two, TagFilter::default());
+    assert!(!satisfied);
+}
+
+#[test]
+fn test_batch_executor_continues_past_unmet_dependency() {
+    let content = "```bash {id=migrate}
+false
+```
+
+```bash {needs=migrate}
+true
+```
+
+```bash
+true
+```
+";
+    let doc = SysadminParser::parse(content).unwrap();
+
+    let report = BatchExecutor::new()
+        .with_continue_on_error(true)
+        .execute(&doc)
+        .unwrap();
+    assert_eq!(report.failed_steps(), vec![1, 2]);
+    assert_eq!(report.succeeded(), 1);
+}
+
+#[test]
+fn test_batch_executor_strict_shell_stops_at_first_failure() {
+    let content = "```bash\nfalse\ntrue\n```\n";
+    let doc = SysadminParser::parse(content).unwrap();
+
+    let report = BatchExecutor::new().execute(&doc).unwrap();
+    assert!(report.is_success());
+
+    let report = BatchExecutor::new()
+        .with_strict_shell(true)
+        .execute(&doc)
+        .unwrap();
+    assert!(!report.is_success());
+}
+
+#[test]
+fn test_batch_executor_filters_by_tag() {
+    let content = "```bash\ntrue\n```\n\n```bash {tags=rollback}\nexit 3\n```\n\n```bash {tags=\"rollback, slow\"}\nexit 4\n```\n";
+    let doc = SysadminParser::parse(content).unwrap();
+
+    let skip_rollback = TagFilter::new(vec![], vec!["rollback".to_string()]);
+    let report = BatchExecutor::new()
+        .with_tag_filter(skip_rollback)
+        .execute(&doc)
+        .unwrap();
+    assert_eq!(report.total_steps, 1);
+    assert!(report.is_success());
+
+    let rollback_not_slow = TagFilter::new(vec!["rollback".to_string()], vec!["slow".to_string()]);
+    let report = BatchExecutor::new()
+        .with_tag_filter(rollback_not_slow)
+        .execute(&doc)
+        .unwrap();
+    assert_eq!(report.total_steps, 1);
+    assert_eq!(report.exit_code(), 3);
+
+    let skipped: Vec<_> = report.steps.iter().map(|s| s.skipped).collect();
+    // The run stops at the failing step, before step 3
+    assert_eq!(skipped, vec![true, false]);
+    assert_eq!(report.steps[1].content, "exit 3");
+    assert!(report.steps[0].started_at.is_none());
+    assert!(report.steps[1].started_at.is_some());
+}
+
+#[test]
+fn test_batch_report_masks_redacted_secrets() {
+    let doc = SysadminParser::parse(
+        "```bash\ntrue --password=hunter2\n```\n\n```bash {tags=later}\necho password=hunter2\n```\n",
+    )
+    .unwrap();
+    let redactor =
+        usr_bin_sysadmin::model::Redactor::new(&[r"password=\S+".to_string()]).unwrap();
+    let mut executor = BatchExecutor::new()
+        .with_redactor(redactor)
+        .with_tag_filter(TagFilter::new(vec![], vec!["later".to_string()]));
+
+    let report = executor.execute(&doc).unwrap();
+    for step in &report.steps {
+        assert!(!step.content.contains("hunter2"), "{}", step.content);
+    }
+    assert!(report.steps[1].skipped);
+}
+
+#[test]
+fn test_batch_masks_secrets_printed_to_stderr() {
+    let path = std::env::temp_dir().join(format!("sysadmin-stderr-{}.txt", std::process::id()));
+    let file = std::fs::File::create(&path).unwrap();
+
+    let doc = SysadminParser::parse("```bash\necho password=hunter2 >&2\n```\n").unwrap();
+    let redactor =
+        usr_bin_sysadmin::model::Redactor::new(&[r"password=\S+".to_string()]).unwrap();
+    let mut executor = BatchExecutor::new()
+        .with_redactor(redactor)
+        .with_transcript(file);
+    assert!(executor.execute(&doc).unwrap().is_success());
+
+    let transcript = std::fs::read_to_string(&path).unwrap();
+    assert!(!transcript.contains("hunter2"), "{}", transcript);
+    assert!(transcript.lines().any(|line| line == "****"), "{}", transcript);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_batch_transcript_records_step_output() {
+    let path =
+        std::env::temp_dir().join(format!("sysadmin-transcript-{}.txt", std::process::id()));
+    let file = std::fs::File::create(&path).unwrap();
+
+    let doc = SysadminParser::parse("```bash\necho to-stdout\necho to-stderr >&2\n```\n").unwrap();
+    let mut executor = BatchExecutor::new().with_transcript(file);
+    assert!(executor.execute(&doc).unwrap().is_success());
+
+    let transcript = std::fs::read_to_string(&path).unwrap();
+    assert!(transcript.contains("echo to-stdout"), "{}", transcript);
+    assert!(transcript.lines().any(|line| line == "to-stdout"), "{}", transcript);
+    assert!(transcript.lines().any(|line| line == "to-stderr"), "{}", transcript);
+
+    std::fs::remove_file(&path).unwrap();
+}